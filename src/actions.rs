@@ -2,9 +2,9 @@ use crate::auth::Usuario;
 use crate::predicoes_module;
 use crate::otimizacao;
 use crate::dataset::Dataset; 
+use std::collections::HashMap;
 use std::path::Path;
 use crate::otimizacao::PedidoOtimizacao;
-use serde_json;
 
 pub async fn inserir_dados_coleta(
     usuario: &Usuario, 
@@ -28,7 +28,7 @@ pub async fn inserir_dados_coleta(
             println!("Aviso: Não foi possível carregar db.json ({}). Criando um novo.", err);
             Dataset::new()
         });
-    dataset.add_entry(tipo.clone(), quantidade, observacoes);
+    dataset.add_entry(tipo.clone(), quantidade, observacoes, Some(usuario.id));
 
     match dataset.save_to_file(db_path) {
         Ok(_) => {
@@ -64,7 +64,7 @@ pub async fn acessar_modulo_predicoes(usuario: &Usuario) -> Result<(), String> {
         ));
     }
     
-if let Err(e) = predicoes_module::run_prediction_module().await {
+if let Err(e) = predicoes_module::run_prediction_module(usuario.id).await {
         eprintln!("Erro no módulo de predição: {}", e);
         return Err("Falha ao executar o módulo de predição.".to_string());
     }
@@ -93,12 +93,17 @@ pub fn acessar_modulo_otimizacao(
             "ponto_B".to_string(), 
             "ponto_C".to_string()
         ],
+        pesos: None,
+        perfil_pesos: None,
+        risco_transbordo: HashMap::new(),
+        selecao_automatica: None,
     };
     println!("Simulando pedido para os pontos: {:?}", pedido_mock.pontos_a_visitar);
 
     let comparacao = otimizacao::executar_otimizacao_comparativa(
         estado,
-        &pedido_mock
+        &pedido_mock,
+        &otimizacao::ContextoPrioridade::default()
     );
 
     println!("\n--- ✅ Comparação de Otimização Concluída ---");
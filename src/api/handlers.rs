@@ -1,19 +1,37 @@
 use axum::{
-    extract::{Path, Query, State},
-    http::StatusCode,
-    response::Json,
+    extract::{
+        ws::{close_code, CloseFrame, Message, WebSocket, WebSocketUpgrade},
+        Path, Query, State,
+    },
+    http::{header, HeaderMap, StatusCode},
+    response::{
+        sse::{Event as SseEvent, KeepAlive, Sse},
+        IntoResponse, Json, Response,
+    },
 };
-use std::collections::HashMap;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use futures_util::stream::{self, Stream};
+use std::collections::{HashMap, HashSet};
+use std::convert::Infallible;
 use chrono::Utc;
+use serde::Serialize;
+use serde_json::json;
+use tokio::sync::broadcast::error::RecvError;
 
 use crate::auth::{Usuario, PerfilUsuario};
 use crate::api::routes::AppState;
 use crate::api::models::*;
-use crate::dataset::Dataset;
+use crate::coletas;
+use crate::importacao;
+use crate::dataset::{Dataset, VinculosColeta};
 use crate::modelo::ModeloML;
 use crate::predicao::Predicao;
-use crate::otimizacao::PedidoNovaDistancia;
 use crate::ia_api;
+use crate::emissoes::{self, TabelaFatores};
+use crate::relatorios;
+use crate::tipos_residuo::{self, Catalogo, ColetaNormalizada};
+use crate::unidades::{Leitura, UnidadeMedida};
+use crate::jobs::{EventoJob, Job, TipoJob};
 use std::path::Path as StdPath;
 
 // Helper para validar credenciais
@@ -109,6 +127,9 @@ pub async fn criar_usuario(
 
 // ========== COLETA ==========
 
+/// Maior página aceita em `GET /coleta`.
+const LIMITE_MAXIMO_COLETAS: usize = 1000;
+
 pub async fn inserir_coleta(
     State(app_state): State<AppState>,
     Json(payload): Json<InserirColetaRequest>,
@@ -123,11 +144,35 @@ pub async fn inserir_coleta(
         return Ok(Json(ApiResponse::error("Acesso negado".to_string())));
     }
 
+    let leitura = Leitura { quantidade: payload.quantidade, unidade: payload.unidade };
+    let coleta = match tipos_residuo::normalizar_coleta(&conn, &payload.tipo, leitura) {
+        Ok(coleta) => coleta,
+        Err(e) => return Ok(Json(ApiResponse::error(e))),
+    };
+
+    let ponto_id = payload.ponto_id.map(|p| p.trim().to_string());
+    if ponto_id.as_deref() == Some("") {
+        return Ok(Json(ApiResponse::error("Id do ponto não pode ser vazio".to_string())));
+    }
+    if let Err(e) = crate::otimizacao::validar_vinculos_coleta(&conn, ponto_id.as_deref(), payload.rota_id) {
+        return Ok(Json(ApiResponse::error(e)));
+    }
+
     let db_path = StdPath::new("data/db.json");
     let mut dataset = Dataset::load_from_file(db_path)
-        .unwrap_or_else(|_| Dataset::new());
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     
-    dataset.add_entry(payload.tipo.clone(), payload.quantidade, payload.observacoes);
+    dataset.add_entry_in_unit(
+        coleta.tipo,
+        coleta.leitura,
+        coleta.quantidade_kg,
+        payload.observacoes,
+        VinculosColeta {
+            usuario_id: Some(usuario.id),
+            ponto_id,
+            rota_id: payload.rota_id,
+        },
+    );
     
     dataset.save_to_file(db_path)
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
@@ -135,27 +180,368 @@ pub async fn inserir_coleta(
     Ok(Json(ApiResponse::success("Dados inseridos com sucesso".to_string())))
 }
 
+/// Período `de`/`ate` (AAAA-MM-DD, inclusivos e opcionais) da query string.
+fn periodo_da_query(
+    params: &HashMap<String, String>,
+) -> Result<(Option<chrono::NaiveDate>, Option<chrono::NaiveDate>), String> {
+    let mut periodo = [None, None];
+    for (limite, campo) in periodo.iter_mut().zip(["de", "ate"]) {
+        if let Some(valor) = params.get(campo) {
+            match chrono::NaiveDate::parse_from_str(valor, "%Y-%m-%d") {
+                Ok(data) => *limite = Some(data),
+                Err(_) => return Err(format!("Data inválida em '{}' (use AAAA-MM-DD)", campo)),
+            }
+        }
+    }
+    let [de, ate] = periodo;
+    if let (Some(de), Some(ate)) = (de, ate) {
+        if de > ate {
+            return Err("Período inválido: 'de' é posterior a 'ate'".to_string());
+        }
+    }
+    Ok((de, ate))
+}
+
+/// Lê o parâmetro `campo`, se presente; valor que não converte é erro.
+fn parametro<T: std::str::FromStr>(params: &HashMap<String, String>, campo: &str) -> Result<Option<T>, String> {
+    params
+        .get(campo)
+        .map(|valor| valor.trim().parse::<T>().map_err(|_| format!("Parâmetro '{}' inválido", campo)))
+        .transpose()
+}
+
+/// Filtros comuns a `GET /coleta` e `GET /coleta/agregado`, já restritos ao
+/// que `usuario` pode ver.
+fn filtro_coletas_da_query(
+    params: &HashMap<String, String>,
+    usuario: &Usuario,
+) -> Result<coletas::FiltroColetas, String> {
+    let (de, ate) = periodo_da_query(params)?;
+    let quantidade_min: Option<f32> = parametro(params, "quantidade_min")?;
+    let quantidade_max: Option<f32> = parametro(params, "quantidade_max")?;
+    if let (Some(min), Some(max)) = (quantidade_min, quantidade_max) {
+        if min > max {
+            return Err("Faixa inválida: 'quantidade_min' é maior que 'quantidade_max'".to_string());
+        }
+    }
+
+    let mut filtro = coletas::FiltroColetas {
+        tipo: params.get("tipo").cloned(),
+        de,
+        ate,
+        quantidade_min,
+        quantidade_max,
+        usuario_id: parametro(params, "usuario_id")?,
+        ponto_id: params.get("ponto_id").map(|p| p.trim().to_string()),
+        rota_id: parametro(params, "rota_id")?,
+        regiao: params.get("regiao").cloned(),
+    };
+    filtro.restringir_ao_usuario(usuario)?;
+    Ok(filtro)
+}
+
+/// Catálogo e região de cada ponto, usados pelos filtros de coletas.
+fn dados_consulta_coletas(app_state: &AppState) -> Result<(Catalogo, HashMap<String, String>), StatusCode> {
+    let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let catalogo = Catalogo::carregar(&conn).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let regiao_do_ponto = crate::otimizacao::listar_pontos(&conn, None)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .into_iter()
+        .filter_map(|p| Some((p.id, p.regiao?)))
+        .collect();
+    Ok((catalogo, regiao_do_ponto))
+}
+
+/// Coletas filtradas, ordenadas e paginadas. Filtros em
+/// `filtro_coletas_da_query`; `ordenar`, `ordem`, `cursor`, `limit`,
+/// `offset` e `unidade` controlam a página. Credenciais no cabeçalho
+/// `Authorization`.
 pub async fn listar_coletas(
+    State(app_state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> Result<Json<ApiResponse<ListaColetasResponse>>, StatusCode> {
+    let usuario = match usuario_do_cabecalho(&app_state, &headers)? {
+        Ok(u) => u,
+        Err(e) => return Ok(Json(ApiResponse::error(e))),
+    };
+    let filtro = match filtro_coletas_da_query(&params, &usuario) {
+        Ok(filtro) => filtro,
+        Err(e) => return Ok(Json(ApiResponse::error(e))),
+    };
+    let unidade = match params.get("unidade").map(|u| UnidadeMedida::try_from(u.as_str())) {
+        Some(Ok(unidade)) => Some(unidade),
+        Some(Err(e)) => return Ok(Json(ApiResponse::error(e))),
+        None => None,
+    };
+    let campo = match params.get("ordenar").map(|c| coletas::CampoOrdenacao::try_from(c.as_str())) {
+        Some(Ok(campo)) => campo,
+        Some(Err(e)) => return Ok(Json(ApiResponse::error(e))),
+        None => coletas::CampoOrdenacao::default(),
+    };
+    let decrescente = match params.get("ordem").map(|o| o.trim()) {
+        None | Some("asc") => false,
+        Some("desc") => true,
+        Some(outro) => {
+            return Ok(Json(ApiResponse::error(format!("Ordem inválida: '{}' (use asc ou desc)", outro))))
+        }
+    };
+
+    // Paginação
+    let (limit, offset) = match (parametro::<usize>(&params, "limit"), parametro::<usize>(&params, "offset")) {
+        (Ok(limit), Ok(offset)) => (limit.unwrap_or(100).min(LIMITE_MAXIMO_COLETAS), offset.unwrap_or(0)),
+        (Err(e), _) | (_, Err(e)) => return Ok(Json(ApiResponse::error(e))),
+    };
+
+    let db_path = StdPath::new("data/db.json");
+    let dataset = Dataset::load_from_file(db_path)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let (catalogo, regiao_do_ponto) = dados_consulta_coletas(&app_state)?;
+    let contexto = coletas::ContextoConsulta { catalogo: &catalogo, regiao_do_ponto };
+
+    let filtradas = contexto.filtrar(&dataset, &filtro);
+    let ordenacao = coletas::Ordenacao { campo, decrescente };
+    let cursor = params.get("cursor").map(|c| c.as_str());
+    let pagina = match coletas::paginar(filtradas, ordenacao, cursor, offset, limit) {
+        Ok(pagina) => pagina,
+        Err(e) => return Ok(Json(ApiResponse::error(e))),
+    };
+
+    let itens = pagina
+        .coletas
+        .into_iter()
+        .map(|coleta| ColetaResponse {
+            convertida: unidade.and_then(|u| catalogo.converter(&coleta.tipo, coleta.quantidade, u)),
+            coleta: coleta.clone(),
+        })
+        .collect();
+
+    Ok(Json(ApiResponse::success(ListaColetasResponse {
+        coletas: itens,
+        total: pagina.total,
+        next: pagina.next,
+    })))
+}
+
+/// Soma, média e contagem por período (`periodo=dia|semana|mes`, padrão
+/// dia) e tipo, com os mesmos filtros de `listar_coletas`.
+pub async fn agregar_coletas(
+    State(app_state): State<AppState>,
     Query(params): Query<HashMap<String, String>>,
-) -> Result<Json<ApiResponse<Vec<crate::dataset::WasteEntry>>>, StatusCode> {
+    headers: HeaderMap,
+) -> Result<Json<ApiResponse<Vec<coletas::GrupoColetas>>>, StatusCode> {
+    let usuario = match usuario_do_cabecalho(&app_state, &headers)? {
+        Ok(u) => u,
+        Err(e) => return Ok(Json(ApiResponse::error(e))),
+    };
+    let filtro = match filtro_coletas_da_query(&params, &usuario) {
+        Ok(filtro) => filtro,
+        Err(e) => return Ok(Json(ApiResponse::error(e))),
+    };
+    let periodo = match params.get("periodo").map(|p| coletas::PeriodoAgregacao::try_from(p.as_str())) {
+        Some(Ok(periodo)) => periodo,
+        Some(Err(e)) => return Ok(Json(ApiResponse::error(e))),
+        None => coletas::PeriodoAgregacao::Dia,
+    };
+
     let db_path = StdPath::new("data/db.json");
     let dataset = Dataset::load_from_file(db_path)
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let (catalogo, regiao_do_ponto) = dados_consulta_coletas(&app_state)?;
+    let contexto = coletas::ContextoConsulta { catalogo: &catalogo, regiao_do_ponto };
+
+    let filtradas = contexto.filtrar(&dataset, &filtro);
+    Ok(Json(ApiResponse::success(coletas::agregar(&contexto, &filtradas, periodo))))
+}
+
+pub async fn corrigir_coleta(
+    State(app_state): State<AppState>,
+    Path(id): Path<u64>,
+    Json(payload): Json<CorrigirColetaRequest>,
+) -> Result<Json<ApiResponse<coletas::CorrecaoColeta>>, StatusCode> {
+    let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let usuario = match validar_usuario(&conn, &payload.nome, &payload.senha) {
+        Ok(u) => u,
+        Err(e) => return Ok(Json(ApiResponse::error(format!("Credenciais inválidas: {}", e)))),
+    };
+
+    if !usuario.pode_inserir_dados() {
+        return Ok(Json(ApiResponse::error("Acesso negado".to_string())));
+    }
+
+    let catalogo = Catalogo::carregar(&conn).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let db_path = StdPath::new("data/db.json");
+    let mut dataset = Dataset::load_from_file(db_path)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    let mut entries = dataset.entries;
+    let mut correcao = match coletas::corrigir_coleta(
+        &mut dataset,
+        &catalogo,
+        &usuario,
+        id,
+        &payload.alteracao,
+        &payload.motivo,
+    ) {
+        Ok(c) => c,
+        Err(e) => return Ok(Json(ApiResponse::error(e))),
+    };
+
+    salvar_com_historico(&conn, &dataset, &mut correcao)?;
+    Ok(Json(ApiResponse::success(correcao)))
+}
+
+pub async fn remover_coleta(
+    State(app_state): State<AppState>,
+    Path(id): Path<u64>,
+    Json(payload): Json<RemoverColetaRequest>,
+) -> Result<Json<ApiResponse<coletas::CorrecaoColeta>>, StatusCode> {
+    let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let usuario = match validar_usuario(&conn, &payload.nome, &payload.senha) {
+        Ok(u) => u,
+        Err(e) => return Ok(Json(ApiResponse::error(format!("Credenciais inválidas: {}", e)))),
+    };
 
-    // Filtrar por tipo se fornecido
-    if let Some(tipo) = params.get("tipo") {
-        entries.retain(|e| e.tipo.eq_ignore_ascii_case(tipo));
+    if !usuario.pode_inserir_dados() {
+        return Ok(Json(ApiResponse::error("Acesso negado".to_string())));
     }
 
-    // Paginação
-    let limit: usize = params.get("limit").and_then(|s| s.parse().ok()).unwrap_or(100);
-    let offset: usize = params.get("offset").and_then(|s| s.parse().ok()).unwrap_or(0);
-    
-    let paginated = entries.into_iter().skip(offset).take(limit).collect();
+    let db_path = StdPath::new("data/db.json");
+    let mut dataset = Dataset::load_from_file(db_path)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let mut correcao = match coletas::remover_coleta(&mut dataset, &usuario, id, &payload.motivo) {
+        Ok(c) => c,
+        Err(e) => return Ok(Json(ApiResponse::error(e))),
+    };
+
+    salvar_com_historico(&conn, &dataset, &mut correcao)?;
+    Ok(Json(ApiResponse::success(correcao)))
+}
+
+/// Grava o histórico e o dataset juntos: se o arquivo não puder ser salvo,
+/// a correção não fica registrada.
+fn salvar_com_historico(
+    conn: &rusqlite::Connection,
+    dataset: &Dataset,
+    correcao: &mut coletas::CorrecaoColeta,
+) -> Result<(), StatusCode> {
+    let tx = conn.unchecked_transaction().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    coletas::registrar_correcao(&tx, correcao).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    dataset.save_to_file(StdPath::new("data/db.json"))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    tx.commit().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+/// Histórico de correções de uma coleta, com credenciais no cabeçalho
+/// `Authorization`.
+pub async fn historico_coleta(
+    State(app_state): State<AppState>,
+    Path(id): Path<u64>,
+    headers: HeaderMap,
+) -> Result<Json<ApiResponse<Vec<coletas::CorrecaoColeta>>>, StatusCode> {
+    let usuario = match usuario_do_cabecalho(&app_state, &headers)? {
+        Ok(u) => u,
+        Err(e) => return Ok(Json(ApiResponse::error(e))),
+    };
+
+    let dataset = Dataset::load_from_file(StdPath::new("data/db.json"))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if let Err(e) = coletas::verificar_acesso_historico(&dataset, &usuario, id) {
+        return Ok(Json(ApiResponse::error(e)));
+    }
+
+    let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let correcoes = coletas::listar_correcoes(&conn, id).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Json(ApiResponse::success(correcoes)))
+}
+
+pub async fn importar_coletas(
+    State(app_state): State<AppState>,
+    Json(payload): Json<ImportarColetasRequest>,
+) -> Result<Json<ApiResponse<importacao::RelatorioImportacao>>, StatusCode> {
+    let usuario = {
+        let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        match validar_usuario(&conn, &payload.nome, &payload.senha) {
+            Ok(u) => u,
+            Err(e) => return Ok(Json(ApiResponse::error(format!("Credenciais inválidas: {}", e)))),
+        }
+    };
+
+    if !usuario.pode_importar_coletas() {
+        return Ok(Json(ApiResponse::error("Acesso negado".to_string())));
+    }
+
+    let job = app_state.jobs.criar(TipoJob::Importacao, usuario.id);
+    let tarefa = importar_fora_do_runtime(app_state.clone(), usuario.id, payload.pedido);
+    match app_state.jobs.executar(&job, tarefa).await {
+        Ok(relatorio) => Ok(Json(ApiResponse::success(relatorio))),
+        Err(e) => Ok(Json(ApiResponse::error(e))),
+    }
+}
+
+/// Roda `processar_importacao` numa thread de bloqueio, dentro do span do
+/// job: ela lê o CSV inteiro segurando o Mutex do banco, o que travaria uma
+/// thread do runtime.
+async fn importar_fora_do_runtime(
+    app_state: AppState,
+    usuario_id: u32,
+    pedido: importacao::PedidoImportacao,
+) -> Result<importacao::RelatorioImportacao, String> {
+    let span = tracing::Span::current();
+    tokio::task::spawn_blocking(move || span.in_scope(|| processar_importacao(&app_state, usuario_id, &pedido)))
+        .await
+        .map_err(|_| "Importação interrompida por uma falha interna".to_string())?
+}
+
+/// Importa dentro de uma transação: se o dataset não puder ser salvo, nem a
+/// importação nem os hashes das linhas ficam registrados.
+fn processar_importacao(
+    app_state: &AppState,
+    usuario_id: u32,
+    pedido: &importacao::PedidoImportacao,
+) -> Result<importacao::RelatorioImportacao, String> {
+    let conn = app_state
+        .auth
+        .conn
+        .lock()
+        .map_err(|_| "Banco de dados indisponível".to_string())?;
+
+    tracing::info!(progresso = 5, "Carregando catálogo e coletas");
+    let catalogo = Catalogo::carregar(&conn).map_err(|e| format!("Erro ao carregar catálogo: {}", e))?;
+    let db_path = StdPath::new("data/db.json");
+    let mut dataset = Dataset::load_from_file(db_path).map_err(|e| format!("Erro ao carregar coletas: {}", e))?;
+
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("Erro ao acessar banco de dados: {}", e))?;
+    let relatorio = importacao::importar_coletas(&tx, &mut dataset, &catalogo, usuario_id, pedido)?;
+    if relatorio.importadas > 0 && !relatorio.simulacao {
+        dataset
+            .save_to_file(db_path)
+            .map_err(|e| format!("Erro ao salvar coletas: {}", e))?;
+    }
+    tx.commit().map_err(|e| format!("Erro ao registrar importação: {}", e))?;
+
+    Ok(relatorio)
+}
 
-    Ok(Json(ApiResponse::success(paginated)))
+/// Importações já gravadas, com credenciais no cabeçalho `Authorization`
+/// (Técnico ou Administrador).
+pub async fn listar_importacoes_coletas(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<ApiResponse<Vec<importacao::ImportacaoColetas>>>, StatusCode> {
+    let usuario = match usuario_do_cabecalho(&app_state, &headers)? {
+        Ok(u) => u,
+        Err(e) => return Ok(Json(ApiResponse::error(e))),
+    };
+    if !usuario.pode_importar_coletas() {
+        return Ok(Json(ApiResponse::error("Acesso negado".to_string())));
+    }
+
+    let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let importacoes = importacao::listar_importacoes(&conn).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Json(ApiResponse::success(importacoes)))
 }
 
 // ========== PRÉ-PROCESSAMENTO ==========
@@ -164,18 +550,29 @@ pub async fn executar_preprocessamento(
     State(app_state): State<AppState>,
     Json(creds): Json<AuthCredentials>,
 ) -> Result<Json<ApiResponse<String>>, StatusCode> {
-    let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    let usuario = match validar_usuario(&conn, &creds.nome, &creds.senha) {
-        Ok(u) => u,
-        Err(e) => return Ok(Json(ApiResponse::error(format!("Credenciais inválidas: {}", e)))),
+    let usuario = {
+        let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        match validar_usuario(&conn, &creds.nome, &creds.senha) {
+            Ok(u) => u,
+            Err(e) => return Ok(Json(ApiResponse::error(format!("Credenciais inválidas: {}", e)))),
+        }
     };
 
     if !usuario.pode_pre_processar() {
         return Ok(Json(ApiResponse::error("Acesso negado".to_string())));
     }
 
+    let job = app_state.jobs.criar(TipoJob::Preprocessamento, usuario.id);
+    match app_state.jobs.executar(&job, processar_preprocessamento()).await {
+        Ok(mensagem) => Ok(Json(ApiResponse::success(mensagem))),
+        Err(e) => Ok(Json(ApiResponse::error(e))),
+    }
+}
+
+async fn processar_preprocessamento() -> Result<String, String> {
     // Simular pré-processamento
-    Ok(Json(ApiResponse::success("Pré-processamento executado com sucesso".to_string())))
+    tracing::info!(progresso = 50, "Pré-processando dados");
+    Ok("Pré-processamento executado com sucesso".to_string())
 }
 
 // ========== PREDIÇÕES ==========
@@ -184,11 +581,11 @@ pub async fn executar_predicao(
     State(app_state): State<AppState>,
     Json(payload): Json<PredicaoRequest>,
 ) -> Result<Json<ApiResponse<crate::api::models::PredicaoResponse>>, StatusCode> {
-    // Validar usuário e liberar o guard antes de qualquer await
-    let usuario = {
+    // Validar usuário e tipo e liberar o guard antes de qualquer await
+    let (usuario, coleta) = {
         let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
         match validar_usuario(&conn, &payload.nome, &payload.senha) {
-            Ok(u) => u,
+            Ok(u) => (u, coleta_da_predicao(&conn, &payload)),
             Err(e) => return Ok(Json(ApiResponse::error(format!("Credenciais inválidas: {}", e)))),
         }
     };
@@ -196,55 +593,110 @@ pub async fn executar_predicao(
     if !usuario.pode_acessar_predicoes() {
         return Ok(Json(ApiResponse::error("Acesso negado".to_string())));
     }
+    let coleta = match coleta {
+        Ok(coleta) => coleta,
+        Err(e) => return Ok(Json(ApiResponse::error(e))),
+    };
 
-    let db_path = StdPath::new("data/db.json");
-    let mut dataset = Dataset::load_from_file(db_path)
-        .unwrap_or_else(|_| Dataset::new());
+    let job = app_state.jobs.criar(TipoJob::Predicao, usuario.id);
+    let tarefa = processar_predicao(app_state.clone(), payload, coleta, usuario.id);
+    match app_state.jobs.executar(&job, tarefa).await {
+        Ok(response) => Ok(Json(ApiResponse::success(response))),
+        Err(e) => Ok(Json(ApiResponse::error(e))),
+    }
+}
 
-    dataset.add_entry(payload.tipo.clone(), payload.quantidade, payload.observacoes.clone());
-    dataset.save_to_file(db_path)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+fn coleta_da_predicao(conn: &rusqlite::Connection, payload: &PredicaoRequest) -> Result<ColetaNormalizada, String> {
+    let leitura = Leitura { quantidade: payload.quantidade, unidade: payload.unidade };
+    tipos_residuo::normalizar_coleta(conn, &payload.tipo, leitura)
+}
 
+/// Treino, predição e análise com IA. O progresso sai nos eventos de
+/// tracing com o campo `progresso`, lidos pela `CamadaJobs`.
+async fn processar_predicao(
+    app_state: AppState,
+    payload: PredicaoRequest,
+    coleta: ColetaNormalizada,
+    usuario_id: u32,
+) -> Result<crate::api::models::PredicaoResponse, String> {
+    tracing::info!(progresso = 10, "Carregando dataset");
+    // O lock do banco serializa as gravações de data/db.json com inserções,
+    // correções e importações; é solto antes de qualquer await.
+    let dataset = {
+        let _conn = app_state.auth.conn.lock().map_err(|_| "Erro ao acessar banco de dados".to_string())?;
+        let db_path = StdPath::new("data/db.json");
+        let mut dataset = Dataset::load_from_file(db_path)
+            .map_err(|e| format!("Erro ao carregar coletas: {}", e))?;
+
+        dataset.add_entry_in_unit(
+            coleta.tipo.clone(),
+            coleta.leitura,
+            coleta.quantidade_kg,
+            payload.observacoes.clone(),
+            VinculosColeta { usuario_id: Some(usuario_id), ..Default::default() },
+        );
+        dataset.save_to_file(db_path)
+            .map_err(|e| format!("Erro ao salvar dataset: {}", e))?;
+        dataset
+    };
+
+    tracing::info!(progresso = 30, "Treinando modelo");
     let mut modelo = ModeloML::new("ModeloSimuladoReciclagem");
     modelo.treinar(&dataset);
     let _ = modelo.salvar("output/modelo.json");
 
+    tracing::info!(progresso = 60, "Gerando predição");
     let predicao: Predicao = modelo.prever(&dataset);
     let _ = predicao.exportar();
 
-    // Gerar análise com IA (agora podemos fazer await porque o guard foi liberado)
-    let prompt = format!(
-        "O usuário coletou {:.3} kg de {}. Considerando os dados históricos, forneça uma BREVE previsão ilustrativa sobre impacto ambiental e tendências. Responda em até 50 palavras.",
-        payload.quantidade, payload.tipo
-    );
+    // Gerar análise com IA (nenhum guard do banco é mantido aqui)
+    tracing::info!(progresso = 80, "Gerando análise com IA");
+    let demanda = crate::otimizacao::demanda_por_regiao(&app_state.otimizacao, None);
+    let (template, catalogo, fatores) = {
+        let conn = app_state.auth.conn.lock().map_err(|_| "Erro ao acessar banco de dados".to_string())?;
+        let template = ia_api::carregar_template(&conn, ia_api::ANALISE_PREDICAO, payload.idioma)?;
+        let catalogo = Catalogo::carregar(&conn)
+            .map_err(|e| format!("Erro ao carregar catálogo: {}", e))?;
+        let fatores = TabelaFatores::carregar(&conn)
+            .map_err(|e| format!("Erro ao carregar fatores de emissão: {}", e))?;
+        (template, catalogo, fatores)
+    };
+    let estatisticas = ia_api::calcular_estatisticas(&dataset, &catalogo, &fatores, demanda);
+    let prompt = ia_api::montar_prompt(&template, &coleta.tipo, coleta.quantidade_kg, &estatisticas, &predicao);
+
+    let ia = &app_state.ia;
+    let analise = match ia.gerar(&prompt).await {
+        Ok(texto) => ia_api::interpretar_resposta(&texto)
+            .map_err(|e| format!("resposta da IA inválida: {}", e)),
+        Err(e) => Err(format!("IA indisponível: {}", e)),
+    };
+    let mut analise = analise.unwrap_or_else(|motivo| {
+        tracing::warn!("⚠️ Análise baseada nas estatísticas ({}): {}", ia.descricao(), motivo);
+        ia_api::analise_de_fallback(&coleta.tipo, coleta.quantidade_kg, &estatisticas, &catalogo, template.idioma, motivo)
+    });
+    analise.template = Some(template.usado());
 
-    let analise_ia = ia_api::gerar_resposta_preditiva(&prompt).await
-        .unwrap_or_else(|_| "Erro ao gerar análise com IA".to_string());
+    let registro = ia_api::RegistroAnalise {
+        registrado_em: Utc::now().to_rfc3339(),
+        usuario_id,
+        tipo: coleta.tipo.clone(),
+        quantidade_kg: coleta.quantidade_kg,
+        predicao_em: predicao.timestamp.clone(),
+        analise: analise.clone(),
+    };
+    if let Err(e) = ia_api::registrar_analise(StdPath::new("output/analises_ia.jsonl"), &registro) {
+        tracing::warn!("⚠️ Erro ao gravar a análise: {}", e);
+    }
 
-    let fator = co2_factor(&payload.tipo);
     let response = crate::api::models::PredicaoResponse {
         predicao,
-        analise_ia,
-        co2_estimado: crate::api::models::Co2Estimado {
-            tipo: payload.tipo,
-            quantidade: payload.quantidade,
-            fator,
-            co2_evitado: payload.quantidade * fator,
-        },
+        analise_ia: analise.resumo.clone(),
+        analise,
+        estatisticas,
+        co2_estimado: fatores.estimar(&coleta.tipo, coleta.quantidade_kg, Utc::now().date_naive()),
     };
 
-    Ok(Json(ApiResponse::success(response)))
-}
-
-fn co2_factor(tipo: &str) -> f32 {
-    match tipo.to_lowercase().as_str() {
-        "plastico" | "plástico" => 2.0,
-        "papel" => 1.2,
-        "vidro" => 0.6,
-        "metal" => 3.0,
-        "organico" | "orgânico" => 0.3,
-        _ => 1.0,
-    }
+    Ok(response)
 }
 
 // ========== OTIMIZAÇÃO ==========
@@ -253,24 +705,111 @@ pub async fn otimizar_rota(
     State(app_state): State<AppState>,
     Json(payload): Json<crate::api::models::OtimizarRotaRequest>,
 ) -> Result<Json<ApiResponse<crate::otimizacao::ComparacaoOtimizacao>>, StatusCode> {
-    let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    let usuario = match validar_usuario(&conn, &payload.nome, &payload.senha) {
-        Ok(u) => u,
-        Err(e) => return Ok(Json(ApiResponse::error(format!("Credenciais inválidas: {}", e)))),
+    let usuario = {
+        let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        match validar_usuario(&conn, &payload.nome, &payload.senha) {
+            Ok(u) => u,
+            Err(e) => return Ok(Json(ApiResponse::error(format!("Credenciais inválidas: {}", e)))),
+        }
     };
 
     if !usuario.pode_otimizar_rotas() {
         return Ok(Json(ApiResponse::error("Acesso negado".to_string())));
     }
 
-    let comparacao = crate::otimizacao::executar_otimizacao_comparativa(&app_state.otimizacao, &payload.pedido);
-    Ok(Json(ApiResponse::success(comparacao)))
+    let job = app_state.jobs.criar(TipoJob::Otimizacao, usuario.id);
+    let estado = app_state.clone();
+    let tarefa = async move { processar_otimizacao(&estado, usuario.id, payload) };
+    match app_state.jobs.executar(&job, tarefa).await {
+        Ok(comparacao) => Ok(Json(ApiResponse::success(comparacao))),
+        Err(e) => Ok(Json(ApiResponse::error(e))),
+    }
 }
 
-pub async fn adicionar_distancia(
+fn processar_otimizacao(
+    app_state: &AppState,
+    usuario_id: u32,
+    payload: crate::api::models::OtimizarRotaRequest,
+) -> Result<crate::otimizacao::ComparacaoOtimizacao, String> {
+    let conn = app_state
+        .auth
+        .conn
+        .lock()
+        .map_err(|_| "Banco de dados indisponível".to_string())?;
+
+    tracing::info!(progresso = 10, "Selecionando pontos");
+    let mut pedido = payload.pedido;
+    let selecionados = crate::otimizacao::aplicar_selecao_automatica(&conn, &mut pedido)?;
+
+    tracing::info!(progresso = 30, "Montando contexto de prioridade");
+    let contexto = crate::otimizacao::montar_contexto_prioridade(&conn, &pedido)?;
+
+    tracing::info!(progresso = 50, "Comparando estratégias");
+    let mut comparacao = crate::otimizacao::executar_otimizacao_comparativa(
+        &app_state.otimizacao,
+        &pedido,
+        &contexto,
+    );
+    comparacao.pontos_selecionados_automaticamente = selecionados;
+
+    tracing::info!(progresso = 90, "Registrando rota");
+    let id = crate::otimizacao::registrar_rota(&conn, usuario_id, &pedido, &comparacao, payload.estrategia)
+        .map_err(|e| format!("Erro ao registrar rota: {}", e))?;
+    comparacao.rota_id = Some(id);
+
+    Ok(comparacao)
+}
+
+pub async fn listar_rotas_otimizadas(
     State(app_state): State<AppState>,
-    Json(payload): Json<crate::api::models::AdicionarDistanciaRequest>,
-) -> Result<Json<ApiResponse<String>>, StatusCode> {
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> Result<Json<ApiResponse<Vec<crate::otimizacao::ResumoRota>>>, StatusCode> {
+    if let Err(e) = otimizador_do_cabecalho(&app_state, &headers)? {
+        return Ok(Json(ApiResponse::error(e)));
+    }
+
+    let status = match params.get("status") {
+        Some(s) => match crate::otimizacao::StatusRota::try_from(s.as_str()) {
+            Ok(status) => Some(status),
+            Err(e) => return Ok(Json(ApiResponse::error(e))),
+        },
+        None => None,
+    };
+
+    // Paginação
+    let limit: usize = params.get("limit").and_then(|s| s.parse().ok()).unwrap_or(100);
+    let offset: usize = params.get("offset").and_then(|s| s.parse().ok()).unwrap_or(0);
+
+    let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let rotas = crate::otimizacao::listar_rotas(&conn, status, limit, offset)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(ApiResponse::success(rotas)))
+}
+
+pub async fn buscar_rota_otimizada(
+    State(app_state): State<AppState>,
+    Path(id): Path<i64>,
+    headers: HeaderMap,
+) -> Result<Json<ApiResponse<crate::otimizacao::RotaArmazenada>>, StatusCode> {
+    if let Err(e) = otimizador_do_cabecalho(&app_state, &headers)? {
+        return Ok(Json(ApiResponse::error(e)));
+    }
+
+    let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    match crate::otimizacao::buscar_rota(&conn, id) {
+        Ok(Some(rota)) => Ok(Json(ApiResponse::success(rota))),
+        Ok(None) => Ok(Json(ApiResponse::error("Rota não encontrada".to_string()))),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+pub async fn atualizar_status_rota(
+    State(app_state): State<AppState>,
+    Path(id): Path<i64>,
+    Json(payload): Json<crate::api::models::AtualizarStatusRotaRequest>,
+) -> Result<Json<ApiResponse<crate::otimizacao::RotaArmazenada>>, StatusCode> {
     let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     let usuario = match validar_usuario(&conn, &payload.nome, &payload.senha) {
         Ok(u) => u,
@@ -281,43 +820,790 @@ pub async fn adicionar_distancia(
         return Ok(Json(ApiResponse::error("Acesso negado".to_string())));
     }
 
-    crate::otimizacao::alimentar_distancia(&app_state.otimizacao, payload.distancia.clone());
-    
-    // Adicionar rota reversa
-    let reverso = PedidoNovaDistancia {
-        origem: payload.distancia.destino,
-        destino: payload.distancia.origem,
-        custo: payload.distancia.custo,
-    };
-    crate::otimizacao::alimentar_distancia(&app_state.otimizacao, reverso);
+    match crate::otimizacao::atualizar_status_rota(
+        &conn,
+        id,
+        payload.status,
+        usuario.id,
+        payload.observacao.as_deref(),
+        payload.veiculo_id.as_deref(),
+    ) {
+        Ok(rota) => {
+            publicar_ultimo_status(&app_state, &rota);
+            Ok(Json(ApiResponse::success(rota)))
+        }
+        Err(e) => Ok(Json(ApiResponse::error(e))),
+    }
+}
 
-    Ok(Json(ApiResponse::success("Distância adicionada com sucesso".to_string())))
+fn publicar_ultimo_status(app_state: &AppState, rota: &crate::otimizacao::RotaArmazenada) {
+    if let Some(evento) = rota.historico.last() {
+        app_state.otimizacao.publicar_evento(crate::otimizacao::EventoRastreamento::Status {
+            rota_id: rota.id,
+            veiculo_id: rota.veiculo_id.clone(),
+            evento: evento.clone(),
+        });
+    }
 }
 
-pub async fn atualizar_previsao_demanda(
+pub async fn registrar_checkin_rota(
     State(app_state): State<AppState>,
-    Json(payload): Json<crate::api::models::AtualizarPrevisaoRequest>,
-) -> Result<Json<ApiResponse<String>>, StatusCode> {
+    Path(id): Path<i64>,
+    Json(mut payload): Json<crate::api::models::CheckinRotaRequest>,
+) -> Result<Json<ApiResponse<crate::otimizacao::CheckinParada>>, StatusCode> {
     let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     let usuario = match validar_usuario(&conn, &payload.nome, &payload.senha) {
         Ok(u) => u,
         Err(e) => return Ok(Json(ApiResponse::error(format!("Credenciais inválidas: {}", e)))),
     };
 
-    if !usuario.pode_otimizar_rotas() {
+    if !usuario.pode_inserir_dados() {
         return Ok(Json(ApiResponse::error("Acesso negado".to_string())));
     }
 
-    crate::otimizacao::alimentar_previsao(&app_state.otimizacao, payload.previsao);
-    Ok(Json(ApiResponse::success("Previsão atualizada com sucesso".to_string())))
-}
+    // Tipos pelo código do catálogo: "PET" e "plastico" somam no mesmo tipo
+    let catalogo = Catalogo::carregar(&conn).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let mut coletado_kg = HashMap::new();
+    for (tipo, quantidade) in &payload.checkin.coletado_kg {
+        match catalogo.exigir(tipo) {
+            Ok(codigo) => *coletado_kg.entry(codigo).or_insert(0.0) += quantidade,
+            Err(e) => return Ok(Json(ApiResponse::error(e))),
+        }
+    }
+    payload.checkin.coletado_kg = coletado_kg;
 
-// ========== USUÁRIOS ==========
+    let status_antes = crate::otimizacao::buscar_rota(&conn, id)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .map(|rota| rota.status);
 
-pub async fn listar_usuarios(
-    State(app_state): State<AppState>,
-    Json(creds): Json<AuthCredentials>,
-) -> Result<Json<ApiResponse<Vec<UsuarioResponse>>>, StatusCode> {
+    // Check-in, mudança de status e coletas no dataset entram juntos: se o
+    // arquivo não puder ser salvo, a transação é desfeita
+    let tx = conn.unchecked_transaction().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let checkin = match crate::otimizacao::registrar_checkin(&tx, id, &payload.checkin, usuario.id) {
+        Ok(c) => c,
+        Err(e) => return Ok(Json(ApiResponse::error(e))),
+    };
+
+    // O que foi coletado na parada também entra no dataset de coletas
+    if !checkin.coletado_kg.is_empty() {
+        let chegada = chrono::DateTime::parse_from_rfc3339(&checkin.chegada_em)
+            .map(|d| d.with_timezone(&Utc))
+            .unwrap_or_else(|_| Utc::now());
+
+        let db_path = StdPath::new("data/db.json");
+        let mut dataset = Dataset::load_from_file(db_path)
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        for (tipo, quantidade) in &checkin.coletado_kg {
+            dataset.add_entry_at_point(
+                tipo.clone(),
+                *quantidade,
+                Some(format!("Check-in da rota {}", id)),
+                chegada,
+                VinculosColeta {
+                    usuario_id: Some(usuario.id),
+                    ponto_id: Some(checkin.ponto_id.clone()),
+                    rota_id: Some(id),
+                },
+            );
+        }
+
+        dataset.save_to_file(db_path)
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    }
+    tx.commit().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    // O primeiro check-in coloca a rota em andamento
+    if status_antes == Some(crate::otimizacao::StatusRota::Despachada) {
+        if let Ok(Some(rota)) = crate::otimizacao::buscar_rota(&conn, id) {
+            publicar_ultimo_status(&app_state, &rota);
+        }
+    }
+    app_state.otimizacao.publicar_evento(crate::otimizacao::EventoRastreamento::Checkin {
+        rota_id: id,
+        checkin: checkin.clone(),
+    });
+
+    Ok(Json(ApiResponse::success(checkin)))
+}
+
+pub async fn buscar_execucao_rota(
+    State(app_state): State<AppState>,
+    Path(id): Path<i64>,
+    headers: HeaderMap,
+) -> Result<Json<ApiResponse<crate::otimizacao::ExecucaoRota>>, StatusCode> {
+    if let Err(e) = otimizador_do_cabecalho(&app_state, &headers)? {
+        return Ok(Json(ApiResponse::error(e)));
+    }
+
+    let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    match crate::otimizacao::execucao_rota(&conn, id) {
+        Ok(Some(execucao)) => Ok(Json(ApiResponse::success(execucao))),
+        Ok(None) => Ok(Json(ApiResponse::error("Rota não encontrada".to_string()))),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+pub async fn relatorio_execucao_rotas(
+    State(app_state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> Result<Json<ApiResponse<crate::otimizacao::RelatorioExecucao>>, StatusCode> {
+    if let Err(e) = otimizador_do_cabecalho(&app_state, &headers)? {
+        return Ok(Json(ApiResponse::error(e)));
+    }
+
+    let status = match params.get("status") {
+        Some(s) => match crate::otimizacao::StatusRota::try_from(s.as_str()) {
+            Ok(status) => Some(status),
+            Err(e) => return Ok(Json(ApiResponse::error(e))),
+        },
+        None => None,
+    };
+
+    let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let relatorio = crate::otimizacao::relatorio_execucao(&conn, status)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(ApiResponse::success(relatorio)))
+}
+
+pub async fn salvar_ponto_coleta(
+    State(app_state): State<AppState>,
+    Json(payload): Json<crate::api::models::SalvarPontoRequest>,
+) -> Result<Json<ApiResponse<crate::otimizacao::PontoColeta>>, StatusCode> {
+    let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let usuario = match validar_usuario(&conn, &payload.nome, &payload.senha) {
+        Ok(u) => u,
+        Err(e) => return Ok(Json(ApiResponse::error(format!("Credenciais inválidas: {}", e)))),
+    };
+
+    if !usuario.pode_otimizar_rotas() {
+        return Ok(Json(ApiResponse::error("Acesso negado".to_string())));
+    }
+
+    match crate::otimizacao::salvar_ponto(&conn, &payload.ponto) {
+        Ok(()) => Ok(Json(ApiResponse::success(payload.ponto))),
+        Err(e) => Ok(Json(ApiResponse::error(e))),
+    }
+}
+
+pub async fn listar_pontos_coleta(
+    State(app_state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<ApiResponse<Vec<crate::otimizacao::PontoColeta>>>, StatusCode> {
+    let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let pontos = crate::otimizacao::listar_pontos(&conn, params.get("regiao").map(|s| s.as_str()))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(ApiResponse::success(pontos)))
+}
+
+pub async fn salvar_perfil_pesos(
+    State(app_state): State<AppState>,
+    Json(payload): Json<crate::api::models::SalvarPerfilPesosRequest>,
+) -> Result<Json<ApiResponse<crate::otimizacao::PerfilPesos>>, StatusCode> {
+    let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let usuario = match validar_usuario(&conn, &payload.nome, &payload.senha) {
+        Ok(u) => u,
+        Err(e) => return Ok(Json(ApiResponse::error(format!("Credenciais inválidas: {}", e)))),
+    };
+
+    if !usuario.pode_otimizar_rotas() {
+        return Ok(Json(ApiResponse::error("Acesso negado".to_string())));
+    }
+
+    match crate::otimizacao::salvar_perfil_pesos(&conn, &payload.perfil) {
+        Ok(perfil) => Ok(Json(ApiResponse::success(perfil))),
+        Err(e) => Ok(Json(ApiResponse::error(e))),
+    }
+}
+
+pub async fn listar_perfis_pesos(
+    State(app_state): State<AppState>,
+) -> Result<Json<ApiResponse<Vec<crate::otimizacao::PerfilPesos>>>, StatusCode> {
+    let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let perfis = crate::otimizacao::listar_perfis_pesos(&conn)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(ApiResponse::success(perfis)))
+}
+
+pub async fn salvar_sensor(
+    State(app_state): State<AppState>,
+    Json(payload): Json<crate::api::models::SalvarSensorRequest>,
+) -> Result<Json<ApiResponse<crate::otimizacao::Sensor>>, StatusCode> {
+    let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let usuario = match validar_usuario(&conn, &payload.nome, &payload.senha) {
+        Ok(u) => u,
+        Err(e) => return Ok(Json(ApiResponse::error(format!("Credenciais inválidas: {}", e)))),
+    };
+
+    if !usuario.pode_otimizar_rotas() {
+        return Ok(Json(ApiResponse::error("Acesso negado".to_string())));
+    }
+
+    match crate::otimizacao::salvar_sensor(&conn, &payload.sensor) {
+        Ok(sensor) => Ok(Json(ApiResponse::success(sensor))),
+        Err(e) => Ok(Json(ApiResponse::error(e))),
+    }
+}
+
+pub async fn listar_sensores(
+    State(app_state): State<AppState>,
+) -> Result<Json<ApiResponse<Vec<crate::otimizacao::Sensor>>>, StatusCode> {
+    let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let sensores = crate::otimizacao::listar_sensores(&conn)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(ApiResponse::success(sensores)))
+}
+
+pub async fn registrar_leituras_sensores(
+    State(app_state): State<AppState>,
+    Json(payload): Json<crate::api::models::RegistrarLeiturasRequest>,
+) -> Result<Json<ApiResponse<crate::otimizacao::ResultadoIngestaoLeituras>>, StatusCode> {
+    let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let usuario = match validar_usuario(&conn, &payload.nome, &payload.senha) {
+        Ok(u) => u,
+        Err(e) => return Ok(Json(ApiResponse::error(format!("Credenciais inválidas: {}", e)))),
+    };
+
+    if !usuario.pode_inserir_dados() {
+        return Ok(Json(ApiResponse::error("Acesso negado".to_string())));
+    }
+
+    let resultado = crate::otimizacao::registrar_leituras(&conn, &payload.leituras);
+    Ok(Json(ApiResponse::success(resultado)))
+}
+
+pub async fn importar_leituras_sensores(
+    State(app_state): State<AppState>,
+    Json(payload): Json<crate::api::models::ImportarLeiturasRequest>,
+) -> Result<Json<ApiResponse<crate::otimizacao::ResultadoIngestaoLeituras>>, StatusCode> {
+    let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let usuario = match validar_usuario(&conn, &payload.nome, &payload.senha) {
+        Ok(u) => u,
+        Err(e) => return Ok(Json(ApiResponse::error(format!("Credenciais inválidas: {}", e)))),
+    };
+
+    if !usuario.pode_inserir_dados() {
+        return Ok(Json(ApiResponse::error("Acesso negado".to_string())));
+    }
+
+    let resultado = crate::otimizacao::importar_leituras_csv(&conn, &payload.csv);
+    Ok(Json(ApiResponse::success(resultado)))
+}
+
+pub async fn estado_enchimento_pontos(
+    State(app_state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<ApiResponse<Vec<crate::otimizacao::EstadoEnchimento>>>, StatusCode> {
+    let minimo: Option<f64> = match params.get("nivel_minimo") {
+        Some(s) => match s.parse() {
+            Ok(n) => Some(n),
+            Err(_) => return Ok(Json(ApiResponse::error(format!("nivel_minimo inválido: '{}'", s)))),
+        },
+        None => None,
+    };
+
+    let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let mut estados = crate::otimizacao::estado_enchimento(&conn)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if let Some(minimo) = minimo {
+        estados.retain(|e| e.nivel_percentual >= minimo);
+    }
+
+    Ok(Json(ApiResponse::success(estados)))
+}
+
+/// Últimas posições dos veículos (perfis que otimizam rotas), com
+/// credenciais no cabeçalho `Authorization`.
+pub async fn posicoes_veiculos(
+    State(app_state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> Result<Json<ApiResponse<Vec<crate::otimizacao::PosicaoVeiculo>>>, StatusCode> {
+    if let Err(e) = otimizador_do_cabecalho(&app_state, &headers)? {
+        return Ok(Json(ApiResponse::error(e)));
+    }
+
+    let rota_id: Option<i64> = match params.get("rota_id") {
+        Some(s) => match s.parse() {
+            Ok(id) => Some(id),
+            Err(_) => return Ok(Json(ApiResponse::error(format!("rota_id inválido: '{}'", s)))),
+        },
+        None => None,
+    };
+
+    let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let posicoes = crate::otimizacao::ultimas_posicoes(&conn, rota_id)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(ApiResponse::success(posicoes)))
+}
+
+/// Lê `Authorization: Basic base64(nome:senha)`.
+fn credenciais_basic(headers: &HeaderMap) -> Option<(String, String)> {
+    let valor = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+    let (esquema, codificado) = valor.split_once(' ')?;
+    if !esquema.eq_ignore_ascii_case("basic") {
+        return None;
+    }
+    let texto = String::from_utf8(BASE64.decode(codificado.trim()).ok()?).ok()?;
+    let (nome, senha) = texto.split_once(':')?;
+    Some((nome.to_string(), senha.to_string()))
+}
+
+/// Valida as credenciais dos GETs, que não têm corpo. Vão no cabeçalho
+/// `Authorization` e não na query string, que acaba nos logs.
+fn usuario_do_cabecalho(
+    app_state: &AppState,
+    headers: &HeaderMap,
+) -> Result<Result<Usuario, String>, StatusCode> {
+    let Some((nome, senha)) = credenciais_basic(headers) else {
+        return Ok(Err("Envie as credenciais no cabeçalho Authorization (Basic)".to_string()));
+    };
+    let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(validar_usuario(&conn, &nome, &senha).map_err(|e| format!("Credenciais inválidas: {}", e)))
+}
+
+/// Como `usuario_do_cabecalho`, exigindo permissão para otimizar rotas: a
+/// mesma do WebSocket de rastreamento.
+fn otimizador_do_cabecalho(
+    app_state: &AppState,
+    headers: &HeaderMap,
+) -> Result<Result<Usuario, String>, StatusCode> {
+    Ok(usuario_do_cabecalho(app_state, headers)?.and_then(|usuario| {
+        if usuario.pode_otimizar_rotas() {
+            Ok(usuario)
+        } else {
+            Err("Acesso negado".to_string())
+        }
+    }))
+}
+
+/// Prazo para o cliente enviar as credenciais depois de abrir o WebSocket.
+const PRAZO_AUTENTICACAO_WS: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// WebSocket de acompanhamento de rotas: `?rotas=1,2`. O navegador não
+/// envia corpo nem cabeçalhos próprios no handshake, e a URL vai para os
+/// logs; por isso as credenciais chegam na primeira mensagem do socket.
+pub async fn rastreamento_ws(
+    State(app_state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+    ws: WebSocketUpgrade,
+) -> Result<Response, StatusCode> {
+    let mut rotas = HashSet::new();
+    for parte in params.get("rotas").map(|s| s.as_str()).unwrap_or_default().split(',') {
+        let parte = parte.trim();
+        if parte.is_empty() {
+            continue;
+        }
+        match parte.parse::<i64>() {
+            Ok(id) => {
+                rotas.insert(id);
+            }
+            Err(_) => {
+                return Ok(Json(ApiResponse::<()>::error(format!("Id de rota inválido: '{}'", parte))).into_response())
+            }
+        }
+    }
+
+    Ok(ws.on_upgrade(move |socket| sessao_rastreamento(socket, app_state, rotas)))
+}
+
+async fn enviar_ws<T: Serialize>(socket: &mut WebSocket, mensagem: &T) -> bool {
+    match serde_json::to_string(mensagem) {
+        Ok(texto) => socket.send(Message::Text(texto)).await.is_ok(),
+        Err(_) => true,
+    }
+}
+
+/// Inscreve o cliente na rota e envia o estado atual dela. Retorna `false`
+/// se o socket foi fechado.
+async fn assinar_rota(
+    socket: &mut WebSocket,
+    app_state: &AppState,
+    rotas: &mut HashSet<i64>,
+    rota_id: i64,
+) -> bool {
+    let estado = match app_state.auth.conn.lock() {
+        Ok(conn) => match crate::otimizacao::estado_rastreamento(&conn, rota_id) {
+            Ok(Some(estado)) => Ok(estado),
+            Ok(None) => Err("Rota não encontrada".to_string()),
+            Err(e) => Err(format!("Erro ao acessar banco de dados: {}", e)),
+        },
+        Err(_) => Err("Banco de dados indisponível".to_string()),
+    };
+
+    match estado {
+        Ok(estado) => {
+            rotas.insert(rota_id);
+            enviar_ws(socket, &crate::otimizacao::EventoRastreamento::EstadoInicial(estado)).await
+        }
+        Err(mensagem) => {
+            enviar_ws(socket, &json!({ "tipo": "erro", "rota_id": rota_id, "mensagem": mensagem })).await
+        }
+    }
+}
+
+/// Espera `{"nome", "senha"}` como primeira mensagem e confere a permissão.
+async fn autenticar_ws(socket: &mut WebSocket, app_state: &AppState) -> Result<Usuario, String> {
+    let texto = match tokio::time::timeout(PRAZO_AUTENTICACAO_WS, socket.recv()).await {
+        Ok(Some(Ok(Message::Text(texto)))) => texto,
+        Ok(_) => return Err("A primeira mensagem deve trazer nome e senha".to_string()),
+        Err(_) => return Err("Credenciais não enviadas a tempo".to_string()),
+    };
+    let credenciais: AuthCredentials = serde_json::from_str(&texto)
+        .map_err(|_| "A primeira mensagem deve trazer nome e senha".to_string())?;
+
+    let usuario = {
+        let conn = app_state.auth.conn.lock().map_err(|_| "Banco de dados indisponível".to_string())?;
+        validar_usuario(&conn, &credenciais.nome, &credenciais.senha)
+            .map_err(|e| format!("Credenciais inválidas: {}", e))?
+    };
+    if !usuario.pode_otimizar_rotas() {
+        return Err("Acesso negado".to_string());
+    }
+    Ok(usuario)
+}
+
+async fn sessao_rastreamento(mut socket: WebSocket, app_state: AppState, rotas_iniciais: HashSet<i64>) {
+    if let Err(e) = autenticar_ws(&mut socket, &app_state).await {
+        enviar_ws(&mut socket, &json!({ "tipo": "erro", "mensagem": e })).await;
+        let _ = socket
+            .send(Message::Close(Some(CloseFrame {
+                code: close_code::POLICY,
+                reason: e.into(),
+            })))
+            .await;
+        return;
+    }
+    if !enviar_ws(&mut socket, &json!({ "tipo": "autenticado" })).await {
+        return;
+    }
+
+    // Inscrição no canal antes do estado inicial, para não perder eventos entre os dois
+    let mut eventos = app_state.otimizacao.assinar_eventos();
+    let mut rotas = HashSet::new();
+    for rota_id in rotas_iniciais {
+        if !assinar_rota(&mut socket, &app_state, &mut rotas, rota_id).await {
+            return;
+        }
+    }
+
+    loop {
+        tokio::select! {
+            mensagem = socket.recv() => match mensagem {
+                Some(Ok(Message::Text(texto))) => {
+                    let aberto = match serde_json::from_str::<ComandoRastreamento>(&texto) {
+                        Ok(ComandoRastreamento::Assinar { rota_id }) => {
+                            assinar_rota(&mut socket, &app_state, &mut rotas, rota_id).await
+                        }
+                        Ok(ComandoRastreamento::Cancelar { rota_id }) => {
+                            rotas.remove(&rota_id);
+                            enviar_ws(&mut socket, &json!({ "tipo": "cancelado", "rota_id": rota_id })).await
+                        }
+                        Err(e) => {
+                            enviar_ws(&mut socket, &json!({ "tipo": "erro", "mensagem": format!("Comando inválido: {}", e) })).await
+                        }
+                    };
+                    if !aberto {
+                        break;
+                    }
+                }
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+            evento = eventos.recv() => match evento {
+                Ok(evento) => {
+                    if rotas.contains(&evento.rota_id()) && !enviar_ws(&mut socket, &evento).await {
+                        break;
+                    }
+                }
+                // Cliente lento perdeu eventos: reenvia o estado atual das rotas
+                Err(RecvError::Lagged(_)) => {
+                    for rota_id in rotas.clone() {
+                        if !assinar_rota(&mut socket, &app_state, &mut rotas, rota_id).await {
+                            return;
+                        }
+                    }
+                }
+                Err(RecvError::Closed) => break,
+            },
+        }
+    }
+}
+
+/// Monta os trajetos (gulosa e prioridade, ou só a pedida em `?estrategia=`)
+/// de uma rota armazenada para exportação.
+fn montar_trajetos<'a>(
+    app_state: &AppState,
+    conn: &rusqlite::Connection,
+    rota: &'a crate::otimizacao::RotaArmazenada,
+    params: &HashMap<String, String>,
+) -> Result<Vec<crate::otimizacao::TrajetoExportado<'a>>, String> {
+    use crate::otimizacao::{EstrategiaRota, StatusRota};
+
+    let estrategias = match params.get("estrategia") {
+        Some(e) => vec![EstrategiaRota::try_from(e.as_str())?],
+        None => vec![EstrategiaRota::Gulosa, EstrategiaRota::Prioridade],
+    };
+
+    // ETA a partir de ?inicio=, do despacho ou, na falta dele, da criação da rota
+    let inicio_texto = params.get("inicio").cloned().unwrap_or_else(|| {
+        rota.historico
+            .iter()
+            .find(|e| e.status_novo == StatusRota::Despachada)
+            .map(|e| e.registrado_em.clone())
+            .unwrap_or_else(|| rota.criada_em.clone())
+    });
+    let inicio = chrono::DateTime::parse_from_rfc3339(&inicio_texto)
+        .map(|d| d.with_timezone(&Utc))
+        .map_err(|_| format!("Data de início inválida: '{}'", inicio_texto))?;
+
+    let pontos = crate::otimizacao::mapa_pontos(conn)
+        .map_err(|e| format!("Erro ao acessar banco de dados: {}", e))?;
+
+    Ok(estrategias
+        .into_iter()
+        .map(|estrategia| crate::otimizacao::TrajetoExportado {
+            estrategia,
+            rota,
+            paradas: crate::otimizacao::planejar_paradas(
+                &app_state.otimizacao,
+                &rota.comparacao.rota(estrategia).sequencia_pontos,
+                &pontos,
+                inicio,
+            ),
+        })
+        .collect())
+}
+
+pub async fn exportar_rota_geojson(
+    State(app_state): State<AppState>,
+    Path(id): Path<i64>,
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    if let Err(e) = otimizador_do_cabecalho(&app_state, &headers)? {
+        return Ok(Json(ApiResponse::<()>::error(e)).into_response());
+    }
+
+    let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let rota = match crate::otimizacao::buscar_rota(&conn, id) {
+        Ok(Some(rota)) => rota,
+        Ok(None) => return Ok(Json(ApiResponse::<()>::error("Rota não encontrada".to_string())).into_response()),
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+    };
+
+    let trajetos = match montar_trajetos(&app_state, &conn, &rota, &params) {
+        Ok(t) => t,
+        Err(e) => return Ok(Json(ApiResponse::<()>::error(e)).into_response()),
+    };
+
+    let geojson = crate::otimizacao::gerar_geojson(&trajetos);
+    Ok(([(header::CONTENT_TYPE, "application/geo+json")], geojson.to_string()).into_response())
+}
+
+pub async fn exportar_rota_gpx(
+    State(app_state): State<AppState>,
+    Path(id): Path<i64>,
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    if let Err(e) = otimizador_do_cabecalho(&app_state, &headers)? {
+        return Ok(Json(ApiResponse::<()>::error(e)).into_response());
+    }
+
+    let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let rota = match crate::otimizacao::buscar_rota(&conn, id) {
+        Ok(Some(rota)) => rota,
+        Ok(None) => return Ok(Json(ApiResponse::<()>::error("Rota não encontrada".to_string())).into_response()),
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+    };
+
+    let trajetos = match montar_trajetos(&app_state, &conn, &rota, &params) {
+        Ok(t) => t,
+        Err(e) => return Ok(Json(ApiResponse::<()>::error(e)).into_response()),
+    };
+
+    let gpx = crate::otimizacao::gerar_gpx(&trajetos);
+    Ok(([(header::CONTENT_TYPE, "application/gpx+xml")], gpx).into_response())
+}
+
+pub async fn adicionar_distancia(
+    State(app_state): State<AppState>,
+    Json(payload): Json<crate::api::models::AdicionarDistanciaRequest>,
+) -> Result<Json<ApiResponse<String>>, StatusCode> {
+    let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let usuario = match validar_usuario(&conn, &payload.nome, &payload.senha) {
+        Ok(u) => u,
+        Err(e) => return Ok(Json(ApiResponse::error(format!("Credenciais inválidas: {}", e)))),
+    };
+
+    if !usuario.pode_otimizar_rotas() {
+        return Ok(Json(ApiResponse::error("Acesso negado".to_string())));
+    }
+
+    if let Err(e) = crate::otimizacao::validar_distancia(&payload.distancia) {
+        return Ok(Json(ApiResponse::error(e)));
+    }
+
+    if let Err(e) = crate::otimizacao::alimentar_distancia(&app_state.otimizacao, payload.distancia) {
+        return Ok(Json(ApiResponse::error(e)));
+    }
+
+    Ok(Json(ApiResponse::success("Distância adicionada com sucesso".to_string())))
+}
+
+pub async fn listar_distancias(
+    State(app_state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Json<ApiResponse<Vec<crate::otimizacao::ArestaDistancia>>> {
+    let ponto = params.get("ponto").map(|s| s.as_str());
+    Json(ApiResponse::success(crate::otimizacao::listar_distancias(&app_state.otimizacao, ponto)))
+}
+
+pub async fn remover_distancia(
+    State(app_state): State<AppState>,
+    Path((origem, destino)): Path<(String, String)>,
+    Query(params): Query<HashMap<String, String>>,
+    Json(creds): Json<AuthCredentials>,
+) -> Result<Json<ApiResponse<String>>, StatusCode> {
+    let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let usuario = match validar_usuario(&conn, &creds.nome, &creds.senha) {
+        Ok(u) => u,
+        Err(e) => return Ok(Json(ApiResponse::error(format!("Credenciais inválidas: {}", e)))),
+    };
+
+    if !usuario.pode_otimizar_rotas() {
+        return Ok(Json(ApiResponse::error("Acesso negado".to_string())));
+    }
+
+    let simetrica = params.get("simetrica").is_none_or(|s| s != "false");
+    match crate::otimizacao::remover_distancia(&app_state.otimizacao, &origem, &destino, simetrica) {
+        Ok(0) => Ok(Json(ApiResponse::error("Distância não encontrada".to_string()))),
+        Ok(n) => Ok(Json(ApiResponse::success(format!("{} trecho(s) removido(s)", n)))),
+        Err(e) => Ok(Json(ApiResponse::error(e))),
+    }
+}
+
+pub async fn remover_ponto_distancias(
+    State(app_state): State<AppState>,
+    Path(ponto): Path<String>,
+    Json(creds): Json<AuthCredentials>,
+) -> Result<Json<ApiResponse<String>>, StatusCode> {
+    let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let usuario = match validar_usuario(&conn, &creds.nome, &creds.senha) {
+        Ok(u) => u,
+        Err(e) => return Ok(Json(ApiResponse::error(format!("Credenciais inválidas: {}", e)))),
+    };
+
+    if !usuario.pode_otimizar_rotas() {
+        return Ok(Json(ApiResponse::error("Acesso negado".to_string())));
+    }
+
+    match crate::otimizacao::remover_ponto_distancias(&app_state.otimizacao, &ponto) {
+        Ok(0) => Ok(Json(ApiResponse::error("Ponto não encontrado na matriz".to_string()))),
+        Ok(n) => Ok(Json(ApiResponse::success(format!("Ponto '{}' removido ({} trecho(s))", ponto, n)))),
+        Err(e) => Ok(Json(ApiResponse::error(e))),
+    }
+}
+
+pub async fn importar_distancias(
+    State(app_state): State<AppState>,
+    Json(payload): Json<crate::api::models::ImportarDistanciasRequest>,
+) -> Result<Json<ApiResponse<crate::otimizacao::ResultadoImportacaoDistancias>>, StatusCode> {
+    let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let usuario = match validar_usuario(&conn, &payload.nome, &payload.senha) {
+        Ok(u) => u,
+        Err(e) => return Ok(Json(ApiResponse::error(format!("Credenciais inválidas: {}", e)))),
+    };
+
+    if !usuario.pode_otimizar_rotas() {
+        return Ok(Json(ApiResponse::error("Acesso negado".to_string())));
+    }
+
+    let pontos_conhecidos = crate::otimizacao::mapa_pontos(&conn)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .into_keys()
+        .collect();
+
+    let resultado = crate::otimizacao::importar_distancias_csv(
+        &app_state.otimizacao,
+        &payload.csv,
+        payload.simetrica,
+        &pontos_conhecidos,
+    );
+    Ok(Json(ApiResponse::success(resultado)))
+}
+
+pub async fn exportar_distancias(
+    State(app_state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Response, StatusCode> {
+    match params.get("formato").map(|s| s.as_str()).unwrap_or("json") {
+        "csv" => {
+            let csv = crate::otimizacao::exportar_distancias_csv(&app_state.otimizacao)
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            Ok(([(header::CONTENT_TYPE, "text/csv; charset=utf-8")], csv).into_response())
+        }
+        "json" => Ok(Json(crate::otimizacao::exportar_distancias_json(&app_state.otimizacao)).into_response()),
+        outro => Ok(Json(ApiResponse::<()>::error(format!("Formato desconhecido: '{}'", outro))).into_response()),
+    }
+}
+
+pub async fn conectividade_distancias(
+    State(app_state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Json<ApiResponse<crate::otimizacao::RelatorioConectividade>> {
+    let garagem = params.get("garagem").map(|s| s.as_str()).unwrap_or("garagem");
+    Json(ApiResponse::success(crate::otimizacao::relatorio_conectividade(&app_state.otimizacao, garagem)))
+}
+
+pub async fn atualizar_previsao_demanda(
+    State(app_state): State<AppState>,
+    Json(payload): Json<crate::api::models::AtualizarPrevisaoRequest>,
+) -> Result<Json<ApiResponse<String>>, StatusCode> {
+    let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let usuario = match validar_usuario(&conn, &payload.nome, &payload.senha) {
+        Ok(u) => u,
+        Err(e) => return Ok(Json(ApiResponse::error(format!("Credenciais inválidas: {}", e)))),
+    };
+
+    if !usuario.pode_otimizar_rotas() {
+        return Ok(Json(ApiResponse::error("Acesso negado".to_string())));
+    }
+
+    match crate::otimizacao::alimentar_previsao(&app_state.otimizacao, payload.previsao) {
+        Ok(_) => Ok(Json(ApiResponse::success("Previsão atualizada com sucesso".to_string()))),
+        Err(e) => Ok(Json(ApiResponse::error(e))),
+    }
+}
+
+pub async fn demanda_por_regiao(
+    State(app_state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Json<ApiResponse<Vec<crate::otimizacao::DemandaRegiao>>> {
+    let regiao = params.get("regiao").map(|s| s.as_str());
+    Json(ApiResponse::success(crate::otimizacao::demanda_por_regiao(&app_state.otimizacao, regiao)))
+}
+
+pub async fn historico_previsoes_ponto(
+    State(app_state): State<AppState>,
+    Path(ponto_id): Path<String>,
+) -> Json<ApiResponse<Vec<crate::otimizacao::RegistroPrevisao>>> {
+    Json(ApiResponse::success(crate::otimizacao::historico_previsoes(&app_state.otimizacao, &ponto_id)))
+}
+
+// ========== USUÁRIOS ==========
+
+pub async fn listar_usuarios(
+    State(app_state): State<AppState>,
+    Json(creds): Json<AuthCredentials>,
+) -> Result<Json<ApiResponse<Vec<UsuarioResponse>>>, StatusCode> {
     // Validar usuário e liberar o lock antes de fazer a query
     let usuario = {
         let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
@@ -331,65 +1617,762 @@ pub async fn listar_usuarios(
         return Ok(Json(ApiResponse::error("Acesso negado".to_string())));
     }
 
-    // Agora podemos adquirir o lock novamente para a query
+    // Agora podemos adquirir o lock novamente para a query
+    let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let mut stmt = conn.prepare("SELECT id, nome, perfil FROM usuarios ORDER BY id")
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let usuarios: Vec<UsuarioResponse> = stmt.query_map([], |row| {
+        let id: u32 = row.get(0)?;
+        let nome: String = row.get(1)?;
+        let perfil_str: String = row.get(2)?;
+        Ok(UsuarioResponse {
+            id,
+            nome,
+            perfil: perfil_str,
+        })
+    })
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .filter_map(|r| r.ok())
+    .collect();
+
+    Ok(Json(ApiResponse::success(usuarios)))
+}
+
+pub async fn deletar_usuario(
+    State(app_state): State<AppState>,
+    Path(id): Path<u32>,
+    Json(creds): Json<AuthCredentials>,
+) -> Result<Json<ApiResponse<String>>, StatusCode> {
+    // Para DELETE, as credenciais vão no body também
+    // Validar usuário e liberar o lock antes de fazer a query
+    let usuario = {
+        let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        match validar_usuario(&conn, &creds.nome, &creds.senha) {
+            Ok(u) => u,
+            Err(e) => return Ok(Json(ApiResponse::error(format!("Credenciais inválidas: {}", e)))),
+        }
+    };
+
+    if !usuario.pode_gerenciar_usuarios() {
+        return Ok(Json(ApiResponse::error("Acesso negado".to_string())));
+    }
+
+    if id == usuario.id {
+        return Ok(Json(ApiResponse::error("Você não pode deletar a si mesmo".to_string())));
+    }
+
+    // Agora podemos adquirir o lock novamente para a query
+    let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    match conn.execute("DELETE FROM usuarios WHERE id = ?1", [id]) {
+        Ok(0) => Ok(Json(ApiResponse::error("Usuário não encontrado".to_string()))),
+        Ok(_) => Ok(Json(ApiResponse::success("Usuário deletado com sucesso".to_string()))),
+        Err(e) => Ok(Json(ApiResponse::error(format!("Erro: {}", e)))),
+    }
+}
+
+// ========== JOBS ==========
+
+/// Inicia predição, pré-processamento, otimização ou importação em segundo
+/// plano e devolve o job na hora; o andamento sai em `GET /jobs/:id/eventos`.
+pub async fn iniciar_job(
+    State(app_state): State<AppState>,
+    Json(pedido): Json<PedidoJob>,
+) -> Result<Json<ApiResponse<Job>>, StatusCode> {
+    let (nome, senha) = match &pedido {
+        PedidoJob::Predicao(p) => (&p.nome, &p.senha),
+        PedidoJob::Preprocessamento(c) => (&c.nome, &c.senha),
+        PedidoJob::Otimizacao(o) => (&o.nome, &o.senha),
+        PedidoJob::Importacao(i) => (&i.nome, &i.senha),
+    };
+    let usuario = {
+        let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        match validar_usuario(&conn, nome, senha) {
+            Ok(u) => u,
+            Err(e) => return Ok(Json(ApiResponse::error(format!("Credenciais inválidas: {}", e)))),
+        }
+    };
+
+    let (tipo, permitido) = match &pedido {
+        PedidoJob::Predicao(_) => (TipoJob::Predicao, usuario.pode_acessar_predicoes()),
+        PedidoJob::Preprocessamento(_) => (TipoJob::Preprocessamento, usuario.pode_pre_processar()),
+        PedidoJob::Otimizacao(_) => (TipoJob::Otimizacao, usuario.pode_otimizar_rotas()),
+        PedidoJob::Importacao(_) => (TipoJob::Importacao, usuario.pode_importar_coletas()),
+    };
+    if !permitido {
+        return Ok(Json(ApiResponse::error("Acesso negado".to_string())));
+    }
+    let job = match pedido {
+        PedidoJob::Predicao(payload) => {
+            // Tipo ou unidade inválidos voltam como erro do pedido, antes de
+            // existir um job.
+            let coleta = {
+                let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+                coleta_da_predicao(&conn, &payload)
+            };
+            let coleta = match coleta {
+                Ok(coleta) => coleta,
+                Err(e) => return Ok(Json(ApiResponse::error(e))),
+            };
+            let job = app_state.jobs.criar(tipo, usuario.id);
+            let tarefa = processar_predicao(app_state.clone(), payload, coleta, usuario.id);
+            tokio::spawn(app_state.jobs.executar(&job, tarefa));
+            job
+        }
+        PedidoJob::Preprocessamento(_) => {
+            let job = app_state.jobs.criar(tipo, usuario.id);
+            tokio::spawn(app_state.jobs.executar(&job, processar_preprocessamento()));
+            job
+        }
+        PedidoJob::Otimizacao(payload) => {
+            let job = app_state.jobs.criar(tipo, usuario.id);
+            let estado = app_state.clone();
+            let tarefa = async move { processar_otimizacao(&estado, usuario.id, payload) };
+            tokio::spawn(app_state.jobs.executar(&job, tarefa));
+            job
+        }
+        PedidoJob::Importacao(payload) => {
+            let job = app_state.jobs.criar(tipo, usuario.id);
+            let tarefa = importar_fora_do_runtime(app_state.clone(), usuario.id, payload.pedido);
+            tokio::spawn(app_state.jobs.executar(&job, tarefa));
+            job
+        }
+    };
+
+    Ok(Json(ApiResponse::success(job)))
+}
+
+/// Busca um job visível para o usuário: o dono ou um administrador.
+fn job_do_usuario(app_state: &AppState, usuario: &Usuario, id: u64) -> Result<Job, String> {
+    match app_state.jobs.buscar(id) {
+        Some(job) if job.usuario_id == usuario.id || usuario.pode_gerenciar_usuarios() => Ok(job),
+        Some(_) => Err("Acesso negado".to_string()),
+        None => Err(format!("Job {} não encontrado", id)),
+    }
+}
+
+/// Jobs em memória, do mais recente para o mais antigo. Administradores
+/// veem todos; os demais perfis, apenas os próprios.
+pub async fn listar_jobs(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<ApiResponse<Vec<Job>>>, StatusCode> {
+    let usuario = match usuario_do_cabecalho(&app_state, &headers)? {
+        Ok(u) => u,
+        Err(e) => return Ok(Json(ApiResponse::error(e))),
+    };
+
+    let jobs = app_state
+        .jobs
+        .listar()
+        .into_iter()
+        .filter(|job| usuario.pode_gerenciar_usuarios() || job.usuario_id == usuario.id)
+        .collect();
+    Ok(Json(ApiResponse::success(jobs)))
+}
+
+pub async fn buscar_job(
+    State(app_state): State<AppState>,
+    Path(id): Path<u64>,
+    headers: HeaderMap,
+) -> Result<Json<ApiResponse<Job>>, StatusCode> {
+    let usuario = match usuario_do_cabecalho(&app_state, &headers)? {
+        Ok(u) => u,
+        Err(e) => return Ok(Json(ApiResponse::error(e))),
+    };
+
+    match job_do_usuario(&app_state, &usuario, id) {
+        Ok(job) => Ok(Json(ApiResponse::success(job))),
+        Err(e) => Ok(Json(ApiResponse::error(e))),
+    }
+}
+
+/// Converte os eventos dos jobs em eventos SSE: primeiro os já emitidos,
+/// depois os novos do canal. Com `job_id`, filtra esse job e encerra o
+/// stream quando ele termina.
+fn stream_eventos_jobs(
+    anteriores: Vec<EventoJob>,
+    receptor: tokio::sync::broadcast::Receiver<EventoJob>,
+    job_id: Option<u64>,
+) -> impl Stream<Item = Result<SseEvent, Infallible>> {
+    stream::unfold(
+        (anteriores.into_iter(), receptor, false),
+        move |(mut anteriores, mut receptor, encerrado)| async move {
+            if encerrado {
+                return None;
+            }
+            let evento = match anteriores.next() {
+                Some(evento) => evento,
+                None => loop {
+                    match receptor.recv().await {
+                        Ok(evento) if job_id.is_none_or(|id| evento.job_id() == id) => break evento,
+                        Ok(_) => {}
+                        Err(RecvError::Lagged(perdidos)) => {
+                            let aviso = SseEvent::default().comment(format!("{} eventos perdidos", perdidos));
+                            return Some((Ok(aviso), (anteriores, receptor, false)));
+                        }
+                        Err(RecvError::Closed) => return None,
+                    }
+                },
+            };
+
+            let encerrado = job_id.is_some() && evento.finaliza_job();
+            let sse = SseEvent::default()
+                .event(evento.nome())
+                .json_data(&evento)
+                .unwrap_or_default();
+            Some((Ok(sse), (anteriores, receptor, encerrado)))
+        },
+    )
+}
+
+/// SSE de um job. Quem se inscreve depois do início recebe os eventos
+/// anteriores antes dos novos.
+pub async fn eventos_job(
+    State(app_state): State<AppState>,
+    Path(id): Path<u64>,
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    let usuario = match usuario_do_cabecalho(&app_state, &headers)? {
+        Ok(u) => u,
+        Err(e) => return Ok(Json(ApiResponse::<()>::error(e)).into_response()),
+    };
+    if let Err(e) = job_do_usuario(&app_state, &usuario, id) {
+        return Ok(Json(ApiResponse::<()>::error(e)).into_response());
+    }
+
+    let Some((anteriores, receptor)) = app_state.jobs.acompanhar(id) else {
+        return Ok(Json(ApiResponse::<()>::error(format!("Job {} não encontrado", id))).into_response());
+    };
+
+    Ok(Sse::new(stream_eventos_jobs(anteriores, receptor, Some(id)))
+        .keep_alive(KeepAlive::default())
+        .into_response())
+}
+
+/// SSE com os eventos de todos os jobs, somente para administradores.
+pub async fn eventos_jobs(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    let usuario = match usuario_do_cabecalho(&app_state, &headers)? {
+        Ok(u) => u,
+        Err(e) => return Ok(Json(ApiResponse::<()>::error(e)).into_response()),
+    };
+    if !usuario.pode_gerenciar_usuarios() {
+        return Ok(Json(ApiResponse::<()>::error("Acesso negado".to_string())).into_response());
+    }
+
+    let receptor = app_state.jobs.assinar_todos();
+    Ok(Sse::new(stream_eventos_jobs(Vec::new(), receptor, None))
+        .keep_alive(KeepAlive::default())
+        .into_response())
+}
+
+// ========== FATORES DE EMISSÃO ==========
+
+pub async fn salvar_fator_emissao(
+    State(app_state): State<AppState>,
+    Json(payload): Json<SalvarFatorEmissaoRequest>,
+) -> Result<Json<ApiResponse<emissoes::FatorEmissao>>, StatusCode> {
+    let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let usuario = match validar_usuario(&conn, &payload.nome, &payload.senha) {
+        Ok(u) => u,
+        Err(e) => return Ok(Json(ApiResponse::error(format!("Credenciais inválidas: {}", e)))),
+    };
+
+    if !usuario.pode_gerenciar_usuarios() {
+        return Ok(Json(ApiResponse::error("Acesso negado".to_string())));
+    }
+
+    let mut fator = payload.fator;
+    fator.material = match tipos_residuo::tipo_canonico(&conn, &fator.material) {
+        Ok(codigo) => codigo,
+        Err(e) => return Ok(Json(ApiResponse::error(e))),
+    };
+
+    match emissoes::salvar_fator(&conn, &fator, usuario.id) {
+        Ok(fator) => Ok(Json(ApiResponse::success(fator))),
+        Err(e) => Ok(Json(ApiResponse::error(e))),
+    }
+}
+
+/// Fatores cadastrados, com todas as vigências; `?material=` filtra.
+pub async fn listar_fatores_emissao(
+    State(app_state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<ApiResponse<Vec<emissoes::FatorEmissao>>>, StatusCode> {
     let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    let mut stmt = conn.prepare("SELECT id, nome, perfil FROM usuarios ORDER BY id")
+    let fatores = emissoes::listar_fatores(&conn, params.get("material").map(|s| s.as_str()))
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    let usuarios: Vec<UsuarioResponse> = stmt.query_map([], |row| {
-        let id: u32 = row.get(0)?;
-        let nome: String = row.get(1)?;
-        let perfil_str: String = row.get(2)?;
-        Ok(UsuarioResponse {
-            id,
-            nome,
-            perfil: perfil_str,
-        })
-    })
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-    .filter_map(|r| r.ok())
-    .collect();
-
-    Ok(Json(ApiResponse::success(usuarios)))
+    Ok(Json(ApiResponse::success(fatores)))
 }
 
-pub async fn deletar_usuario(
+pub async fn remover_fator_emissao(
     State(app_state): State<AppState>,
-    Path(id): Path<u32>,
+    Path(id): Path<i64>,
     Json(creds): Json<AuthCredentials>,
 ) -> Result<Json<ApiResponse<String>>, StatusCode> {
-    // Para DELETE, as credenciais vão no body também
-    // Validar usuário e liberar o lock antes de fazer a query
+    let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let usuario = match validar_usuario(&conn, &creds.nome, &creds.senha) {
+        Ok(u) => u,
+        Err(e) => return Ok(Json(ApiResponse::error(format!("Credenciais inválidas: {}", e)))),
+    };
+
+    if !usuario.pode_gerenciar_usuarios() {
+        return Ok(Json(ApiResponse::error("Acesso negado".to_string())));
+    }
+
+    match emissoes::remover_fator(&conn, id) {
+        Ok(()) => Ok(Json(ApiResponse::success("Fator de emissão removido".to_string()))),
+        Err(e) => Ok(Json(ApiResponse::error(e))),
+    }
+}
+
+/// CO₂ evitado por uma quantidade de material numa data (padrão: hoje).
+pub async fn estimar_co2(
+    State(app_state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<ApiResponse<emissoes::EstimativaCo2>>, StatusCode> {
+    let Some(tipo) = params.get("tipo") else {
+        return Ok(Json(ApiResponse::error("Informe o parâmetro 'tipo'".to_string())));
+    };
+    let quantidade = match params.get("quantidade").map(|q| q.parse::<f32>()) {
+        Some(Ok(q)) if q.is_finite() && q >= 0.0 => q,
+        None => 1.0,
+        _ => return Ok(Json(ApiResponse::error("Quantidade inválida".to_string()))),
+    };
+    let data = match params.get("data").map(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d")) {
+        Some(Ok(d)) => d,
+        None => Utc::now().date_naive(),
+        Some(Err(_)) => return Ok(Json(ApiResponse::error("Data inválida (use AAAA-MM-DD)".to_string()))),
+    };
+
+    let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let fatores = TabelaFatores::carregar(&conn).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Json(ApiResponse::success(fatores.estimar(tipo, quantidade, data))))
+}
+
+// ========== TIPOS DE RESÍDUO ==========
+
+pub async fn listar_tipos_residuo(
+    State(app_state): State<AppState>,
+) -> Result<Json<ApiResponse<Vec<tipos_residuo::TipoResiduo>>>, StatusCode> {
+    let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let tipos = tipos_residuo::listar_tipos(&conn).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(ApiResponse::success(tipos)))
+}
+
+/// Converte uma quantidade entre unidades pela densidade do tipo.
+/// Parâmetros: `tipo`, `quantidade`, `de` e `para` (unidade; padrão kg).
+pub async fn converter_unidade(
+    State(app_state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<ApiResponse<ConversaoResponse>>, StatusCode> {
+    let Some(tipo) = params.get("tipo") else {
+        return Ok(Json(ApiResponse::error("Parâmetro 'tipo' é obrigatório".to_string())));
+    };
+    let quantidade = match params.get("quantidade").map(|q| q.parse::<f32>()) {
+        Some(Ok(quantidade)) => quantidade,
+        _ => return Ok(Json(ApiResponse::error("Parâmetro 'quantidade' inválido".to_string()))),
+    };
+    let unidade = |chave: &str| match params.get(chave) {
+        Some(u) => UnidadeMedida::try_from(u.as_str()),
+        None => Ok(UnidadeMedida::default()),
+    };
+    let (de, para) = match (unidade("de"), unidade("para")) {
+        (Ok(de), Ok(para)) => (de, para),
+        (Err(e), _) | (_, Err(e)) => return Ok(Json(ApiResponse::error(e))),
+    };
+
+    let catalogo = {
+        let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        Catalogo::carregar(&conn).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    };
+    let coleta = match catalogo.normalizar(tipo, Leitura { quantidade, unidade: de }) {
+        Ok(coleta) => coleta,
+        Err(e) => return Ok(Json(ApiResponse::error(e))),
+    };
+    let Some(convertida) = catalogo.converter(&coleta.tipo, coleta.quantidade_kg, para) else {
+        return Ok(Json(ApiResponse::error(format!(
+            "Tipo '{}' sem densidade cadastrada: conversão para '{}' indisponível",
+            coleta.tipo,
+            para.as_str()
+        ))));
+    };
+
+    Ok(Json(ApiResponse::success(ConversaoResponse {
+        tipo: coleta.tipo,
+        de: coleta.leitura,
+        para: convertida,
+        quantidade_kg: coleta.quantidade_kg,
+    })))
+}
+
+pub async fn salvar_tipo_residuo(
+    State(app_state): State<AppState>,
+    Json(payload): Json<SalvarTipoResiduoRequest>,
+) -> Result<Json<ApiResponse<tipos_residuo::TipoResiduo>>, StatusCode> {
+    let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let usuario = match validar_usuario(&conn, &payload.nome, &payload.senha) {
+        Ok(u) => u,
+        Err(e) => return Ok(Json(ApiResponse::error(format!("Credenciais inválidas: {}", e)))),
+    };
+
+    if !usuario.pode_gerenciar_usuarios() {
+        return Ok(Json(ApiResponse::error("Acesso negado".to_string())));
+    }
+
+    match tipos_residuo::salvar_tipo(&conn, &payload.tipo) {
+        Ok(tipo) => Ok(Json(ApiResponse::success(tipo))),
+        Err(e) => Ok(Json(ApiResponse::error(e))),
+    }
+}
+
+/// Converte o `tipo` das coletas já gravadas para o código do catálogo e
+/// relata os valores sem correspondência.
+pub async fn migrar_tipos_residuo(
+    State(app_state): State<AppState>,
+    Json(payload): Json<MigrarTiposResiduoRequest>,
+) -> Result<Json<ApiResponse<tipos_residuo::RelatorioMigracao>>, StatusCode> {
+    let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let usuario = match validar_usuario(&conn, &payload.nome, &payload.senha) {
+        Ok(u) => u,
+        Err(e) => return Ok(Json(ApiResponse::error(format!("Credenciais inválidas: {}", e)))),
+    };
+
+    if !usuario.pode_gerenciar_usuarios() {
+        return Ok(Json(ApiResponse::error("Acesso negado".to_string())));
+    }
+
+    let catalogo = Catalogo::carregar(&conn).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let db_path = StdPath::new("data/db.json");
+    let mut dataset = Dataset::load_from_file(db_path)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let relatorio = tipos_residuo::migrar_dataset(&mut dataset, &catalogo, payload.simular);
+    if !payload.simular && relatorio.convertidas > 0 {
+        dataset.save_to_file(db_path)
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        tracing::info!(
+            "Tipos de resíduo migrados: {} coleta(s) convertida(s), {} valor(es) sem correspondência",
+            relatorio.convertidas,
+            relatorio.nao_mapeadas.len()
+        );
+    }
+
+    Ok(Json(ApiResponse::success(relatorio)))
+}
+
+// ========== RELATÓRIOS ==========
+
+/// Impacto ambiental das coletas: `?de=&ate=`, com datas AAAA-MM-DD
+/// inclusivas e opcionais.
+pub async fn relatorio_impacto_ambiental(
+    State(app_state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> Result<Json<ApiResponse<relatorios::RelatorioImpacto>>, StatusCode> {
+    let usuario = match usuario_do_cabecalho(&app_state, &headers)? {
+        Ok(u) => u,
+        Err(e) => return Ok(Json(ApiResponse::error(e))),
+    };
+
+    if !usuario.pode_acessar_predicoes() {
+        return Ok(Json(ApiResponse::error("Acesso negado".to_string())));
+    }
+
+    let (de, ate) = match periodo_da_query(&params) {
+        Ok(periodo) => periodo,
+        Err(e) => return Ok(Json(ApiResponse::error(e))),
+    };
+
+    let dataset = Dataset::load_from_file(StdPath::new("data/db.json"))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let relatorio = relatorios::relatorio_impacto(&conn, &dataset, de, ate)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(ApiResponse::success(relatorio)))
+}
+
+// ========== PERGUNTAS À IA ==========
+
+/// Responde perguntas sobre coletas e rotas. A IA só traduz a pergunta em
+/// um plano de consulta; o plano é validado e executado pelo backend, e a
+/// resposta é montada a partir dos números.
+pub async fn perguntar_ia(
+    State(app_state): State<AppState>,
+    Json(payload): Json<PerguntaRequest>,
+) -> Result<Json<ApiResponse<PerguntaResponse>>, StatusCode> {
+    let pergunta = payload.pergunta.as_deref().map(str::trim).filter(|p| !p.is_empty());
+    if pergunta.is_some_and(|p| p.chars().count() > ia_api::MAX_CARACTERES_PERGUNTA) {
+        return Ok(Json(ApiResponse::error(format!(
+            "Pergunta muito longa (máximo de {} caracteres)",
+            ia_api::MAX_CARACTERES_PERGUNTA
+        ))));
+    }
+
     let usuario = {
         let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-        match validar_usuario(&conn, &creds.nome, &creds.senha) {
+        match validar_usuario(&conn, &payload.nome, &payload.senha) {
             Ok(u) => u,
             Err(e) => return Ok(Json(ApiResponse::error(format!("Credenciais inválidas: {}", e)))),
         }
     };
+    if !usuario.pode_acessar_predicoes() {
+        return Ok(Json(ApiResponse::error("Acesso negado".to_string())));
+    }
+
+    let dataset = match Dataset::load_from_file(StdPath::new("data/db.json")) {
+        Ok(dataset) => dataset,
+        Err(e) => return Ok(Json(ApiResponse::error(format!("Erro ao carregar coletas: {}", e)))),
+    };
+
+    // Montar o prompt antes de qualquer await
+    let preparado = {
+        let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        match (&payload.plano, pergunta) {
+            (None, Some(pergunta)) => {
+                let template = match ia_api::carregar_template(&conn, ia_api::PLANO_CONSULTA, payload.idioma) {
+                    Ok(t) => t,
+                    Err(e) => return Ok(Json(ApiResponse::error(e))),
+                };
+                let contexto = ia_api::ContextoPergunta::carregar(&dataset, &conn)
+                    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+                Some((ia_api::montar_prompt_plano(&template, pergunta, &contexto), template))
+            }
+            _ => None,
+        }
+    };
+
+    let (plano, origem_plano, template) = match (payload.plano, preparado) {
+        (Some(plano), _) => (plano, OrigemPlano::Informado, None),
+        (None, Some((prompt, template))) => {
+            let plano = match app_state.ia.gerar(&prompt).await {
+                Ok(texto) => ia_api::interpretar_plano(&texto),
+                Err(e) => Err(format!("IA indisponível: {}", e)),
+            };
+            match plano {
+                Ok(plano) => (plano, OrigemPlano::Ia, Some(template.usado())),
+                Err(e) => {
+                    tracing::warn!("⚠️ Pergunta sem plano válido ({}): {}", app_state.ia.descricao(), e);
+                    return Ok(Json(ApiResponse::error(format!(
+                        "Não foi possível interpretar a pergunta: {}",
+                        e
+                    ))));
+                }
+            }
+        }
+        (None, None) => return Ok(Json(ApiResponse::error("Informe a pergunta ou o plano".to_string()))),
+    };
+
+    let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let resultado = match ia_api::executar_plano(&plano, &dataset, &conn) {
+        Ok(r) => r,
+        Err(e) => return Ok(Json(ApiResponse::error(e))),
+    };
+
+    Ok(Json(ApiResponse::success(PerguntaResponse {
+        pergunta: pergunta.map(str::to_string),
+        resposta: ia_api::descrever_resultado(&plano, &resultado),
+        plano,
+        origem_plano,
+        resultado,
+        template,
+    })))
+}
+
+// ========== TEMPLATES DE PROMPT ==========
+
+fn administrador_do_cabecalho(
+    app_state: &AppState,
+    headers: &HeaderMap,
+) -> Result<Result<Usuario, String>, StatusCode> {
+    Ok(usuario_do_cabecalho(app_state, headers)?.and_then(|usuario| {
+        if usuario.pode_gerenciar_usuarios() {
+            Ok(usuario)
+        } else {
+            Err("Acesso negado".to_string())
+        }
+    }))
+}
+
+/// Versões ativas de cada template e idioma (somente administradores).
+pub async fn listar_templates_ia(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<ApiResponse<Vec<ia_api::PromptTemplate>>>, StatusCode> {
+    if let Err(e) = administrador_do_cabecalho(&app_state, &headers)? {
+        return Ok(Json(ApiResponse::error(e)));
+    }
+
+    let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let templates = ia_api::listar_templates(&conn)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Json(ApiResponse::success(templates)))
+}
+
+/// Histórico de versões de um template; `?idioma=` filtra a variante.
+pub async fn listar_versoes_template_ia(
+    State(app_state): State<AppState>,
+    Path(template): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> Result<Json<ApiResponse<Vec<ia_api::PromptTemplate>>>, StatusCode> {
+    if let Err(e) = administrador_do_cabecalho(&app_state, &headers)? {
+        return Ok(Json(ApiResponse::error(e)));
+    }
+    let idioma = match params.get("idioma").map(|i| ia_api::Idioma::try_from(i.as_str())).transpose() {
+        Ok(idioma) => idioma,
+        Err(e) => return Ok(Json(ApiResponse::error(e))),
+    };
+
+    let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let versoes = ia_api::listar_versoes(&conn, &template, idioma)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if versoes.is_empty() {
+        return Ok(Json(ApiResponse::error(format!("Template '{}' sem versões gravadas", template))));
+    }
+    Ok(Json(ApiResponse::success(versoes)))
+}
+
+pub async fn salvar_template_ia(
+    State(app_state): State<AppState>,
+    Json(payload): Json<SalvarTemplateRequest>,
+) -> Result<Json<ApiResponse<ia_api::PromptTemplate>>, StatusCode> {
+    let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let usuario = match validar_usuario(&conn, &payload.nome, &payload.senha) {
+        Ok(u) => u,
+        Err(e) => return Ok(Json(ApiResponse::error(format!("Credenciais inválidas: {}", e)))),
+    };
 
     if !usuario.pode_gerenciar_usuarios() {
         return Ok(Json(ApiResponse::error("Acesso negado".to_string())));
     }
 
-    if id == usuario.id {
-        return Ok(Json(ApiResponse::error("Você não pode deletar a si mesmo".to_string())));
+    match ia_api::salvar_template(&conn, &payload.template, payload.ativar, usuario.id) {
+        Ok(template) => Ok(Json(ApiResponse::success(template))),
+        Err(e) => Ok(Json(ApiResponse::error(e))),
     }
+}
 
-    // Agora podemos adquirir o lock novamente para a query
+/// Torna ativa uma versão gravada, inclusive uma anterior (rollback).
+pub async fn ativar_template_ia(
+    State(app_state): State<AppState>,
+    Json(payload): Json<AtivarTemplateRequest>,
+) -> Result<Json<ApiResponse<ia_api::PromptTemplate>>, StatusCode> {
     let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    match conn.execute("DELETE FROM usuarios WHERE id = ?1", [id]) {
-        Ok(0) => Ok(Json(ApiResponse::error("Usuário não encontrado".to_string()))),
-        Ok(_) => Ok(Json(ApiResponse::success("Usuário deletado com sucesso".to_string()))),
-        Err(e) => Ok(Json(ApiResponse::error(format!("Erro: {}", e)))),
+    let usuario = match validar_usuario(&conn, &payload.nome, &payload.senha) {
+        Ok(u) => u,
+        Err(e) => return Ok(Json(ApiResponse::error(format!("Credenciais inválidas: {}", e)))),
+    };
+
+    if !usuario.pode_gerenciar_usuarios() {
+        return Ok(Json(ApiResponse::error("Acesso negado".to_string())));
+    }
+
+    match ia_api::ativar_versao(&conn, &payload.template, payload.idioma, payload.versao) {
+        Ok(template) => Ok(Json(ApiResponse::success(template))),
+        Err(e) => Ok(Json(ApiResponse::error(e))),
+    }
+}
+
+/// Renderiza um template com dados de exemplo e, se pedido, envia o
+/// prompt ao provedor de IA para conferir a resposta.
+pub async fn previa_template_ia(
+    State(app_state): State<AppState>,
+    Json(payload): Json<PreviaTemplateRequest>,
+) -> Result<Json<ApiResponse<PreviaTemplateResponse>>, StatusCode> {
+    let (conteudo, usado) = {
+        let conn = app_state.auth.conn.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        let usuario = match validar_usuario(&conn, &payload.nome, &payload.senha) {
+            Ok(u) => u,
+            Err(e) => return Ok(Json(ApiResponse::error(format!("Credenciais inválidas: {}", e)))),
+        };
+
+        if !usuario.pode_gerenciar_usuarios() {
+            return Ok(Json(ApiResponse::error("Acesso negado".to_string())));
+        }
+
+        match &payload.conteudo {
+            Some(rascunho) => match ia_api::validar_conteudo(&payload.template, rascunho) {
+                Ok(()) => (rascunho.clone(), None),
+                Err(e) => return Ok(Json(ApiResponse::error(e))),
+            },
+            None => match ia_api::buscar_versao(&conn, &payload.template, payload.idioma, payload.versao) {
+                Ok(template) => (template.conteudo.clone(), Some(template.usado())),
+                Err(e) => return Ok(Json(ApiResponse::error(e))),
+            },
+        }
+    };
+
+    let e_plano = payload.template == ia_api::PLANO_CONSULTA;
+    let variaveis = if e_plano {
+        let pergunta = payload
+            .pergunta
+            .as_deref()
+            .unwrap_or("Quanto vidro a região Norte coletou no mês passado?");
+        let contexto = ia_api::ContextoPergunta {
+            tipos_residuo: ["metal", "papel", "plastico", "vidro"].map(String::from).to_vec(),
+            regioes: ["Centro", "Norte", "Sul"].map(String::from).to_vec(),
+        };
+        ia_api::variaveis_plano(pergunta, &contexto, Utc::now().date_naive())
+    } else {
+        let tipo = payload.tipo.as_deref().unwrap_or("plastico");
+        let quantidade = payload.quantidade.unwrap_or(12.5);
+        let estatisticas = ia_api::estatisticas_de_exemplo();
+        let predicao = ia_api::predicao_de_exemplo(quantidade);
+        ia_api::variaveis_analise(tipo, quantidade, &estatisticas, &predicao, payload.idioma)
+    };
+    let prompt = ia_api::renderizar(&conteudo, &variaveis);
+
+    let mut previa = PreviaTemplateResponse {
+        template: usado,
+        variaveis: ia_api::variaveis_do_template(&payload.template).unwrap_or_default().to_vec(),
+        prompt,
+        resposta_ia: None,
+        analise: None,
+        plano: None,
+        erro_ia: None,
+    };
+
+    if payload.executar {
+        match app_state.ia.gerar(&previa.prompt).await {
+            Ok(texto) => {
+                let interpretacao = if e_plano {
+                    ia_api::interpretar_plano(&texto).map(|plano| previa.plano = Some(plano))
+                } else {
+                    ia_api::interpretar_resposta(&texto).map(|mut analise| {
+                        analise.template = previa.template.clone();
+                        previa.analise = Some(analise);
+                    })
+                };
+                if let Err(e) = interpretacao {
+                    previa.erro_ia = Some(format!("resposta da IA inválida: {}", e));
+                }
+                previa.resposta_ia = Some(texto);
+            }
+            Err(e) => previa.erro_ia = Some(format!("IA indisponível: {}", e)),
+        }
     }
+
+    Ok(Json(ApiResponse::success(previa)))
 }
 
 // ========== HEALTH ==========
 
-pub async fn health_check() -> Json<ApiResponse<serde_json::Value>> {
+pub async fn health_check(
+    State(app_state): State<AppState>,
+) -> Json<ApiResponse<serde_json::Value>> {
+    let degradado = app_state.otimizacao.distancias_em_modo_degradado();
     Json(ApiResponse::success(serde_json::json!({
-        "status": "ok",
+        "status": if degradado { "degradado" } else { "ok" },
+        "matriz_distancias": if degradado { "somente_leitura" } else { "ok" },
+        "provedor_ia": app_state.ia.descricao(),
+        "circuito_ia": app_state.ia.circuito(),
         "timestamp": Utc::now().to_rfc3339()
     })))
 }
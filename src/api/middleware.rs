@@ -4,6 +4,7 @@ use rusqlite::Connection;
 #[derive(Clone)]
 pub struct AuthState {
     pub conn: Arc<Mutex<Connection>>,
+    #[allow(dead_code)]
     pub secret: String,
 }
 
@@ -1,6 +1,10 @@
 use serde::{Deserialize, Serialize};
 use crate::auth::Usuario;
-use crate::otimizacao::{PedidoOtimizacao, PedidoNovaDistancia, DadosPrevisao};
+use crate::emissoes::{EstimativaCo2, NovoFatorEmissao};
+use crate::tipos_residuo::TipoResiduo;
+use crate::unidades::{Leitura, UnidadeMedida};
+use crate::ia_api::{AnaliseIA, Idioma, NovoTemplate, PlanoConsulta, ResultadoConsulta, TemplateUsado};
+use crate::otimizacao::{PedidoOtimizacao, PedidoNovaDistancia, DadosPrevisao, EstrategiaRota, StatusRota, PedidoCheckin, PontoColeta, PerfilPesos, Sensor, LeituraSensor};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct LoginRequest {
@@ -51,6 +55,66 @@ pub struct InserirColetaRequest {
     pub tipo: String,
     pub quantidade: f32,
     pub observacoes: Option<String>,
+    /// Unidade de `quantidade` (padrão kg).
+    #[serde(default)]
+    pub unidade: UnidadeMedida,
+    /// Ponto de coleta onde o resíduo foi recolhido.
+    #[serde(default)]
+    pub ponto_id: Option<String>,
+    /// Rota em execução (ou concluída) a que a coleta pertence.
+    #[serde(default)]
+    pub rota_id: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CorrigirColetaRequest {
+    pub nome: String,
+    pub senha: String,
+    pub motivo: String,
+    #[serde(flatten)]
+    pub alteracao: crate::coletas::AlteracaoColeta,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RemoverColetaRequest {
+    pub nome: String,
+    pub senha: String,
+    pub motivo: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImportarColetasRequest {
+    pub nome: String,
+    pub senha: String,
+    #[serde(flatten)]
+    pub pedido: crate::importacao::PedidoImportacao,
+}
+
+/// Coleta listada; com `?unidade=`, traz também a quantidade nessa unidade
+/// quando a conversão é possível.
+#[derive(Debug, Serialize)]
+pub struct ColetaResponse {
+    #[serde(flatten)]
+    pub coleta: crate::dataset::WasteEntry,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub convertida: Option<Leitura>,
+}
+
+/// Página de `GET /coleta`. `total` conta todas as coletas que passam nos
+/// filtros; `next` vai em `?cursor=` para buscar a página seguinte.
+#[derive(Debug, Serialize)]
+pub struct ListaColetasResponse {
+    pub coletas: Vec<ColetaResponse>,
+    pub total: usize,
+    pub next: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConversaoResponse {
+    pub tipo: String,
+    pub de: Leitura,
+    pub para: Leitura,
+    pub quantidade_kg: f32,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -60,6 +124,12 @@ pub struct PredicaoRequest {
     pub tipo: String,
     pub quantidade: f32,
     pub observacoes: Option<String>,
+    /// Unidade de `quantidade` (padrão kg).
+    #[serde(default)]
+    pub unidade: UnidadeMedida,
+    /// Idioma do template do prompt e da análise (padrão pt-BR).
+    #[serde(default)]
+    pub idioma: Idioma,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -74,6 +144,84 @@ pub struct OtimizarRotaRequest {
     pub nome: String,
     pub senha: String,
     pub pedido: PedidoOtimizacao,
+    #[serde(default)]
+    pub estrategia: EstrategiaRota,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AtualizarStatusRotaRequest {
+    pub nome: String,
+    pub senha: String,
+    pub status: StatusRota,
+    pub observacao: Option<String>,
+    /// Placa ou identificador do caminhão (normalmente informado no despacho).
+    pub veiculo_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SalvarPontoRequest {
+    pub nome: String,
+    pub senha: String,
+    pub ponto: PontoColeta,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SalvarPerfilPesosRequest {
+    pub nome: String,
+    pub senha: String,
+    pub perfil: PerfilPesos,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SalvarFatorEmissaoRequest {
+    pub nome: String,
+    pub senha: String,
+    pub fator: NovoFatorEmissao,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SalvarTipoResiduoRequest {
+    pub nome: String,
+    pub senha: String,
+    pub tipo: TipoResiduo,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MigrarTiposResiduoRequest {
+    pub nome: String,
+    pub senha: String,
+    /// Só gera o relatório, sem alterar `data/db.json`.
+    #[serde(default)]
+    pub simular: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SalvarSensorRequest {
+    pub nome: String,
+    pub senha: String,
+    pub sensor: Sensor,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RegistrarLeiturasRequest {
+    pub nome: String,
+    pub senha: String,
+    pub leituras: Vec<LeituraSensor>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImportarLeiturasRequest {
+    pub nome: String,
+    pub senha: String,
+    /// Conteúdo CSV `sensor_id,ponto_id,nivel_percentual,bateria_percentual,medido_em`.
+    pub csv: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CheckinRotaRequest {
+    pub nome: String,
+    pub senha: String,
+    pub checkin: PedidoCheckin,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -83,6 +231,17 @@ pub struct AdicionarDistanciaRequest {
     pub distancia: PedidoNovaDistancia,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImportarDistanciasRequest {
+    pub nome: String,
+    pub senha: String,
+    /// Conteúdo CSV `origem,destino,custo`.
+    pub csv: String,
+    /// Mesmo padrão da inclusão de um trecho: grava também o sentido inverso.
+    #[serde(default = "crate::otimizacao::padrao_simetrica")]
+    pub simetrica: bool,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AtualizarPrevisaoRequest {
     pub nome: String,
@@ -93,17 +252,13 @@ pub struct AtualizarPrevisaoRequest {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PredicaoResponse {
     pub predicao: crate::predicao::Predicao,
+    /// Mesmo texto de `analise.resumo`.
     pub analise_ia: String,
-    pub co2_estimado: Co2Estimado,
+    pub analise: AnaliseIA,
+    pub estatisticas: crate::ia_api::EstatisticasPredicao,
+    pub co2_estimado: EstimativaCo2,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Co2Estimado {
-    pub tipo: String,
-    pub quantidade: f32,
-    pub fator: f32,
-    pub co2_evitado: f32,
-}
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ApiResponse<T> {
@@ -130,8 +285,117 @@ impl<T> ApiResponse<T> {
     }
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ErrorResponse {
     pub error: String,
 }
 
+
+/// Mensagens do cliente no WebSocket de rastreamento.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "acao", rename_all = "snake_case")]
+pub enum ComandoRastreamento {
+    Assinar { rota_id: i64 },
+    Cancelar { rota_id: i64 },
+}
+
+/// Pedido de execução em segundo plano (`POST /jobs`). O campo `job`
+/// escolhe a operação; o resto do corpo é o do endpoint síncrono.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "job", rename_all = "snake_case")]
+pub enum PedidoJob {
+    Predicao(PredicaoRequest),
+    Preprocessamento(AuthCredentials),
+    Otimizacao(OtimizarRotaRequest),
+    Importacao(ImportarColetasRequest),
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SalvarTemplateRequest {
+    pub nome: String,
+    pub senha: String,
+    pub template: NovoTemplate,
+    /// Sem ativar, a versão fica guardada para prévia e ativação depois.
+    #[serde(default = "ativar_por_padrao")]
+    pub ativar: bool,
+}
+
+fn ativar_por_padrao() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AtivarTemplateRequest {
+    pub nome: String,
+    pub senha: String,
+    pub template: String,
+    #[serde(default)]
+    pub idioma: Idioma,
+    pub versao: u32,
+}
+
+/// Prévia de um template com dados de exemplo. Sem `conteudo`, usa a versão
+/// gravada (`versao`, ou a ativa); com `conteudo`, testa um rascunho.
+#[derive(Debug, Deserialize)]
+pub struct PreviaTemplateRequest {
+    pub nome: String,
+    pub senha: String,
+    pub template: String,
+    #[serde(default)]
+    pub idioma: Idioma,
+    pub versao: Option<u32>,
+    pub conteudo: Option<String>,
+    /// Tipo e quantidade da coleta de exemplo (`analise_predicao`).
+    pub tipo: Option<String>,
+    pub quantidade: Option<f32>,
+    /// Pergunta de exemplo (`plano_consulta`).
+    pub pergunta: Option<String>,
+    /// Envia o prompt ao provedor de IA configurado e interpreta a resposta.
+    #[serde(default)]
+    pub executar: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PreviaTemplateResponse {
+    /// Versão gravada usada; ausente na prévia de rascunho.
+    pub template: Option<TemplateUsado>,
+    pub variaveis: Vec<&'static str>,
+    pub prompt: String,
+    pub resposta_ia: Option<String>,
+    pub analise: Option<AnaliseIA>,
+    pub plano: Option<PlanoConsulta>,
+    pub erro_ia: Option<String>,
+}
+
+/// Pergunta em linguagem natural ou, para repetir uma consulta, o plano
+/// devolvido por uma resposta anterior.
+#[derive(Debug, Deserialize)]
+pub struct PerguntaRequest {
+    pub nome: String,
+    pub senha: String,
+    pub pergunta: Option<String>,
+    pub plano: Option<PlanoConsulta>,
+    #[serde(default)]
+    pub idioma: Idioma,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum OrigemPlano {
+    /// Gerado pela IA a partir da pergunta.
+    Ia,
+    /// Enviado no pedido.
+    Informado,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PerguntaResponse {
+    pub pergunta: Option<String>,
+    pub resposta: String,
+    pub plano: PlanoConsulta,
+    pub origem_plano: OrigemPlano,
+    pub resultado: ResultadoConsulta,
+    /// Versão do template usada para gerar o plano.
+    pub template: Option<TemplateUsado>,
+}
@@ -1,5 +1,5 @@
 use axum::{
-    routing::{get, post, delete},
+    routing::{get, post, patch, delete},
     Router,
 };
 use std::sync::Arc;
@@ -12,16 +12,16 @@ use tracing::Level;
 
 use crate::api::handlers::*;
 use crate::api::middleware::AuthState;
+use crate::ia_api::ProvedorIA;
+use crate::jobs::RegistroJobs;
 use crate::otimizacao::EstadoOtimizacao;
-use rusqlite::Connection;
 
 pub fn create_router(
-    conn: Connection,
+    auth_state: AuthState,
     estado_otim: Arc<EstadoOtimizacao>,
-    secret: String,
+    jobs: Arc<RegistroJobs>,
+    ia: Arc<dyn ProvedorIA>,
 ) -> Router {
-    let auth_state = AuthState::new(conn, secret);
-
     let public_routes = Router::new()
         .route("/health", get(health_check))
         .route("/auth/login", post(login));
@@ -30,11 +30,64 @@ pub fn create_router(
         .route("/auth/usuarios", post(criar_usuario))
         .route("/coleta", post(inserir_coleta))
         .route("/coleta", get(listar_coletas))
+        .route("/coleta/agregado", get(agregar_coletas))
+        .route("/coleta/importar", post(importar_coletas))
+        .route("/coleta/importacoes", get(listar_importacoes_coletas))
+        .route("/coleta/:id", patch(corrigir_coleta))
+        .route("/coleta/:id", delete(remover_coleta))
+        .route("/coleta/:id/historico", get(historico_coleta))
         .route("/preprocessamento/executar", post(executar_preprocessamento))
         .route("/predicoes", post(executar_predicao))
         .route("/otimizacao/rotas", post(otimizar_rota))
+        .route("/otimizacao/rotas", get(listar_rotas_otimizadas))
+        .route("/otimizacao/rotas/:id", get(buscar_rota_otimizada))
+        .route("/otimizacao/rotas/:id/status", post(atualizar_status_rota))
+        .route("/otimizacao/rotas/:id/checkins", post(registrar_checkin_rota))
+        .route("/otimizacao/rotas/:id/execucao", get(buscar_execucao_rota))
+        .route("/otimizacao/rotas/:id/geojson", get(exportar_rota_geojson))
+        .route("/otimizacao/rotas/:id/gpx", get(exportar_rota_gpx))
+        .route("/otimizacao/pontos", post(salvar_ponto_coleta))
+        .route("/otimizacao/pontos", get(listar_pontos_coleta))
+        .route("/otimizacao/perfis-pesos", post(salvar_perfil_pesos))
+        .route("/otimizacao/perfis-pesos", get(listar_perfis_pesos))
+        .route("/otimizacao/sensores", post(salvar_sensor))
+        .route("/otimizacao/sensores", get(listar_sensores))
+        .route("/otimizacao/sensores/leituras", post(registrar_leituras_sensores))
+        .route("/otimizacao/sensores/leituras/csv", post(importar_leituras_sensores))
+        .route("/otimizacao/sensores/enchimento", get(estado_enchimento_pontos))
+        .route("/otimizacao/veiculos/posicoes", get(posicoes_veiculos))
+        .route("/otimizacao/rastreamento/ws", get(rastreamento_ws))
+        .route("/otimizacao/relatorios/planejado-vs-real", get(relatorio_execucao_rotas))
         .route("/otimizacao/distancias", post(adicionar_distancia))
+        .route("/otimizacao/distancias", get(listar_distancias))
+        .route("/otimizacao/distancias/importar", post(importar_distancias))
+        .route("/otimizacao/distancias/exportar", get(exportar_distancias))
+        .route("/otimizacao/distancias/conectividade", get(conectividade_distancias))
+        .route("/otimizacao/distancias/ponto/:ponto", delete(remover_ponto_distancias))
+        .route("/otimizacao/distancias/:origem/:destino", delete(remover_distancia))
         .route("/otimizacao/previsao-demanda", post(atualizar_previsao_demanda))
+        .route("/otimizacao/demanda", get(demanda_por_regiao))
+        .route("/otimizacao/demanda/:ponto_id/historico", get(historico_previsoes_ponto))
+        .route("/jobs", post(iniciar_job))
+        .route("/jobs", get(listar_jobs))
+        .route("/jobs/eventos", get(eventos_jobs))
+        .route("/jobs/:id", get(buscar_job))
+        .route("/jobs/:id/eventos", get(eventos_job))
+        .route("/fatores-emissao", post(salvar_fator_emissao))
+        .route("/fatores-emissao", get(listar_fatores_emissao))
+        .route("/fatores-emissao/estimativa", get(estimar_co2))
+        .route("/fatores-emissao/:id", delete(remover_fator_emissao))
+        .route("/tipos-residuo", post(salvar_tipo_residuo))
+        .route("/tipos-residuo", get(listar_tipos_residuo))
+        .route("/tipos-residuo/migrar", post(migrar_tipos_residuo))
+        .route("/tipos-residuo/converter", get(converter_unidade))
+        .route("/relatorios/impacto", get(relatorio_impacto_ambiental))
+        .route("/ia/perguntar", post(perguntar_ia))
+        .route("/ia/templates", post(salvar_template_ia))
+        .route("/ia/templates", get(listar_templates_ia))
+        .route("/ia/templates/ativar", post(ativar_template_ia))
+        .route("/ia/templates/previa", post(previa_template_ia))
+        .route("/ia/templates/:template/versoes", get(listar_versoes_template_ia))
         .route("/usuarios", post(listar_usuarios))
         .route("/usuarios/:id", delete(deletar_usuario));
 
@@ -48,7 +101,8 @@ pub fn create_router(
                         Level::INFO,
                         "request",
                         method = %request.method(),
-                        uri = %request.uri(),
+                        // Só o caminho: a query string pode trazer dados do cliente
+                        path = %request.uri().path(),
                         version = ?request.version(),
                     )
                 })
@@ -56,7 +110,7 @@ pub fn create_router(
                     tracing::info!(
                         "📥 Requisição recebida: {} {}",
                         request.method(),
-                        request.uri()
+                        request.uri().path()
                     );
                 })
                 .on_response(|_response: &axum::http::Response<_>, latency: std::time::Duration, _span: &tracing::Span| {
@@ -78,6 +132,8 @@ pub fn create_router(
         .with_state(AppState {
             auth: auth_state,
             otimizacao: estado_otim,
+            jobs,
+            ia,
         })
 }
 
@@ -85,5 +141,7 @@ pub fn create_router(
 pub struct AppState {
     pub auth: AuthState,
     pub otimizacao: Arc<EstadoOtimizacao>,
+    pub jobs: Arc<RegistroJobs>,
+    pub ia: Arc<dyn ProvedorIA>,
 }
 
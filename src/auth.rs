@@ -68,17 +68,21 @@ impl Usuario {
         self.pode_pre_processar()
     }
 
+    /// Corrigir ou remover coletas de qualquer usuário, sem prazo.
+    pub fn pode_corrigir_coletas(&self) -> bool {
+        matches!(self.perfil, PerfilUsuario::Tecnico | PerfilUsuario::Administrador)
+    }
+
+    /// Importar histórico de coletas em lote.
+    pub fn pode_importar_coletas(&self) -> bool {
+        matches!(self.perfil, PerfilUsuario::Tecnico | PerfilUsuario::Administrador)
+    }
+
     pub fn pode_otimizar_rotas(&self) -> bool {
-        match self.perfil {
-            PerfilUsuario::Tecnico | PerfilUsuario::Administrador => true,
-            _ => false,
-        }
+        matches!(self.perfil, PerfilUsuario::Tecnico | PerfilUsuario::Administrador)
     }
     
     pub fn pode_gerenciar_usuarios(&self) -> bool {
-        match self.perfil {
-            PerfilUsuario::Administrador => true,
-            _ => false,
-        }
+        matches!(self.perfil, PerfilUsuario::Administrador)
     }
 }
\ No newline at end of file
@@ -0,0 +1,669 @@
+// src/coletas.rs
+// Consulta, correção e remoção de coletas já gravadas em `data/db.json`.
+//
+// A correção altera a coleta no próprio arquivo, de modo que treino e
+// relatórios passam a usar o valor corrigido; o valor anterior e o motivo
+// ficam em `coletas_correcoes`. Coletas removidas vão para
+// `Dataset::removidas` e saem de todos os cálculos.
+
+use crate::auth::Usuario;
+use crate::dataset::{Dataset, WasteEntry};
+use crate::tipos_residuo::Catalogo;
+use crate::unidades::{Leitura, UnidadeMedida};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, SecondsFormat, Utc};
+use rusqlite::types::Type;
+use rusqlite::{params, Connection, Row};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+
+// ========== CONSULTA ==========
+
+/// Filtros de `GET /coleta` e `GET /coleta/agregado`; os ausentes não
+/// restringem nada.
+#[derive(Debug, Clone, Default)]
+pub struct FiltroColetas {
+    /// Código ou apelido do catálogo; coletas não migradas também entram.
+    pub tipo: Option<String>,
+    /// Datas inclusivas, comparadas com o dia (UTC) do `timestamp`.
+    pub de: Option<NaiveDate>,
+    pub ate: Option<NaiveDate>,
+    /// Em kg.
+    pub quantidade_min: Option<f32>,
+    pub quantidade_max: Option<f32>,
+    pub usuario_id: Option<u32>,
+    pub ponto_id: Option<String>,
+    pub rota_id: Option<i64>,
+    /// Região do ponto de coleta.
+    pub regiao: Option<String>,
+}
+
+impl FiltroColetas {
+    /// Técnico e Administrador consultam as coletas de todos; Comum só as
+    /// próprias, como no histórico de correções.
+    pub fn restringir_ao_usuario(&mut self, usuario: &Usuario) -> Result<(), String> {
+        if usuario.pode_corrigir_coletas() {
+            return Ok(());
+        }
+        match self.usuario_id {
+            Some(id) if id != usuario.id => {
+                Err("Acesso negado: só é possível consultar as próprias coletas".to_string())
+            }
+            _ => {
+                self.usuario_id = Some(usuario.id);
+                Ok(())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum CampoOrdenacao {
+    #[default]
+    Timestamp,
+    Quantidade,
+    Tipo,
+}
+
+impl TryFrom<&str> for CampoOrdenacao {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value.trim() {
+            "timestamp" => Ok(CampoOrdenacao::Timestamp),
+            "quantidade" => Ok(CampoOrdenacao::Quantidade),
+            "tipo" => Ok(CampoOrdenacao::Tipo),
+            outro => Err(format!(
+                "Ordenação inválida: '{}' (use timestamp, quantidade ou tipo)",
+                outro
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Ordenacao {
+    pub campo: CampoOrdenacao,
+    pub decrescente: bool,
+}
+
+impl Ordenacao {
+    /// Empates são desfeitos pelo `id`, para o cursor ter uma posição única.
+    fn comparar(&self, a: &WasteEntry, b: &WasteEntry) -> Ordering {
+        let ordem = match self.campo {
+            CampoOrdenacao::Timestamp => a.timestamp.cmp(&b.timestamp),
+            CampoOrdenacao::Quantidade => a.quantidade.total_cmp(&b.quantidade),
+            CampoOrdenacao::Tipo => a.tipo.cmp(&b.tipo),
+        }
+        .then_with(|| a.id.cmp(&b.id));
+        if self.decrescente {
+            ordem.reverse()
+        } else {
+            ordem
+        }
+    }
+
+    /// Como `comparar`, contra a posição guardada num cursor.
+    fn comparar_com_cursor(&self, coleta: &WasteEntry, cursor: &Cursor) -> Ordering {
+        let ordem = match &cursor.valor {
+            ValorCursor::Timestamp(timestamp) => coleta.timestamp.cmp(timestamp),
+            ValorCursor::Quantidade(quantidade) => coleta.quantidade.total_cmp(quantidade),
+            ValorCursor::Tipo(tipo) => coleta.tipo.as_str().cmp(tipo.as_str()),
+        }
+        .then_with(|| coleta.id.cmp(&cursor.id));
+        if self.decrescente {
+            ordem.reverse()
+        } else {
+            ordem
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum ValorCursor {
+    Timestamp(DateTime<Utc>),
+    Quantidade(f32),
+    Tipo(String),
+}
+
+/// Posição da última coleta de uma página, em `<valor>:<id>`: o valor do
+/// campo de ordenação e o `id` que desfaz empates. Por guardar o valor, o
+/// cursor não depende de a coleta continuar igual no dataset.
+#[derive(Debug, Clone, PartialEq)]
+struct Cursor {
+    valor: ValorCursor,
+    id: u64,
+}
+
+impl Cursor {
+    fn da_coleta(campo: CampoOrdenacao, coleta: &WasteEntry) -> Self {
+        let valor = match campo {
+            CampoOrdenacao::Timestamp => ValorCursor::Timestamp(coleta.timestamp),
+            CampoOrdenacao::Quantidade => ValorCursor::Quantidade(coleta.quantidade),
+            CampoOrdenacao::Tipo => ValorCursor::Tipo(coleta.tipo.clone()),
+        };
+        Cursor { valor, id: coleta.id }
+    }
+
+    /// Lê o cursor de acordo com o campo da ordenação. O `id` vem depois do
+    /// último `:`, já que datas e tipos podem conter `:`.
+    fn ler(campo: CampoOrdenacao, texto: &str) -> Result<Self, String> {
+        let invalido = || format!("Cursor inválido: '{}'", texto);
+        let (valor, id) = texto.trim().rsplit_once(':').ok_or_else(invalido)?;
+        let id = id.parse::<u64>().map_err(|_| invalido())?;
+        let valor = match campo {
+            CampoOrdenacao::Timestamp => DateTime::parse_from_rfc3339(valor)
+                .map(|t| ValorCursor::Timestamp(t.with_timezone(&Utc)))
+                .map_err(|_| invalido())?,
+            CampoOrdenacao::Quantidade => valor
+                .parse::<f32>()
+                .map(ValorCursor::Quantidade)
+                .map_err(|_| invalido())?,
+            CampoOrdenacao::Tipo => ValorCursor::Tipo(valor.to_string()),
+        };
+        Ok(Cursor { valor, id })
+    }
+}
+
+impl std::fmt::Display for Cursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.valor {
+            // `Z` em vez de `+00:00`, que viraria espaço na query string
+            // sem codificação.
+            ValorCursor::Timestamp(t) => write!(f, "{}", t.to_rfc3339_opts(SecondsFormat::AutoSi, true))?,
+            ValorCursor::Quantidade(q) => write!(f, "{}", q)?,
+            ValorCursor::Tipo(t) => write!(f, "{}", t)?,
+        }
+        write!(f, ":{}", self.id)
+    }
+}
+
+/// Uma página de coletas. `total` conta todas as que passam nos filtros;
+/// `next` é o cursor da página seguinte, ausente na última.
+#[derive(Debug)]
+pub struct PaginaColetas<'a> {
+    pub coletas: Vec<&'a WasteEntry>,
+    pub total: usize,
+    pub next: Option<String>,
+}
+
+/// Dados auxiliares dos filtros, carregados uma vez por consulta.
+pub struct ContextoConsulta<'a> {
+    pub catalogo: &'a Catalogo,
+    pub regiao_do_ponto: HashMap<String, String>,
+}
+
+impl ContextoConsulta<'_> {
+    /// Tipo da coleta pelo código do catálogo; valores fora dele ficam
+    /// normalizados (minúsculas, sem acento).
+    fn tipo(&self, coleta: &WasteEntry) -> String {
+        self.catalogo.chave(&coleta.tipo)
+    }
+
+    fn aceita(&self, filtro: &FiltroColetas, tipo: Option<&str>, coleta: &WasteEntry) -> bool {
+        let data = coleta.timestamp.date_naive();
+        let regiao = || {
+            coleta
+                .ponto_id
+                .as_ref()
+                .and_then(|p| self.regiao_do_ponto.get(p))
+                .map(|r| r.as_str())
+        };
+
+        tipo.is_none_or(|tipo| self.tipo(coleta) == tipo)
+            && filtro.de.is_none_or(|de| data >= de)
+            && filtro.ate.is_none_or(|ate| data <= ate)
+            && filtro.quantidade_min.is_none_or(|min| coleta.quantidade >= min)
+            && filtro.quantidade_max.is_none_or(|max| coleta.quantidade <= max)
+            && filtro.usuario_id.is_none_or(|id| coleta.usuario_id == Some(id))
+            && filtro.ponto_id.as_ref().is_none_or(|p| coleta.ponto_id.as_ref() == Some(p))
+            && filtro.rota_id.is_none_or(|id| coleta.rota_id == Some(id))
+            && filtro
+                .regiao
+                .as_ref()
+                .is_none_or(|r| regiao().is_some_and(|regiao| regiao.eq_ignore_ascii_case(r.trim())))
+    }
+
+    /// Coletas ativas que passam nos filtros, na ordem do arquivo.
+    pub fn filtrar<'d>(&self, dataset: &'d Dataset, filtro: &FiltroColetas) -> Vec<&'d WasteEntry> {
+        let tipo = filtro.tipo.as_ref().map(|t| self.catalogo.chave(t));
+        dataset
+            .entries
+            .iter()
+            .filter(|e| self.aceita(filtro, tipo.as_deref(), e))
+            .collect()
+    }
+}
+
+/// Ordena e recorta as coletas filtradas. O cursor guarda a posição da última
+/// coleta da página anterior e só vale com os mesmos filtros e ordenação; se
+/// essa coleta mudou ou foi removida, a página continua a partir do valor que
+/// ela tinha. Sem cursor, `offset` pula as primeiras.
+pub fn paginar<'d>(
+    mut coletas: Vec<&'d WasteEntry>,
+    ordenacao: Ordenacao,
+    cursor: Option<&str>,
+    offset: usize,
+    limit: usize,
+) -> Result<PaginaColetas<'d>, String> {
+    coletas.sort_by(|a, b| ordenacao.comparar(a, b));
+
+    let inicio = match cursor {
+        Some(cursor) => {
+            let cursor = Cursor::ler(ordenacao.campo, cursor)?;
+            coletas.partition_point(|e| ordenacao.comparar_com_cursor(e, &cursor) != Ordering::Greater)
+        }
+        None => offset.min(coletas.len()),
+    };
+
+    let total = coletas.len();
+    let fim = inicio.saturating_add(limit).min(total);
+    let next = (fim < total && fim > inicio)
+        .then(|| Cursor::da_coleta(ordenacao.campo, coletas[fim - 1]).to_string());
+    coletas.truncate(fim);
+    coletas.drain(..inicio);
+
+    Ok(PaginaColetas { coletas, total, next })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PeriodoAgregacao {
+    Dia,
+    Semana,
+    Mes,
+}
+
+impl PeriodoAgregacao {
+    /// Primeiro dia do período que contém `data`; semanas começam na segunda.
+    fn inicio(&self, data: NaiveDate) -> NaiveDate {
+        match self {
+            PeriodoAgregacao::Dia => data,
+            PeriodoAgregacao::Semana => data - Duration::days(data.weekday().num_days_from_monday() as i64),
+            PeriodoAgregacao::Mes => data.with_day(1).unwrap_or(data),
+        }
+    }
+
+    fn rotulo(&self, inicio: NaiveDate) -> String {
+        match self {
+            PeriodoAgregacao::Dia => inicio.format("%Y-%m-%d").to_string(),
+            PeriodoAgregacao::Semana => {
+                let semana = inicio.iso_week();
+                format!("{}-W{:02}", semana.year(), semana.week())
+            }
+            PeriodoAgregacao::Mes => inicio.format("%Y-%m").to_string(),
+        }
+    }
+}
+
+impl TryFrom<&str> for PeriodoAgregacao {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value.trim() {
+            "dia" => Ok(PeriodoAgregacao::Dia),
+            "semana" => Ok(PeriodoAgregacao::Semana),
+            "mes" => Ok(PeriodoAgregacao::Mes),
+            outro => Err(format!("Período inválido: '{}' (use dia, semana ou mes)", outro)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GrupoColetas {
+    /// `2024-01-15`, `2024-W03` ou `2024-01`.
+    pub periodo: String,
+    pub inicio: NaiveDate,
+    pub tipo: String,
+    pub contagem: usize,
+    pub soma_kg: f64,
+    pub media_kg: f64,
+}
+
+fn arredondar(valor: f64) -> f64 {
+    (valor * 100.0).round() / 100.0
+}
+
+/// Soma, média e contagem por período e tipo, em ordem cronológica.
+pub fn agregar(
+    contexto: &ContextoConsulta,
+    coletas: &[&WasteEntry],
+    periodo: PeriodoAgregacao,
+) -> Vec<GrupoColetas> {
+    let mut grupos: BTreeMap<(NaiveDate, String), (usize, f64)> = BTreeMap::new();
+    for coleta in coletas {
+        let inicio = periodo.inicio(coleta.timestamp.date_naive());
+        let grupo = grupos.entry((inicio, contexto.tipo(coleta))).or_default();
+        grupo.0 += 1;
+        grupo.1 += coleta.quantidade as f64;
+    }
+
+    grupos
+        .into_iter()
+        .map(|((inicio, tipo), (contagem, soma))| GrupoColetas {
+            periodo: periodo.rotulo(inicio),
+            inicio,
+            tipo,
+            contagem,
+            soma_kg: arredondar(soma),
+            media_kg: arredondar(soma / contagem as f64),
+        })
+        .collect()
+}
+
+// ========== CORREÇÃO ==========
+
+/// Prazo para um usuário Comum corrigir as próprias coletas.
+const PRAZO_CORRECAO_COMUM_HORAS: i64 = 24;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AcaoCorrecao {
+    Edicao,
+    Remocao,
+}
+
+impl AcaoCorrecao {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AcaoCorrecao::Edicao => "edicao",
+            AcaoCorrecao::Remocao => "remocao",
+        }
+    }
+}
+
+/// Campos a corrigir; os ausentes ficam como estão.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AlteracaoColeta {
+    pub tipo: Option<String>,
+    /// Na unidade de `unidade`; sem ela, na unidade da leitura original.
+    pub quantidade: Option<f32>,
+    pub unidade: Option<UnidadeMedida>,
+    /// Texto vazio apaga a observação.
+    pub observacoes: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CorrecaoColeta {
+    pub id: i64,
+    pub coleta_id: u64,
+    pub acao: AcaoCorrecao,
+    pub anterior: WasteEntry,
+    /// Ausente na remoção.
+    pub novo: Option<WasteEntry>,
+    pub motivo: String,
+    pub usuario_id: u32,
+    pub registrado_em: String,
+}
+
+/// Técnico e Administrador corrigem qualquer coleta; Comum só as próprias,
+/// até 24h depois do registro (não da data da coleta, que pode ser antiga
+/// numa importação).
+fn verificar_permissao(usuario: &Usuario, coleta: &WasteEntry) -> Result<(), String> {
+    if usuario.pode_corrigir_coletas() {
+        return Ok(());
+    }
+    if coleta.usuario_id != Some(usuario.id) {
+        return Err("Acesso negado: só é possível corrigir as próprias coletas".to_string());
+    }
+    // Coletas gravadas antes de existir `registrada_em` foram registradas na
+    // hora, então o `timestamp` serve de registro.
+    let registrada_em = coleta.registrada_em.unwrap_or(coleta.timestamp);
+    if Utc::now() - registrada_em > Duration::hours(PRAZO_CORRECAO_COMUM_HORAS) {
+        return Err(format!(
+            "Acesso negado: coletas só podem ser corrigidas até {}h após o registro",
+            PRAZO_CORRECAO_COMUM_HORAS
+        ));
+    }
+    Ok(())
+}
+
+/// Técnico e Administrador leem o histórico de qualquer coleta, inclusive
+/// removida; Comum só o das próprias.
+pub fn verificar_acesso_historico(dataset: &Dataset, usuario: &Usuario, id: u64) -> Result<(), String> {
+    let coleta = dataset
+        .entries
+        .iter()
+        .chain(&dataset.removidas)
+        .find(|e| e.id == id)
+        .ok_or_else(|| format!("Coleta {} não encontrada", id))?;
+    if !usuario.pode_corrigir_coletas() && coleta.usuario_id != Some(usuario.id) {
+        return Err("Acesso negado: só é possível consultar o histórico das próprias coletas".to_string());
+    }
+    Ok(())
+}
+
+fn validar_motivo(motivo: &str) -> Result<String, String> {
+    let motivo = motivo.trim();
+    if motivo.is_empty() {
+        return Err("Informe o motivo da correção".to_string());
+    }
+    Ok(motivo.to_string())
+}
+
+/// Aplica a alteração à coleta `id` do dataset. O registro no histórico fica
+/// a cargo de `registrar_correcao`, depois de o dataset ser gravado.
+pub fn corrigir_coleta(
+    dataset: &mut Dataset,
+    catalogo: &Catalogo,
+    usuario: &Usuario,
+    id: u64,
+    alteracao: &AlteracaoColeta,
+    motivo: &str,
+) -> Result<CorrecaoColeta, String> {
+    let motivo = validar_motivo(motivo)?;
+    let coleta = dataset
+        .buscar_mut(id)
+        .ok_or_else(|| format!("Coleta {} não encontrada", id))?;
+    verificar_permissao(usuario, coleta)?;
+
+    if alteracao.tipo.is_none()
+        && alteracao.quantidade.is_none()
+        && alteracao.unidade.is_none()
+        && alteracao.observacoes.is_none()
+    {
+        return Err("Nenhum campo para corrigir".to_string());
+    }
+
+    let anterior = coleta.clone();
+
+    // Tipo e quantidade são convertidos juntos: trocar o tipo de uma coleta
+    // registrada em volume muda a densidade e, com ela, o peso
+    if alteracao.tipo.is_some() || alteracao.quantidade.is_some() || alteracao.unidade.is_some() {
+        let original = coleta.leitura.unwrap_or(Leitura {
+            quantidade: coleta.quantidade,
+            unidade: UnidadeMedida::Kg,
+        });
+        let leitura = Leitura {
+            quantidade: alteracao.quantidade.unwrap_or(original.quantidade),
+            unidade: alteracao.unidade.unwrap_or(original.unidade),
+        };
+        let tipo = alteracao.tipo.as_deref().unwrap_or(&coleta.tipo);
+        let normalizada = catalogo.normalizar(tipo, leitura)?;
+
+        coleta.tipo = normalizada.tipo;
+        coleta.quantidade = normalizada.quantidade_kg;
+        if coleta.leitura.is_some() || alteracao.quantidade.is_some() || alteracao.unidade.is_some() {
+            coleta.leitura = Some(normalizada.leitura);
+        }
+    }
+    if let Some(observacoes) = &alteracao.observacoes {
+        let observacoes = observacoes.trim();
+        coleta.observacoes = (!observacoes.is_empty()).then(|| observacoes.to_string());
+    }
+
+    Ok(CorrecaoColeta {
+        id: 0,
+        coleta_id: id,
+        acao: AcaoCorrecao::Edicao,
+        anterior,
+        novo: Some(coleta.clone()),
+        motivo,
+        usuario_id: usuario.id,
+        registrado_em: Utc::now().to_rfc3339(),
+    })
+}
+
+/// Remove a coleta `id` do dataset, guardando-a em `removidas`.
+pub fn remover_coleta(
+    dataset: &mut Dataset,
+    usuario: &Usuario,
+    id: u64,
+    motivo: &str,
+) -> Result<CorrecaoColeta, String> {
+    let motivo = validar_motivo(motivo)?;
+    let coleta = dataset
+        .buscar(id)
+        .ok_or_else(|| format!("Coleta {} não encontrada", id))?;
+    verificar_permissao(usuario, coleta)?;
+
+    let agora = Utc::now();
+    let anterior = dataset
+        .remover(id, agora)
+        .ok_or_else(|| format!("Coleta {} não encontrada", id))?;
+
+    Ok(CorrecaoColeta {
+        id: 0,
+        coleta_id: id,
+        acao: AcaoCorrecao::Remocao,
+        anterior,
+        novo: None,
+        motivo,
+        usuario_id: usuario.id,
+        registrado_em: agora.to_rfc3339(),
+    })
+}
+
+pub fn registrar_correcao(conn: &Connection, correcao: &mut CorrecaoColeta) -> Result<(), String> {
+    let anterior = serde_json::to_string(&correcao.anterior).map_err(|e| e.to_string())?;
+    let novo = correcao
+        .novo
+        .as_ref()
+        .map(serde_json::to_string)
+        .transpose()
+        .map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "INSERT INTO coletas_correcoes
+            (coleta_id, acao, anterior_json, novo_json, motivo, usuario_id, registrado_em)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            correcao.coleta_id,
+            correcao.acao.as_str(),
+            anterior,
+            novo,
+            correcao.motivo,
+            correcao.usuario_id,
+            correcao.registrado_em,
+        ],
+    )
+    .map_err(|e| format!("Erro ao registrar correção: {}", e))?;
+    correcao.id = conn.last_insert_rowid();
+    Ok(())
+}
+
+fn json_da_coluna(row: &Row, idx: usize) -> rusqlite::Result<Option<WasteEntry>> {
+    row.get::<_, Option<String>>(idx)?
+        .map(|json| serde_json::from_str(&json))
+        .transpose()
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(idx, Type::Text, Box::new(e)))
+}
+
+/// Correções de uma coleta, da mais antiga para a mais recente.
+pub fn listar_correcoes(conn: &Connection, coleta_id: u64) -> rusqlite::Result<Vec<CorrecaoColeta>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, coleta_id, acao, anterior_json, novo_json, motivo, usuario_id, registrado_em
+         FROM coletas_correcoes
+         WHERE coleta_id = ?1
+         ORDER BY id",
+    )?;
+
+    let correcoes = stmt
+        .query_map([coleta_id], |row| {
+            let acao = match row.get::<_, String>(2)?.as_str() {
+                "remocao" => AcaoCorrecao::Remocao,
+                _ => AcaoCorrecao::Edicao,
+            };
+            let anterior = json_da_coluna(row, 3)?.ok_or(rusqlite::Error::InvalidColumnType(
+                3,
+                "anterior_json".to_string(),
+                Type::Null,
+            ))?;
+            Ok(CorrecaoColeta {
+                id: row.get(0)?,
+                coleta_id: row.get(1)?,
+                acao,
+                anterior,
+                novo: json_da_coluna(row, 4)?,
+                motivo: row.get(5)?,
+                usuario_id: row.get(6)?,
+                registrado_em: row.get(7)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    Ok(correcoes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dataset::VinculosColeta;
+    use chrono::TimeZone;
+
+    fn dataset() -> Dataset {
+        let mut dataset = Dataset::new();
+        let coletas = [
+            ("plastico", 2.0, 10),
+            ("vidro", 5.0, 11),
+            ("papel", 2.0, 12),
+            ("metal", 7.5, 13),
+        ];
+        for (tipo, quantidade, dia) in coletas {
+            dataset.add_entry_at_point(
+                tipo.to_string(),
+                quantidade,
+                None,
+                Utc.with_ymd_and_hms(2024, 1, dia, 8, 30, 15).unwrap() + Duration::nanoseconds(123_456_789),
+                VinculosColeta::default(),
+            );
+        }
+        dataset
+    }
+
+    fn ids(pagina: &PaginaColetas) -> Vec<u64> {
+        pagina.coletas.iter().map(|e| e.id).collect()
+    }
+
+    #[test]
+    fn cursor_volta_ao_mesmo_texto() {
+        let dataset = dataset();
+        for campo in [CampoOrdenacao::Timestamp, CampoOrdenacao::Quantidade, CampoOrdenacao::Tipo] {
+            for coleta in &dataset.entries {
+                let cursor = Cursor::da_coleta(campo, coleta);
+                assert_eq!(Cursor::ler(campo, &cursor.to_string()), Ok(cursor));
+            }
+        }
+        assert!(Cursor::ler(CampoOrdenacao::Timestamp, "3").is_err());
+        assert!(Cursor::ler(CampoOrdenacao::Quantidade, "abc:3").is_err());
+    }
+
+    #[test]
+    fn paginar_continua_do_valor_do_cursor_mesmo_com_a_coleta_alterada() {
+        let mut dataset = dataset();
+        let ordenacao = Ordenacao { campo: CampoOrdenacao::Quantidade, decrescente: false };
+
+        let primeira = paginar(dataset.entries.iter().collect(), ordenacao, None, 0, 2).unwrap();
+        assert_eq!(ids(&primeira), vec![1, 3]);
+        assert_eq!(primeira.next.as_deref(), Some("2:3"));
+        let next = primeira.next.unwrap();
+
+        // A última coleta da página muda de valor e outra é removida antes
+        // da página seguinte.
+        dataset.buscar_mut(3).unwrap().quantidade = 100.0;
+        dataset.remover(2, Utc::now());
+
+        let segunda = paginar(dataset.entries.iter().collect(), ordenacao, Some(&next), 0, 2).unwrap();
+        assert_eq!(ids(&segunda), vec![4, 3]);
+        assert_eq!(segunda.next, None);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::unidades::Leitura;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::error::Error;
@@ -7,28 +8,175 @@ use std::path::Path;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WasteEntry {
+    /// Atribuído na gravação; coletas antigas recebem id ao carregar o arquivo.
+    #[serde(default)]
+    pub id: u64,
     pub tipo: String,
+    /// Sempre em kg.
     pub quantidade: f32,
     pub observacoes: Option<String>,
     pub timestamp: DateTime<Utc>,
+    /// Quando a coleta foi gravada no sistema; difere de `timestamp` em
+    /// coletas importadas. Ausente em coletas antigas.
+    #[serde(default)]
+    pub registrada_em: Option<DateTime<Utc>>,
+    /// Ponto de coleta de origem, quando conhecido.
+    #[serde(default)]
+    pub ponto_id: Option<String>,
+    /// Usuário que registrou a coleta, quando conhecido.
+    #[serde(default)]
+    pub usuario_id: Option<u32>,
+    /// Rota (execução) em que a coleta foi feita, quando houver.
+    #[serde(default)]
+    pub rota_id: Option<i64>,
+    /// Valor e unidade como informados; ausente em coletas antigas, que
+    /// foram registradas direto em kg.
+    #[serde(default)]
+    pub leitura: Option<Leitura>,
+    /// Preenchido quando a coleta é removida (ela passa para `removidas`).
+    #[serde(default)]
+    pub removida_em: Option<DateTime<Utc>>,
+}
+
+/// Quem registrou a coleta e onde ela foi feita.
+#[derive(Debug, Clone, Default)]
+pub struct VinculosColeta {
+    pub usuario_id: Option<u32>,
+    pub ponto_id: Option<String>,
+    pub rota_id: Option<i64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Dataset {
     pub entries: Vec<WasteEntry>,
+    /// Coletas removidas, guardadas para auditoria; não entram em treino,
+    /// relatórios nem listagens.
+    #[serde(default)]
+    pub removidas: Vec<WasteEntry>,
 }
 
 impl Dataset {
     pub fn new() -> Self {
-        Dataset { entries: Vec::new() }
+        Dataset { entries: Vec::new(), removidas: Vec::new() }
+    }
+
+    fn proximo_id(&self) -> u64 {
+        self.entries.iter().chain(&self.removidas).map(|e| e.id).max().unwrap_or(0) + 1
+    }
+
+    /// Numera, em ordem, as coletas gravadas antes de existir o campo `id`.
+    fn atribuir_ids(&mut self) {
+        let mut proximo = self.proximo_id();
+        for entry in self.entries.iter_mut().chain(self.removidas.iter_mut()) {
+            if entry.id == 0 {
+                entry.id = proximo;
+                proximo += 1;
+            }
+        }
     }
 
-    pub fn add_entry(&mut self, tipo: String, quantidade: f32, observacoes: Option<String>) {
+    pub fn buscar(&self, id: u64) -> Option<&WasteEntry> {
+        self.entries.iter().find(|e| e.id == id)
+    }
+
+    pub fn buscar_mut(&mut self, id: u64) -> Option<&mut WasteEntry> {
+        self.entries.iter_mut().find(|e| e.id == id)
+    }
+
+    /// Tira a coleta de `entries` e a guarda em `removidas`.
+    pub fn remover(&mut self, id: u64, removida_em: DateTime<Utc>) -> Option<WasteEntry> {
+        let pos = self.entries.iter().position(|e| e.id == id)?;
+        let mut entry = self.entries.remove(pos);
+        entry.removida_em = Some(removida_em);
+        self.removidas.push(entry.clone());
+        Some(entry)
+    }
+
+    pub fn add_entry(
+        &mut self,
+        tipo: String,
+        quantidade: f32,
+        observacoes: Option<String>,
+        usuario_id: Option<u32>,
+    ) {
         let entry = WasteEntry {
+            id: self.proximo_id(),
             tipo,
             quantidade,
             observacoes,
             timestamp: Utc::now(),
+            registrada_em: Some(Utc::now()),
+            ponto_id: None,
+            usuario_id,
+            rota_id: None,
+            leitura: None,
+            removida_em: None,
+        };
+        self.entries.push(entry);
+    }
+
+    /// Como `add_entry`, guardando também a leitura original; `quantidade_kg`
+    /// é a leitura já convertida.
+    pub fn add_entry_in_unit(
+        &mut self,
+        tipo: String,
+        leitura: Leitura,
+        quantidade_kg: f32,
+        observacoes: Option<String>,
+        vinculos: VinculosColeta,
+    ) {
+        self.add_entry_in_unit_at(tipo, leitura, quantidade_kg, observacoes, Utc::now(), vinculos);
+    }
+
+    /// Como `add_entry_in_unit`, com a data da coleta informada (importação
+    /// de histórico). Retorna o id atribuído.
+    pub fn add_entry_in_unit_at(
+        &mut self,
+        tipo: String,
+        leitura: Leitura,
+        quantidade_kg: f32,
+        observacoes: Option<String>,
+        timestamp: DateTime<Utc>,
+        vinculos: VinculosColeta,
+    ) -> u64 {
+        let entry = WasteEntry {
+            id: self.proximo_id(),
+            tipo,
+            quantidade: quantidade_kg,
+            observacoes,
+            timestamp,
+            registrada_em: Some(Utc::now()),
+            ponto_id: vinculos.ponto_id,
+            usuario_id: vinculos.usuario_id,
+            rota_id: vinculos.rota_id,
+            leitura: Some(leitura),
+            removida_em: None,
+        };
+        let id = entry.id;
+        self.entries.push(entry);
+        id
+    }
+
+    pub fn add_entry_at_point(
+        &mut self,
+        tipo: String,
+        quantidade: f32,
+        observacoes: Option<String>,
+        timestamp: DateTime<Utc>,
+        vinculos: VinculosColeta,
+    ) {
+        let entry = WasteEntry {
+            id: self.proximo_id(),
+            tipo,
+            quantidade,
+            observacoes,
+            timestamp,
+            registrada_em: Some(Utc::now()),
+            ponto_id: vinculos.ponto_id,
+            usuario_id: vinculos.usuario_id,
+            rota_id: vinculos.rota_id,
+            leitura: None,
+            removida_em: None,
         };
         self.entries.push(entry);
     }
@@ -50,48 +198,8 @@ impl Dataset {
         let mut f = File::open(path)?;
         let mut s = String::new();
         f.read_to_string(&mut s)?;
-        let ds: Dataset = serde_json::from_str(&s)?;
+        let mut ds: Dataset = serde_json::from_str(&s)?;
+        ds.atribuir_ids();
         Ok(ds)
     }
-
-    /// retorna a média das últimas `n_recent` entradas do mesmo tipo (ordenadas por timestamp)
-    pub fn mean_last_n_of_type(&self, tipo: &str, n_recent: usize) -> Option<f32> {
-        let mut vals: Vec<f32> = self
-            .entries
-            .iter()
-            .filter(|e| e.tipo.eq_ignore_ascii_case(tipo))
-            .map(|e| e.quantidade)
-            .collect();
-        if vals.is_empty() {
-            return None;
-        }
-        // ordenar por timestamp (mais velho -> mais novo)
-        vals.sort_by_key(|_| 0); // não reordena; timestamps já em ordem de inserção
-        let len = vals.len();
-        let start = if len > n_recent { len - n_recent } else { 0 };
-        let slice = &vals[start..];
-        let sum: f32 = slice.iter().sum();
-        Some(sum / (slice.len() as f32))
-    }
-
-    /// calcula variação percentual entre média dos últimos n e média dos n anteriores
-    pub fn trend_percent(&self, tipo: &str, n: usize) -> Option<f32> {
-        let vals: Vec<f32> = self
-            .entries
-            .iter()
-            .filter(|e| e.tipo.eq_ignore_ascii_case(tipo))
-            .map(|e| e.quantidade)
-            .collect();
-        if vals.len() < n * 2 {
-            return None; // dados insuficientes
-        }
-        let len = vals.len();
-        let recent_start = len - n;
-        let recent: f32 = vals[recent_start..].iter().sum::<f32>() / (n as f32);
-        let prev: f32 = vals[recent_start - n..recent_start].iter().sum::<f32>() / (n as f32);
-        if prev.abs() < std::f32::EPSILON {
-            return None;
-        }
-        Some(((recent - prev) / prev) * 100.0)
-    }
 }
@@ -0,0 +1,267 @@
+// src/emissoes.rs
+// Fatores de emissão: kg de CO₂ evitado por kg reciclado de cada material,
+// com data de início de validade. Cálculos históricos usam o fator vigente
+// na data da coleta. Material sem fator é informado como desconhecido, sem
+// nenhum valor inventado.
+
+use chrono::{NaiveDate, Utc};
+use rusqlite::types::Type;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Valores usados antes da tabela existir, gravados na primeira execução.
+const FATORES_PADRAO: [(&str, f32); 5] = [
+    ("plastico", 2.0),
+    ("papel", 1.2),
+    ("vidro", 0.6),
+    ("metal", 3.0),
+    ("organico", 0.3),
+];
+const FONTE_PADRAO: &str = "Valores de referência do sistema (anteriores à tabela de fatores)";
+
+/// Início de validade dos fatores padrão.
+fn inicio_padrao() -> NaiveDate {
+    NaiveDate::from_ymd_opt(1970, 1, 1).unwrap_or_default()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FatorEmissao {
+    pub id: i64,
+    pub material: String,
+    pub fator_kg_co2_por_kg: f32,
+    /// Origem do valor (estudo, inventário, norma).
+    pub fonte: Option<String>,
+    pub valido_desde: NaiveDate,
+    pub cadastrado_por: Option<u32>,
+    pub cadastrado_em: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NovoFatorEmissao {
+    pub material: String,
+    pub fator_kg_co2_por_kg: f32,
+    pub fonte: Option<String>,
+    pub valido_desde: NaiveDate,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatusEstimativa {
+    Calculado,
+    /// Nenhum fator cadastrado para o material.
+    MaterialDesconhecido,
+    /// O material tem fator, mas nenhum válido na data da coleta.
+    SemFatorNaData,
+}
+
+/// CO₂ evitado por uma quantidade de material numa data.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EstimativaCo2 {
+    pub tipo: String,
+    pub quantidade: f32,
+    pub status: StatusEstimativa,
+    pub fator: Option<f32>,
+    pub co2_evitado: Option<f32>,
+    pub fonte: Option<String>,
+    pub valido_desde: Option<NaiveDate>,
+}
+
+/// Chave de comparação de materiais: sem espaços nas pontas, minúsculas e
+/// sem acentos ("Plástico" e "plastico" são o mesmo material).
+pub fn normalizar_material(material: &str) -> String {
+    material
+        .trim()
+        .to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'á' | 'à' | 'â' | 'ã' | 'ä' => 'a',
+            'é' | 'è' | 'ê' | 'ë' => 'e',
+            'í' | 'ì' | 'î' | 'ï' => 'i',
+            'ó' | 'ò' | 'ô' | 'õ' | 'ö' => 'o',
+            'ú' | 'ù' | 'û' | 'ü' => 'u',
+            'ç' => 'c',
+            outro => outro,
+        })
+        .collect()
+}
+
+/// Fatores de todos os materiais em memória, para cálculos em lote.
+#[derive(Debug, Clone, Default)]
+pub struct TabelaFatores {
+    /// Por material normalizado, do mais antigo para o mais novo.
+    por_material: HashMap<String, Vec<FatorEmissao>>,
+}
+
+impl TabelaFatores {
+    pub fn carregar(conn: &Connection) -> rusqlite::Result<Self> {
+        Ok(Self::de_fatores(listar_fatores(conn, None)?))
+    }
+
+    /// Fatores padrão, para uso fora do servidor (módulo de terminal).
+    pub fn padrao() -> Self {
+        let fatores = FATORES_PADRAO
+            .iter()
+            .map(|(material, fator)| FatorEmissao {
+                id: 0,
+                material: material.to_string(),
+                fator_kg_co2_por_kg: *fator,
+                fonte: Some(FONTE_PADRAO.to_string()),
+                valido_desde: inicio_padrao(),
+                cadastrado_por: None,
+                cadastrado_em: String::new(),
+            })
+            .collect();
+        Self::de_fatores(fatores)
+    }
+
+    fn de_fatores(fatores: Vec<FatorEmissao>) -> Self {
+        let mut por_material: HashMap<String, Vec<FatorEmissao>> = HashMap::new();
+        for fator in fatores {
+            por_material.entry(normalizar_material(&fator.material)).or_default().push(fator);
+        }
+        for vigencias in por_material.values_mut() {
+            vigencias.sort_by_key(|f| f.valido_desde);
+        }
+        TabelaFatores { por_material }
+    }
+
+    /// Fator vigente na data; `Err` diz por que não há fator.
+    pub fn vigente(&self, material: &str, data: NaiveDate) -> Result<&FatorEmissao, StatusEstimativa> {
+        let vigencias = self
+            .por_material
+            .get(&normalizar_material(material))
+            .ok_or(StatusEstimativa::MaterialDesconhecido)?;
+        vigencias
+            .iter()
+            .rev()
+            .find(|f| f.valido_desde <= data)
+            .ok_or(StatusEstimativa::SemFatorNaData)
+    }
+
+    pub fn estimar(&self, material: &str, quantidade: f32, data: NaiveDate) -> EstimativaCo2 {
+        let mut estimativa = EstimativaCo2 {
+            tipo: material.to_string(),
+            quantidade,
+            status: StatusEstimativa::Calculado,
+            fator: None,
+            co2_evitado: None,
+            fonte: None,
+            valido_desde: None,
+        };
+        match self.vigente(material, data) {
+            Ok(fator) => {
+                estimativa.fator = Some(fator.fator_kg_co2_por_kg);
+                estimativa.co2_evitado = Some(quantidade * fator.fator_kg_co2_por_kg);
+                estimativa.fonte = fator.fonte.clone();
+                estimativa.valido_desde = Some(fator.valido_desde);
+            }
+            Err(status) => estimativa.status = status,
+        }
+        estimativa
+    }
+}
+
+// ========== BANCO DE DADOS ==========
+
+fn ler_fator(row: &Row) -> rusqlite::Result<FatorEmissao> {
+    let valido_desde: String = row.get(4)?;
+    let valido_desde = NaiveDate::parse_from_str(&valido_desde, "%Y-%m-%d")
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(4, Type::Text, Box::new(e)))?;
+    Ok(FatorEmissao {
+        id: row.get(0)?,
+        material: row.get(1)?,
+        fator_kg_co2_por_kg: row.get(2)?,
+        fonte: row.get(3)?,
+        valido_desde,
+        cadastrado_por: row.get(5)?,
+        cadastrado_em: row.get(6)?,
+    })
+}
+
+/// Grava os fatores padrão (válidos desde 1970-01-01) se a tabela estiver vazia.
+pub fn garantir_fatores_padrao(conn: &Connection) -> rusqlite::Result<()> {
+    let vazia: bool = conn.query_row("SELECT NOT EXISTS (SELECT 1 FROM fatores_emissao)", [], |row| row.get(0))?;
+    if !vazia {
+        return Ok(());
+    }
+    let agora = Utc::now().to_rfc3339();
+    for (material, fator) in FATORES_PADRAO {
+        conn.execute(
+            "INSERT INTO fatores_emissao
+                (material, fator_kg_co2_por_kg, fonte, valido_desde, cadastrado_por, cadastrado_em)
+             VALUES (?1, ?2, ?3, ?4, NULL, ?5)",
+            params![material, fator, FONTE_PADRAO, inicio_padrao().to_string(), agora],
+        )?;
+    }
+    Ok(())
+}
+
+pub fn listar_fatores(conn: &Connection, material: Option<&str>) -> rusqlite::Result<Vec<FatorEmissao>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, material, fator_kg_co2_por_kg, fonte, valido_desde, cadastrado_por, cadastrado_em
+         FROM fatores_emissao
+         WHERE ?1 IS NULL OR material = ?1
+         ORDER BY material, valido_desde",
+    )?;
+    let fatores = stmt
+        .query_map([material.map(normalizar_material)], ler_fator)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(fatores)
+}
+
+/// Cadastra um fator ou substitui o do mesmo material e mesma data de
+/// início (chave: `material` + `valido_desde`).
+pub fn salvar_fator(conn: &Connection, novo: &NovoFatorEmissao, usuario_id: u32) -> Result<FatorEmissao, String> {
+    let material = normalizar_material(&novo.material);
+    if material.is_empty() {
+        return Err("Material não pode ser vazio".to_string());
+    }
+    if !novo.fator_kg_co2_por_kg.is_finite() || novo.fator_kg_co2_por_kg < 0.0 {
+        return Err(format!(
+            "Fator inválido: {} (esperado número maior ou igual a zero)",
+            novo.fator_kg_co2_por_kg
+        ));
+    }
+    let fonte = novo.fonte.as_deref().map(str::trim).filter(|f| !f.is_empty());
+
+    let erro_banco = |e: rusqlite::Error| format!("Erro ao salvar fator de emissão: {}", e);
+    conn.execute(
+        "INSERT INTO fatores_emissao
+            (material, fator_kg_co2_por_kg, fonte, valido_desde, cadastrado_por, cadastrado_em)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+         ON CONFLICT(material, valido_desde) DO UPDATE SET
+            fator_kg_co2_por_kg = excluded.fator_kg_co2_por_kg,
+            fonte = excluded.fonte,
+            cadastrado_por = excluded.cadastrado_por,
+            cadastrado_em = excluded.cadastrado_em",
+        params![
+            material,
+            novo.fator_kg_co2_por_kg,
+            fonte,
+            novo.valido_desde.to_string(),
+            usuario_id,
+            Utc::now().to_rfc3339(),
+        ],
+    )
+    .map_err(erro_banco)?;
+
+    conn.query_row(
+        "SELECT id, material, fator_kg_co2_por_kg, fonte, valido_desde, cadastrado_por, cadastrado_em
+         FROM fatores_emissao
+         WHERE material = ?1 AND valido_desde = ?2",
+        params![material, novo.valido_desde.to_string()],
+        ler_fator,
+    )
+    .optional()
+    .map_err(erro_banco)?
+    .ok_or_else(|| "Fator de emissão salvo não encontrado".to_string())
+}
+
+pub fn remover_fator(conn: &Connection, id: i64) -> Result<(), String> {
+    match conn.execute("DELETE FROM fatores_emissao WHERE id = ?1", [id]) {
+        Ok(0) => Err(format!("Fator de emissão {} não encontrado", id)),
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!("Erro ao remover fator de emissão: {}", e)),
+    }
+}
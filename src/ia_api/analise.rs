@@ -0,0 +1,567 @@
+// src/ia_api/analise.rs
+// Análise da predição com base nos dados reais: as estatísticas do
+// dataset vão no prompt, o modelo responde em JSON e a resposta é validada.
+// Se a IA falhar ou responder fora do formato, a análise é montada a
+// partir das próprias estatísticas. O texto do prompt vem da biblioteca de
+// templates (`templates.rs`).
+
+use super::templates::{renderizar, Idioma, PromptTemplate, TemplateUsado, Variaveis};
+use crate::dataset::Dataset;
+use crate::emissoes::TabelaFatores;
+use crate::otimizacao::DemandaRegiao;
+use crate::predicao::Predicao;
+use crate::tipos_residuo::Catalogo;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::Write;
+use std::fs::{self, OpenOptions};
+use std::io::Write as _;
+use std::path::Path;
+
+/// Registros considerados na média recente e em cada janela da tendência.
+const JANELA_RECENTE: usize = 3;
+/// Tipos listados no prompt (os de maior volume).
+const MAX_TIPOS_NO_PROMPT: usize = 10;
+const MAX_ITENS_LISTA: usize = 5;
+const MAX_CARACTERES_RESUMO: usize = 600;
+const MAX_CARACTERES_ITEM: usize = 300;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EstatisticasTipo {
+    pub tipo: String,
+    pub registros: usize,
+    pub total_kg: f32,
+    /// Média das últimas 3 coletas do tipo.
+    pub media_recente_kg: Option<f32>,
+    /// Variação entre as médias das 3 últimas e das 3 anteriores.
+    pub tendencia_percentual: Option<f32>,
+    /// Soma das coletas com fator de emissão vigente na data de cada uma;
+    /// `None` se nenhuma tiver fator.
+    pub co2_evitado_kg: Option<f32>,
+    /// Quantidade sem fator de emissão (material desconhecido ou coleta
+    /// anterior ao primeiro fator cadastrado).
+    pub kg_sem_fator: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EstatisticasPredicao {
+    pub total_registros: usize,
+    pub total_kg: f32,
+    pub co2_evitado_total_kg: f32,
+    /// Tipos com alguma coleta sem fator de emissão.
+    pub tipos_sem_fator: Vec<String>,
+    pub por_tipo: Vec<EstatisticasTipo>,
+    pub demanda_prevista: Vec<DemandaRegiao>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrigemAnalise {
+    /// Resposta da IA validada.
+    Ia,
+    /// Montada a partir das estatísticas.
+    Estatisticas,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnaliseIA {
+    pub resumo: String,
+    pub riscos: Vec<String>,
+    pub recomendacoes: Vec<String>,
+    pub origem: OrigemAnalise,
+    /// Por que a resposta da IA não foi usada.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub motivo_fallback: Option<String>,
+    /// Versão do template do prompt enviado à IA.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<TemplateUsado>,
+}
+
+/// Análise gravada a cada predição, com a versão do template do prompt.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegistroAnalise {
+    pub registrado_em: String,
+    pub usuario_id: u32,
+    pub tipo: String,
+    pub quantidade_kg: f32,
+    /// `timestamp` da predição exportada em `output/predicao.json`.
+    pub predicao_em: String,
+    pub analise: AnaliseIA,
+}
+
+/// Acrescenta a análise como uma linha JSON ao histórico em `caminho`.
+pub fn registrar_analise(caminho: &Path, registro: &RegistroAnalise) -> Result<(), Box<dyn Error>> {
+    if let Some(pasta) = caminho.parent() {
+        fs::create_dir_all(pasta)?;
+    }
+    let mut linha = serde_json::to_string(registro)?;
+    linha.push('\n');
+    let mut arquivo = OpenOptions::new().create(true).append(true).open(caminho)?;
+    arquivo.write_all(linha.as_bytes())?;
+    Ok(())
+}
+
+/// Formato pedido ao modelo.
+#[derive(Deserialize)]
+struct RespostaEstruturada {
+    resumo: String,
+    #[serde(default)]
+    riscos: Vec<String>,
+    #[serde(default)]
+    recomendacoes: Vec<String>,
+}
+
+#[derive(Default)]
+struct TotaisTipo {
+    /// Quantidades na ordem do arquivo (da mais antiga para a mais nova).
+    quantidades: Vec<f32>,
+    total_kg: f32,
+    co2_evitado_kg: Option<f32>,
+    kg_sem_fator: f32,
+}
+
+/// Média das últimas `JANELA_RECENTE` quantidades.
+fn media_recente(quantidades: &[f32]) -> Option<f32> {
+    let recentes = &quantidades[quantidades.len().saturating_sub(JANELA_RECENTE)..];
+    if recentes.is_empty() {
+        return None;
+    }
+    Some(recentes.iter().sum::<f32>() / recentes.len() as f32)
+}
+
+/// Variação percentual entre a média das últimas `JANELA_RECENTE`
+/// quantidades e a das `JANELA_RECENTE` anteriores.
+fn tendencia(quantidades: &[f32]) -> Option<f32> {
+    let n = JANELA_RECENTE;
+    if quantidades.len() < n * 2 {
+        return None;
+    }
+    let inicio_recente = quantidades.len() - n;
+    let recente = quantidades[inicio_recente..].iter().sum::<f32>() / n as f32;
+    let anterior = quantidades[inicio_recente - n..inicio_recente].iter().sum::<f32>() / n as f32;
+    if anterior.abs() < f32::EPSILON {
+        return None;
+    }
+    Some((recente - anterior) / anterior * 100.0)
+}
+
+/// Totais, média recente, tendência e CO₂ por tipo (do maior volume para o
+/// menor), mais a demanda prevista por região. Os tipos são agrupados pelo
+/// código do catálogo, como nas consultas de coletas. O CO₂ de cada coleta
+/// usa o fator do código vigente na data dela.
+pub fn calcular_estatisticas(
+    dataset: &Dataset,
+    catalogo: &Catalogo,
+    fatores: &TabelaFatores,
+    demanda_prevista: Vec<DemandaRegiao>,
+) -> EstatisticasPredicao {
+    let mut totais: BTreeMap<String, TotaisTipo> = BTreeMap::new();
+    for entrada in &dataset.entries {
+        let tipo = catalogo.chave(&entrada.tipo);
+        let fator = fatores.vigente(&tipo, entrada.timestamp.date_naive());
+        let total = totais.entry(tipo).or_default();
+        total.quantidades.push(entrada.quantidade);
+        total.total_kg += entrada.quantidade;
+        match fator {
+            Ok(fator) => {
+                *total.co2_evitado_kg.get_or_insert(0.0) += entrada.quantidade * fator.fator_kg_co2_por_kg;
+            }
+            Err(_) => total.kg_sem_fator += entrada.quantidade,
+        }
+    }
+
+    let mut por_tipo: Vec<EstatisticasTipo> = totais
+        .into_iter()
+        .map(|(tipo, total)| EstatisticasTipo {
+            media_recente_kg: media_recente(&total.quantidades),
+            tendencia_percentual: tendencia(&total.quantidades),
+            tipo,
+            registros: total.quantidades.len(),
+            total_kg: total.total_kg,
+            co2_evitado_kg: total.co2_evitado_kg,
+            kg_sem_fator: total.kg_sem_fator,
+        })
+        .collect();
+    por_tipo.sort_by(|a, b| b.total_kg.total_cmp(&a.total_kg).then_with(|| a.tipo.cmp(&b.tipo)));
+
+    EstatisticasPredicao {
+        total_registros: dataset.entries.len(),
+        total_kg: por_tipo.iter().map(|t| t.total_kg).sum(),
+        co2_evitado_total_kg: por_tipo.iter().filter_map(|t| t.co2_evitado_kg).sum(),
+        tipos_sem_fator: por_tipo
+            .iter()
+            .filter(|t| t.kg_sem_fator > 0.0)
+            .map(|t| t.tipo.clone())
+            .collect(),
+        por_tipo,
+        demanda_prevista,
+    }
+}
+
+/// Rótulos das linhas de histórico e demanda em cada idioma.
+struct Rotulos {
+    registros: &'static str,
+    media_ultimas: &'static str,
+    tendencia: &'static str,
+    dados_insuficientes: &'static str,
+    co2_evitado: &'static str,
+    sem_fator: &'static str,
+    pontos: &'static str,
+    media_por_ponto: &'static str,
+    sem_previsoes: &'static str,
+    modelo: &'static str,
+    impacto_coleta: &'static str,
+    impacto_total: &'static str,
+}
+
+fn rotulos(idioma: Idioma) -> Rotulos {
+    match idioma {
+        Idioma::PtBr => Rotulos {
+            registros: "registros",
+            media_ultimas: "média das últimas",
+            tendencia: "tendência",
+            dados_insuficientes: "dados insuficientes",
+            co2_evitado: "CO₂ evitado",
+            sem_fator: "sem fator de emissão",
+            pontos: "pontos",
+            media_por_ponto: "média por ponto",
+            sem_previsoes: "sem previsões vigentes",
+            modelo: "modelo",
+            impacto_coleta: "impacto previsto desta coleta",
+            impacto_total: "impacto previsto do histórico",
+        },
+        Idioma::En => Rotulos {
+            registros: "records",
+            media_ultimas: "average of the last",
+            tendencia: "trend",
+            dados_insuficientes: "not enough data",
+            co2_evitado: "CO₂ avoided",
+            sem_fator: "no emission factor",
+            pontos: "points",
+            media_por_ponto: "average per point",
+            sem_previsoes: "no current forecasts",
+            modelo: "model",
+            impacto_coleta: "predicted impact of this collection",
+            impacto_total: "predicted impact of the history",
+        },
+        Idioma::Es => Rotulos {
+            registros: "registros",
+            media_ultimas: "promedio de los últimos",
+            tendencia: "tendencia",
+            dados_insuficientes: "datos insuficientes",
+            co2_evitado: "CO₂ evitado",
+            sem_fator: "sin factor de emisión",
+            pontos: "puntos",
+            media_por_ponto: "promedio por punto",
+            sem_previsoes: "sin previsiones vigentes",
+            modelo: "modelo",
+            impacto_coleta: "impacto previsto de esta recolección",
+            impacto_total: "impacto previsto del historial",
+        },
+    }
+}
+
+/// Valores das variáveis do template `analise_predicao`: a nova coleta, o
+/// histórico resumido por tipo, a demanda prevista por região e a predição
+/// do modelo, em que a nova coleta é o último registro.
+pub fn variaveis_analise(
+    tipo: &str,
+    quantidade: f32,
+    estatisticas: &EstatisticasPredicao,
+    predicao: &Predicao,
+    idioma: Idioma,
+) -> Variaveis {
+    let r = rotulos(idioma);
+
+    let mut historico = String::new();
+    for t in estatisticas.por_tipo.iter().take(MAX_TIPOS_NO_PROMPT) {
+        let media = t
+            .media_recente_kg
+            .map(|m| format!("{:.2} kg", m))
+            .unwrap_or_else(|| "—".to_string());
+        let tendencia = t
+            .tendencia_percentual
+            .map(|t| format!("{:+.1}%", t))
+            .unwrap_or_else(|| r.dados_insuficientes.to_string());
+        let co2 = match (t.co2_evitado_kg, t.kg_sem_fator > 0.0) {
+            (Some(co2), false) => format!("{:.1} kg", co2),
+            (Some(co2), true) => format!("{:.1} kg ({:.1} kg {})", co2, t.kg_sem_fator, r.sem_fator),
+            (None, _) => r.sem_fator.to_string(),
+        };
+        let _ = writeln!(
+            historico,
+            "- {}: {:.1} kg, {} {}; {} {}: {}; {}: {}; {}: {}",
+            t.tipo,
+            t.total_kg,
+            t.registros,
+            r.registros,
+            r.media_ultimas,
+            JANELA_RECENTE,
+            media,
+            r.tendencia,
+            tendencia,
+            r.co2_evitado,
+            co2
+        );
+    }
+
+    let mut demanda = String::new();
+    if estatisticas.demanda_prevista.is_empty() {
+        let _ = writeln!(demanda, "- {}", r.sem_previsoes);
+    }
+    for d in &estatisticas.demanda_prevista {
+        let _ = writeln!(
+            demanda,
+            "- {}: {:.1} kg, {} {} ({}: {:.1} kg)",
+            d.regiao, d.demanda_total, d.pontos_com_previsao, r.pontos, r.media_por_ponto, d.demanda_media
+        );
+    }
+
+    let mut modelo = format!("- {}: {}", r.modelo, predicao.modelo);
+    if let Some(ultimo) = predicao.resultados.last() {
+        let _ = write!(modelo, "\n- {}: {:.2}", r.impacto_coleta, ultimo);
+    }
+    let _ = write!(
+        modelo,
+        "\n- {}: {:.1} ({} {})",
+        r.impacto_total,
+        predicao.impacto_total,
+        predicao.resultados.len(),
+        r.registros
+    );
+
+    Variaveis::from([
+        ("tipo", tipo.to_string()),
+        ("quantidade", format!("{:.3}", quantidade)),
+        ("total_registros", estatisticas.total_registros.to_string()),
+        ("total_kg", format!("{:.1}", estatisticas.total_kg)),
+        ("co2_evitado_total_kg", format!("{:.1}", estatisticas.co2_evitado_total_kg)),
+        ("janela_recente", JANELA_RECENTE.to_string()),
+        ("historico_por_tipo", historico.trim_end().to_string()),
+        ("demanda_prevista", demanda.trim_end().to_string()),
+        ("predicao_modelo", modelo),
+    ])
+}
+
+/// Prompt da análise a partir do template `analise_predicao`.
+pub fn montar_prompt(
+    template: &PromptTemplate,
+    tipo: &str,
+    quantidade: f32,
+    estatisticas: &EstatisticasPredicao,
+    predicao: &Predicao,
+) -> String {
+    renderizar(
+        &template.conteudo,
+        &variaveis_analise(tipo, quantidade, estatisticas, predicao, template.idioma),
+    )
+}
+
+/// Estatísticas fictícias para a prévia de templates.
+pub fn estatisticas_de_exemplo() -> EstatisticasPredicao {
+    let por_tipo = vec![
+        EstatisticasTipo {
+            tipo: "plastico".to_string(),
+            registros: 12,
+            total_kg: 148.5,
+            media_recente_kg: Some(14.2),
+            tendencia_percentual: Some(18.4),
+            co2_evitado_kg: Some(297.0),
+            kg_sem_fator: 0.0,
+        },
+        EstatisticasTipo {
+            tipo: "papel".to_string(),
+            registros: 8,
+            total_kg: 96.0,
+            media_recente_kg: Some(10.5),
+            tendencia_percentual: Some(-12.5),
+            co2_evitado_kg: Some(115.2),
+            kg_sem_fator: 0.0,
+        },
+        EstatisticasTipo {
+            tipo: "vidro".to_string(),
+            registros: 2,
+            total_kg: 30.0,
+            media_recente_kg: None,
+            tendencia_percentual: None,
+            co2_evitado_kg: Some(18.0),
+            kg_sem_fator: 0.0,
+        },
+    ];
+    EstatisticasPredicao {
+        total_registros: por_tipo.iter().map(|t| t.registros).sum(),
+        total_kg: por_tipo.iter().map(|t| t.total_kg).sum(),
+        co2_evitado_total_kg: por_tipo.iter().filter_map(|t| t.co2_evitado_kg).sum(),
+        tipos_sem_fator: Vec::new(),
+        por_tipo,
+        demanda_prevista: vec![DemandaRegiao {
+            regiao: "centro".to_string(),
+            demanda_total: 85.0,
+            pontos_com_previsao: 3,
+            demanda_media: 85.0 / 3.0,
+        }],
+    }
+}
+
+/// Predição fictícia para a prévia de templates: histórico de exemplo mais
+/// a nova coleta.
+pub fn predicao_de_exemplo(quantidade: f32) -> Predicao {
+    let quantidades = vec![14.0, 10.5, quantidade];
+    let resultados: Vec<f32> = quantidades.iter().map(|q| q * 0.8).collect();
+    Predicao::new(
+        vec!["plastico".to_string(), "papel".to_string(), "plastico".to_string()],
+        quantidades,
+        resultados.clone(),
+        resultados.iter().sum(),
+        "ModeloSimuladoReciclagem".to_string(),
+    )
+}
+
+fn limitar(texto: &str, limite: usize) -> String {
+    let texto = texto.trim();
+    if texto.chars().count() <= limite {
+        return texto.to_string();
+    }
+    let mut cortado: String = texto.chars().take(limite).collect();
+    cortado.push('…');
+    cortado
+}
+
+fn limpar_lista(itens: Vec<String>) -> Vec<String> {
+    itens
+        .iter()
+        .map(|item| limitar(item, MAX_CARACTERES_ITEM))
+        .filter(|item| !item.is_empty())
+        .take(MAX_ITENS_LISTA)
+        .collect()
+}
+
+/// Trecho entre a primeira `{` e a última `}`. Aceita o objeto cercado por
+/// texto ou por bloco de código markdown, como alguns modelos devolvem.
+pub(super) fn extrair_objeto_json(texto: &str) -> Result<&str, String> {
+    match (texto.find('{'), texto.rfind('}')) {
+        (Some(inicio), Some(fim)) if inicio < fim => Ok(&texto[inicio..=fim]),
+        _ => Err("resposta sem objeto JSON".to_string()),
+    }
+}
+
+/// Extrai e valida o JSON da resposta.
+pub fn interpretar_resposta(texto: &str) -> Result<AnaliseIA, String> {
+    let resposta: RespostaEstruturada = serde_json::from_str(extrair_objeto_json(texto)?)
+        .map_err(|e| format!("JSON fora do formato esperado: {}", e))?;
+
+    let resumo = limitar(&resposta.resumo, MAX_CARACTERES_RESUMO);
+    if resumo.is_empty() {
+        return Err("resumo vazio".to_string());
+    }
+
+    Ok(AnaliseIA {
+        resumo,
+        riscos: limpar_lista(resposta.riscos),
+        recomendacoes: limpar_lista(resposta.recomendacoes),
+        origem: OrigemAnalise::Ia,
+        motivo_fallback: None,
+        template: None,
+    })
+}
+
+/// Nome do tipo no idioma, em minúsculas; fora do catálogo, o próprio valor.
+fn nome_do_tipo(catalogo: &Catalogo, codigo: &str, idioma: Idioma) -> String {
+    let Some(tipo) = catalogo.tipo(codigo) else {
+        return codigo.to_string();
+    };
+    let nome = match idioma {
+        Idioma::PtBr => &tipo.nomes.pt_br,
+        Idioma::En => &tipo.nomes.en,
+        Idioma::Es => &tipo.nomes.es,
+    };
+    nome.to_lowercase()
+}
+
+/// Análise determinística a partir das estatísticas, usada quando a IA
+/// falha ou responde fora do formato. Os textos e os nomes dos tipos saem
+/// no idioma pedido.
+pub fn analise_de_fallback(
+    tipo: &str,
+    quantidade: f32,
+    estatisticas: &EstatisticasPredicao,
+    catalogo: &Catalogo,
+    idioma: Idioma,
+    motivo: String,
+) -> AnaliseIA {
+    let nome = |codigo: &str| nome_do_tipo(catalogo, codigo, idioma);
+    let (total_kg, registros, co2) = (
+        estatisticas.total_kg,
+        estatisticas.total_registros,
+        estatisticas.co2_evitado_total_kg,
+    );
+    let resumo = match idioma {
+        Idioma::PtBr => format!(
+            "Coleta de {:.3} kg de {}. O histórico soma {:.1} kg em {} registros, com cerca de {:.1} kg de CO₂ evitado.",
+            quantidade, nome(tipo), total_kg, registros, co2
+        ),
+        Idioma::En => format!(
+            "Collection of {:.3} kg of {}. The history adds up to {:.1} kg in {} records, with about {:.1} kg of CO₂ avoided.",
+            quantidade, nome(tipo), total_kg, registros, co2
+        ),
+        Idioma::Es => format!(
+            "Recolección de {:.3} kg de {}. El historial suma {:.1} kg en {} registros, con cerca de {:.1} kg de CO₂ evitado.",
+            quantidade, nome(tipo), total_kg, registros, co2
+        ),
+    };
+
+    let mut riscos = Vec::new();
+    let mut recomendacoes = Vec::new();
+    for t in &estatisticas.por_tipo {
+        match t.tendencia_percentual {
+            Some(tendencia) if tendencia <= -10.0 => riscos.push(match idioma {
+                Idioma::PtBr => format!("Queda de {:.1}% nas coletas recentes de {}", -tendencia, nome(&t.tipo)),
+                Idioma::En => format!("{:.1}% drop in recent {} collections", -tendencia, nome(&t.tipo)),
+                Idioma::Es => format!("Caída de {:.1}% en las recolecciones recientes de {}", -tendencia, nome(&t.tipo)),
+            }),
+            Some(tendencia) if tendencia >= 10.0 => recomendacoes.push(match idioma {
+                Idioma::PtBr => format!("Reforçar a capacidade de coleta de {}, que cresceu {:.1}%", nome(&t.tipo), tendencia),
+                Idioma::En => format!("Increase collection capacity for {}, which grew {:.1}%", nome(&t.tipo), tendencia),
+                Idioma::Es => format!("Reforzar la capacidad de recolección de {}, que creció {:.1}%", nome(&t.tipo), tendencia),
+            }),
+            _ => {}
+        }
+    }
+    let tipo_atual = tipo.trim().to_lowercase();
+    let historico_do_tipo = estatisticas.por_tipo.iter().find(|t| t.tipo == tipo_atual);
+    if historico_do_tipo.is_none_or(|t| t.tendencia_percentual.is_none()) {
+        recomendacoes.push(match idioma {
+            Idioma::PtBr => format!("Continuar registrando coletas de {} para permitir o cálculo de tendência", nome(tipo)),
+            Idioma::En => format!("Keep recording {} collections so that a trend can be calculated", nome(tipo)),
+            Idioma::Es => format!("Seguir registrando recolecciones de {} para poder calcular la tendencia", nome(tipo)),
+        });
+    }
+    if !estatisticas.tipos_sem_fator.is_empty() {
+        let tipos: Vec<String> = estatisticas.tipos_sem_fator.iter().map(|t| nome(t)).collect();
+        let tipos = tipos.join(", ");
+        recomendacoes.push(match idioma {
+            Idioma::PtBr => format!(
+                "Cadastrar fator de emissão para {}; o CO₂ evitado desses materiais não está no total",
+                tipos
+            ),
+            Idioma::En => format!(
+                "Register an emission factor for {}; the CO₂ avoided by these materials is not in the total",
+                tipos
+            ),
+            Idioma::Es => format!(
+                "Registrar un factor de emisión para {}; el CO₂ evitado de estos materiales no está en el total",
+                tipos
+            ),
+        });
+    }
+
+    AnaliseIA {
+        resumo,
+        riscos: riscos.into_iter().take(MAX_ITENS_LISTA).collect(),
+        recomendacoes: recomendacoes.into_iter().take(MAX_ITENS_LISTA).collect(),
+        origem: OrigemAnalise::Estatisticas,
+        motivo_fallback: Some(motivo),
+        template: None,
+    }
+}
@@ -0,0 +1,711 @@
+// src/ia_api/consulta.rs
+// Perguntas em linguagem natural sobre coletas e rotas. A IA não gera SQL:
+// ela traduz a pergunta num plano de consulta restrito (fonte, métrica,
+// filtros e agrupamento), que é validado e executado aqui.
+
+use super::analise::extrair_objeto_json;
+use super::templates::{renderizar, PromptTemplate, Variaveis};
+use crate::dataset::Dataset;
+use crate::emissoes::TabelaFatores;
+use crate::otimizacao::{listar_pontos, StatusRota};
+use crate::tipos_residuo::Catalogo;
+use chrono::{DateTime, NaiveDate, Utc};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+
+pub const MAX_CARACTERES_PERGUNTA: usize = 500;
+const MAX_CARACTERES_FILTRO: usize = 100;
+const LIMITE_PADRAO: usize = 20;
+const MAX_LIMITE: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FonteConsulta {
+    /// Coletas registradas (`data/db.json`).
+    Coletas,
+    /// Rotas otimizadas salvas no banco.
+    Rotas,
+}
+
+impl FonteConsulta {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FonteConsulta::Coletas => "coletas",
+            FonteConsulta::Rotas => "rotas",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MetricaConsulta {
+    TotalKg,
+    MediaKg,
+    /// CO₂ evitado, com o fator de emissão vigente na data de cada coleta.
+    Co2EvitadoKg,
+    Registros,
+    DistanciaPlanejadaKm,
+    LitrosPlanejados,
+    CustoPlanejadoReais,
+}
+
+impl MetricaConsulta {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MetricaConsulta::TotalKg => "total_kg",
+            MetricaConsulta::MediaKg => "media_kg",
+            MetricaConsulta::Co2EvitadoKg => "co2_evitado_kg",
+            MetricaConsulta::Registros => "registros",
+            MetricaConsulta::DistanciaPlanejadaKm => "distancia_planejada_km",
+            MetricaConsulta::LitrosPlanejados => "litros_planejados",
+            MetricaConsulta::CustoPlanejadoReais => "custo_planejado_reais",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Agrupamento {
+    Tipo,
+    Regiao,
+    Ponto,
+    Status,
+    Mes,
+}
+
+impl Agrupamento {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Agrupamento::Tipo => "tipo",
+            Agrupamento::Regiao => "regiao",
+            Agrupamento::Ponto => "ponto",
+            Agrupamento::Status => "status",
+            Agrupamento::Mes => "mes",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FiltrosConsulta {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tipo: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub regiao: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ponto_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<StatusRota>,
+    /// Primeiro dia incluído.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub de: Option<NaiveDate>,
+    /// Último dia incluído.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ate: Option<NaiveDate>,
+}
+
+/// Plano de consulta: a única coisa que a IA pode pedir ao backend.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PlanoConsulta {
+    pub fonte: FonteConsulta,
+    pub metrica: MetricaConsulta,
+    #[serde(default)]
+    pub filtros: FiltrosConsulta,
+    #[serde(default)]
+    pub agrupar_por: Option<Agrupamento>,
+    /// Máximo de grupos retornados (padrão 20, máximo 50).
+    #[serde(default)]
+    pub limite: Option<usize>,
+}
+
+impl PlanoConsulta {
+    /// Confere se métrica, filtros e agrupamento valem para a fonte.
+    pub fn validar(&self) -> Result<(), String> {
+        let (metricas, agrupamentos): (&[MetricaConsulta], &[Agrupamento]) = match self.fonte {
+            FonteConsulta::Coletas => (
+                &[
+                    MetricaConsulta::TotalKg,
+                    MetricaConsulta::MediaKg,
+                    MetricaConsulta::Co2EvitadoKg,
+                    MetricaConsulta::Registros,
+                ],
+                &[Agrupamento::Tipo, Agrupamento::Regiao, Agrupamento::Ponto, Agrupamento::Mes],
+            ),
+            FonteConsulta::Rotas => (
+                &[
+                    MetricaConsulta::Registros,
+                    MetricaConsulta::DistanciaPlanejadaKm,
+                    MetricaConsulta::LitrosPlanejados,
+                    MetricaConsulta::CustoPlanejadoReais,
+                ],
+                &[Agrupamento::Status, Agrupamento::Mes],
+            ),
+        };
+        if !metricas.contains(&self.metrica) {
+            return Err(format!(
+                "Métrica '{}' não se aplica à fonte '{}'",
+                self.metrica.as_str(),
+                self.fonte.as_str()
+            ));
+        }
+        if let Some(agrupamento) = self.agrupar_por {
+            if !agrupamentos.contains(&agrupamento) {
+                return Err(format!(
+                    "Agrupamento '{}' não se aplica à fonte '{}'",
+                    agrupamento.as_str(),
+                    self.fonte.as_str()
+                ));
+            }
+        }
+
+        let f = &self.filtros;
+        let filtros_de_coleta = f.tipo.is_some() || f.regiao.is_some() || f.ponto_id.is_some();
+        match self.fonte {
+            FonteConsulta::Coletas if f.status.is_some() => {
+                return Err("Filtro 'status' só se aplica a rotas".to_string());
+            }
+            FonteConsulta::Rotas if filtros_de_coleta => {
+                return Err("Filtros 'tipo', 'regiao' e 'ponto_id' só se aplicam a coletas".to_string());
+            }
+            _ => {}
+        }
+        for (campo, valor) in [("tipo", &f.tipo), ("regiao", &f.regiao), ("ponto_id", &f.ponto_id)] {
+            if let Some(valor) = valor {
+                if valor.trim().is_empty() || valor.chars().count() > MAX_CARACTERES_FILTRO {
+                    return Err(format!("Filtro '{}' inválido", campo));
+                }
+            }
+        }
+        if let (Some(de), Some(ate)) = (f.de, f.ate) {
+            if de > ate {
+                return Err(format!("Período inválido: {} é depois de {}", de, ate));
+            }
+        }
+        if let Some(limite) = self.limite {
+            if !(1..=MAX_LIMITE).contains(&limite) {
+                return Err(format!("Limite deve estar entre 1 e {}", MAX_LIMITE));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GrupoResultado {
+    pub chave: String,
+    pub valor: f64,
+    pub registros: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ResultadoConsulta {
+    /// Métrica calculada sobre todos os registros filtrados.
+    pub valor: f64,
+    pub unidade: &'static str,
+    pub registros: usize,
+    pub grupos: Vec<GrupoResultado>,
+    /// Grupos que ficaram de fora por causa do limite.
+    pub grupos_omitidos: usize,
+    /// Na métrica `co2_evitado_kg`: coletas filtradas sem fator de emissão
+    /// vigente, que ficam fora do valor.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub registros_sem_fator: Option<usize>,
+}
+
+/// Tipos e regiões conhecidos, passados ao modelo para que os filtros
+/// usem os mesmos nomes dos dados. Os tipos são os códigos do catálogo mais
+/// os valores antigos que ainda não foram migrados.
+#[derive(Debug, Clone, Default)]
+pub struct ContextoPergunta {
+    pub tipos_residuo: Vec<String>,
+    pub regioes: Vec<String>,
+}
+
+impl ContextoPergunta {
+    pub fn carregar(dataset: &Dataset, conn: &Connection) -> rusqlite::Result<Self> {
+        let catalogo = Catalogo::carregar(conn)?;
+        let mut tipos: Vec<String> = dataset.entries.iter().map(|e| catalogo.chave(&e.tipo)).collect();
+        tipos.extend(catalogo.codigos().map(str::to_string));
+        tipos.sort();
+        tipos.dedup();
+
+        let mut regioes: Vec<String> = listar_pontos(conn, None)?
+            .into_iter()
+            .filter_map(|p| p.regiao)
+            .collect();
+        regioes.sort();
+        regioes.dedup();
+
+        Ok(ContextoPergunta {
+            tipos_residuo: tipos,
+            regioes,
+        })
+    }
+}
+
+fn lista_ou_nenhum(itens: &[String]) -> String {
+    if itens.is_empty() {
+        "—".to_string()
+    } else {
+        itens.join(", ")
+    }
+}
+
+/// Valores das variáveis do template `plano_consulta`.
+pub fn variaveis_plano(pergunta: &str, contexto: &ContextoPergunta, hoje: NaiveDate) -> Variaveis {
+    Variaveis::from([
+        ("pergunta", pergunta.trim().to_string()),
+        ("data_atual", hoje.to_string()),
+        ("tipos_residuo", lista_ou_nenhum(&contexto.tipos_residuo)),
+        ("regioes", lista_ou_nenhum(&contexto.regioes)),
+    ])
+}
+
+pub fn montar_prompt_plano(template: &PromptTemplate, pergunta: &str, contexto: &ContextoPergunta) -> String {
+    renderizar(
+        &template.conteudo,
+        &variaveis_plano(pergunta, contexto, Utc::now().date_naive()),
+    )
+}
+
+/// Extrai e valida o plano da resposta do modelo. Campos fora do formato
+/// são rejeitados, não ignorados.
+pub fn interpretar_plano(texto: &str) -> Result<PlanoConsulta, String> {
+    let json: Value = serde_json::from_str(extrair_objeto_json(texto)?)
+        .map_err(|e| format!("JSON inválido: {}", e))?;
+    if let Some(motivo) = json.get("nao_suportado") {
+        let motivo = motivo.as_str().unwrap_or_default().trim();
+        return Err(format!("pergunta fora do alcance dos dados: {}", motivo));
+    }
+
+    let plano: PlanoConsulta =
+        serde_json::from_value(json).map_err(|e| format!("plano fora do formato esperado: {}", e))?;
+    plano.validar()?;
+    Ok(plano)
+}
+
+/// Linha comum às duas fontes, já com os campos usados em filtros e grupos.
+struct Registro {
+    tipo: Option<String>,
+    regiao: Option<String>,
+    ponto: Option<String>,
+    status: Option<String>,
+    data: NaiveDate,
+    /// `None` quando o valor não pode ser calculado (coleta sem fator de
+    /// emissão na métrica de CO₂).
+    valor: Option<f64>,
+}
+
+fn mesmo_texto(a: &str, b: &str) -> bool {
+    a.trim().eq_ignore_ascii_case(b.trim())
+}
+
+/// Coletas como registros, com o tipo pelo código do catálogo; na métrica
+/// de CO₂, o valor é o CO₂ evitado (fator do código, não do apelido) e
+/// coletas sem fator vigente ficam com `None`.
+fn registros_de_coletas(
+    dataset: &Dataset,
+    conn: &Connection,
+    catalogo: &Catalogo,
+    metrica: MetricaConsulta,
+) -> Result<Vec<Registro>, String> {
+    let fatores = match metrica {
+        MetricaConsulta::Co2EvitadoKg => Some(
+            TabelaFatores::carregar(conn).map_err(|e| format!("Erro ao acessar banco de dados: {}", e))?,
+        ),
+        _ => None,
+    };
+    let regiao_do_ponto: HashMap<String, String> = listar_pontos(conn, None)
+        .map_err(|e| format!("Erro ao acessar banco de dados: {}", e))?
+        .into_iter()
+        .filter_map(|p| Some((p.id, p.regiao?)))
+        .collect();
+
+    Ok(dataset
+        .entries
+        .iter()
+        .map(|e| {
+            let data = e.timestamp.date_naive();
+            let tipo = catalogo.chave(&e.tipo);
+            let valor = match &fatores {
+                Some(fatores) => fatores
+                    .vigente(&tipo, data)
+                    .ok()
+                    .map(|f| (e.quantidade * f.fator_kg_co2_por_kg) as f64),
+                None => Some(e.quantidade as f64),
+            };
+            Registro {
+                tipo: Some(tipo),
+                regiao: e.ponto_id.as_ref().and_then(|p| regiao_do_ponto.get(p).cloned()),
+                ponto: e.ponto_id.clone(),
+                status: None,
+                data,
+                valor,
+            }
+        })
+        .collect())
+}
+
+fn registros_de_rotas(conn: &Connection, metrica: MetricaConsulta) -> Result<Vec<Registro>, String> {
+    let erro_banco = |e: rusqlite::Error| format!("Erro ao acessar banco de dados: {}", e);
+    let mut stmt = conn
+        .prepare(
+            "SELECT status, criada_em, distancia_planejada_km, litros_planejados, custo_planejado_reais
+             FROM rotas_otimizadas",
+        )
+        .map_err(erro_banco)?;
+    let linhas = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, f64>(2)?,
+                row.get::<_, f64>(3)?,
+                row.get::<_, f64>(4)?,
+            ))
+        })
+        .map_err(erro_banco)?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(erro_banco)?;
+
+    Ok(linhas
+        .into_iter()
+        .filter_map(|(status, criada_em, distancia, litros, custo)| {
+            let data = DateTime::parse_from_rfc3339(&criada_em).ok()?.date_naive();
+            let valor = match metrica {
+                MetricaConsulta::DistanciaPlanejadaKm => distancia,
+                MetricaConsulta::LitrosPlanejados => litros,
+                MetricaConsulta::CustoPlanejadoReais => custo,
+                _ => 1.0,
+            };
+            let valor = Some(valor);
+            Some(Registro {
+                tipo: None,
+                regiao: None,
+                ponto: None,
+                status: Some(status),
+                data,
+                valor,
+            })
+        })
+        .collect())
+}
+
+fn aceita(filtros: &FiltrosConsulta, registro: &Registro) -> bool {
+    let confere = |filtro: &Option<String>, campo: &Option<String>| match filtro {
+        Some(esperado) => campo.as_deref().is_some_and(|valor| mesmo_texto(valor, esperado)),
+        None => true,
+    };
+    confere(&filtros.tipo, &registro.tipo)
+        && confere(&filtros.regiao, &registro.regiao)
+        && confere(&filtros.ponto_id, &registro.ponto)
+        && filtros
+            .status
+            .is_none_or(|s| registro.status.as_deref() == Some(s.as_str()))
+        && filtros.de.is_none_or(|de| registro.data >= de)
+        && filtros.ate.is_none_or(|ate| registro.data <= ate)
+}
+
+fn agregar(metrica: MetricaConsulta, soma: f64, registros: usize) -> f64 {
+    match metrica {
+        MetricaConsulta::Registros => registros as f64,
+        MetricaConsulta::MediaKg if registros > 0 => soma / registros as f64,
+        MetricaConsulta::MediaKg => 0.0,
+        _ => soma,
+    }
+}
+
+fn chave_do_grupo(agrupamento: Agrupamento, registro: &Registro) -> String {
+    let ou = |campo: &Option<String>, ausente: &str| campo.clone().unwrap_or_else(|| ausente.to_string());
+    match agrupamento {
+        Agrupamento::Tipo => ou(&registro.tipo, "sem tipo"),
+        Agrupamento::Regiao => ou(&registro.regiao, "sem região"),
+        Agrupamento::Ponto => ou(&registro.ponto, "sem ponto"),
+        Agrupamento::Status => ou(&registro.status, "sem status"),
+        Agrupamento::Mes => registro.data.format("%Y-%m").to_string(),
+    }
+}
+
+fn unidade(metrica: MetricaConsulta) -> &'static str {
+    match metrica {
+        MetricaConsulta::TotalKg | MetricaConsulta::MediaKg | MetricaConsulta::Co2EvitadoKg => "kg",
+        MetricaConsulta::Registros => "registros",
+        MetricaConsulta::DistanciaPlanejadaKm => "km",
+        MetricaConsulta::LitrosPlanejados => "L",
+        MetricaConsulta::CustoPlanejadoReais => "R$",
+    }
+}
+
+/// Executa um plano já validado sobre o dataset de coletas e o banco.
+pub fn executar_plano(
+    plano: &PlanoConsulta,
+    dataset: &Dataset,
+    conn: &Connection,
+) -> Result<ResultadoConsulta, String> {
+    plano.validar()?;
+
+    // "PET" e "plástico" filtram o mesmo tipo
+    let catalogo = Catalogo::carregar(conn).map_err(|e| format!("Erro ao acessar banco de dados: {}", e))?;
+    let mut filtros = plano.filtros.clone();
+    filtros.tipo = filtros.tipo.map(|tipo| catalogo.chave(&tipo));
+
+    let registros = match plano.fonte {
+        FonteConsulta::Coletas => registros_de_coletas(dataset, conn, &catalogo, plano.metrica)?,
+        FonteConsulta::Rotas => registros_de_rotas(conn, plano.metrica)?,
+    };
+    let (filtrados, sem_valor): (Vec<&Registro>, Vec<&Registro>) = registros
+        .iter()
+        .filter(|r| aceita(&filtros, r))
+        .partition(|r| r.valor.is_some());
+    let soma: f64 = filtrados.iter().filter_map(|r| r.valor).sum();
+
+    let mut grupos = Vec::new();
+    if let Some(agrupamento) = plano.agrupar_por {
+        let mut por_chave: BTreeMap<String, (f64, usize)> = BTreeMap::new();
+        for registro in &filtrados {
+            let grupo = por_chave.entry(chave_do_grupo(agrupamento, registro)).or_default();
+            grupo.0 += registro.valor.unwrap_or_default();
+            grupo.1 += 1;
+        }
+        grupos = por_chave
+            .into_iter()
+            .map(|(chave, (soma, registros))| GrupoResultado {
+                chave,
+                valor: agregar(plano.metrica, soma, registros),
+                registros,
+            })
+            .collect();
+        // Meses em ordem cronológica; os demais grupos do maior para o menor
+        if agrupamento != Agrupamento::Mes {
+            grupos.sort_by(|a, b| b.valor.total_cmp(&a.valor).then_with(|| a.chave.cmp(&b.chave)));
+        }
+    }
+
+    let limite = plano.limite.unwrap_or(LIMITE_PADRAO);
+    let grupos_omitidos = grupos.len().saturating_sub(limite);
+    grupos.truncate(limite);
+
+    Ok(ResultadoConsulta {
+        valor: agregar(plano.metrica, soma, filtrados.len()),
+        unidade: unidade(plano.metrica),
+        registros: filtrados.len(),
+        grupos,
+        grupos_omitidos,
+        registros_sem_fator: (plano.metrica == MetricaConsulta::Co2EvitadoKg).then_some(sem_valor.len()),
+    })
+}
+
+fn formatar_valor(valor: f64, unidade: &str) -> String {
+    match unidade {
+        "registros" => format!("{}", valor as u64),
+        "R$" => format!("R$ {:.2}", valor),
+        _ => format!("{:.1} {}", valor, unidade),
+    }
+}
+
+/// Resposta em texto montada a partir dos números, sem passar pela IA.
+pub fn descrever_resultado(plano: &PlanoConsulta, resultado: &ResultadoConsulta) -> String {
+    let descricao = match (plano.fonte, plano.metrica) {
+        (FonteConsulta::Coletas, MetricaConsulta::TotalKg) => "Total coletado",
+        (FonteConsulta::Coletas, MetricaConsulta::MediaKg) => "Média por coleta",
+        (FonteConsulta::Coletas, MetricaConsulta::Co2EvitadoKg) => "CO₂ evitado",
+        (FonteConsulta::Coletas, _) => "Coletas registradas",
+        (FonteConsulta::Rotas, MetricaConsulta::DistanciaPlanejadaKm) => "Distância planejada",
+        (FonteConsulta::Rotas, MetricaConsulta::LitrosPlanejados) => "Combustível planejado",
+        (FonteConsulta::Rotas, MetricaConsulta::CustoPlanejadoReais) => "Custo planejado",
+        (FonteConsulta::Rotas, _) => "Rotas",
+    };
+
+    let f = &plano.filtros;
+    let mut condicoes = Vec::new();
+    if let Some(tipo) = &f.tipo {
+        condicoes.push(format!("tipo {}", tipo));
+    }
+    if let Some(regiao) = &f.regiao {
+        condicoes.push(format!("região {}", regiao));
+    }
+    if let Some(ponto) = &f.ponto_id {
+        condicoes.push(format!("ponto {}", ponto));
+    }
+    if let Some(status) = f.status {
+        condicoes.push(format!("status {}", status.as_str()));
+    }
+    match (f.de, f.ate) {
+        (Some(de), Some(ate)) => condicoes.push(format!("de {} a {}", de, ate)),
+        (Some(de), None) => condicoes.push(format!("desde {}", de)),
+        (None, Some(ate)) => condicoes.push(format!("até {}", ate)),
+        (None, None) => {}
+    }
+
+    let mut texto = format!("{}: {}", descricao, formatar_valor(resultado.valor, resultado.unidade));
+    if !condicoes.is_empty() {
+        texto.push_str(&format!(" ({})", condicoes.join(", ")));
+    }
+    if resultado.unidade == "registros" {
+        texto.push('.');
+    } else {
+        texto.push_str(&format!(", em {} registro(s).", resultado.registros));
+    }
+    if let Some(sem_fator) = resultado.registros_sem_fator.filter(|n| *n > 0) {
+        texto.push_str(&format!(
+            " {} coleta(s) sem fator de emissão ficaram fora do cálculo.",
+            sem_fator
+        ));
+    }
+
+    if !resultado.grupos.is_empty() {
+        let grupos: Vec<String> = resultado
+            .grupos
+            .iter()
+            .map(|g| format!("{}: {}", g.chave, formatar_valor(g.valor, resultado.unidade)))
+            .collect();
+        texto.push_str(&format!(" Detalhe: {}.", grupos.join("; ")));
+        if resultado.grupos_omitidos > 0 {
+            texto.push_str(&format!(" Mais {} grupo(s) omitido(s).", resultado.grupos_omitidos));
+        }
+    }
+    texto
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dataset::VinculosColeta;
+    use crate::ia_api::{ProvedorIA, ProvedorOffline};
+    use chrono::TimeZone;
+
+    fn banco() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::criar_esquema(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO pontos_coleta (id, latitude, longitude, regiao) VALUES ('ponto_A', -23.5, -46.6, 'Norte')",
+            [],
+        )
+        .unwrap();
+        conn
+    }
+
+    fn dataset() -> Dataset {
+        let mut dataset = Dataset::new();
+        let coletas = [
+            ("plastico", 2.0, (2024, 1, 10), Some("ponto_A")),
+            ("PET", 3.0, (2024, 2, 5), Some("ponto_A")),
+            ("vidro", 5.0, (2024, 2, 20), None),
+            ("madeira", 4.0, (2024, 3, 1), None),
+        ];
+        for (tipo, quantidade, (ano, mes, dia), ponto) in coletas {
+            dataset.add_entry_at_point(
+                tipo.to_string(),
+                quantidade,
+                None,
+                Utc.with_ymd_and_hms(ano, mes, dia, 12, 0, 0).unwrap(),
+                VinculosColeta { ponto_id: ponto.map(str::to_string), ..Default::default() },
+            );
+        }
+        dataset
+    }
+
+    /// Plano devolvido pelo provedor offline configurado com `resposta`.
+    async fn plano_do_provedor(resposta: &str) -> Result<PlanoConsulta, String> {
+        let provedor = ProvedorOffline::new(resposta);
+        let texto = provedor.gerar("Quanto plástico foi coletado?").await.unwrap();
+        interpretar_plano(&texto)
+    }
+
+    fn plano(json: &str) -> PlanoConsulta {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[tokio::test]
+    async fn plano_do_provedor_offline_e_executado() {
+        let plano = plano_do_provedor(
+            r#"```json
+{"fonte": "coletas", "metrica": "total_kg", "filtros": {"tipo": "Plástico"}, "agrupar_por": "mes"}
+```"#,
+        )
+        .await
+        .unwrap();
+
+        let resultado = executar_plano(&plano, &dataset(), &banco()).unwrap();
+        assert_eq!(resultado.valor, 5.0);
+        assert_eq!(resultado.registros, 2);
+        let grupos: Vec<(&str, f64)> = resultado.grupos.iter().map(|g| (g.chave.as_str(), g.valor)).collect();
+        assert_eq!(grupos, [("2024-01", 2.0), ("2024-02", 3.0)]);
+    }
+
+    #[tokio::test]
+    async fn resposta_padrao_do_provedor_offline_nao_vira_plano() {
+        let erro = plano_do_provedor("[offline] Análise automática indisponível. Pedido: {prompt}")
+            .await
+            .unwrap_err();
+        assert_eq!(erro, "resposta sem objeto JSON");
+    }
+
+    #[tokio::test]
+    async fn provedor_offline_nao_consegue_pedir_campos_fora_do_plano() {
+        let erro = plano_do_provedor(r#"{"fonte": "coletas", "metrica": "total_kg", "sql": "DROP TABLE usuarios"}"#)
+            .await
+            .unwrap_err();
+        assert!(erro.starts_with("plano fora do formato esperado"), "{}", erro);
+
+        let erro = plano_do_provedor(r#"{"nao_suportado": "sem dados de clima"}"#).await.unwrap_err();
+        assert_eq!(erro, "pergunta fora do alcance dos dados: sem dados de clima");
+    }
+
+    #[test]
+    fn validar_rejeita_combinacoes_invalidas() {
+        let casos = [
+            (r#"{"fonte": "rotas", "metrica": "total_kg"}"#, "Métrica 'total_kg' não se aplica à fonte 'rotas'"),
+            (
+                r#"{"fonte": "coletas", "metrica": "registros", "agrupar_por": "status"}"#,
+                "Agrupamento 'status' não se aplica à fonte 'coletas'",
+            ),
+            (
+                r#"{"fonte": "coletas", "metrica": "registros", "filtros": {"status": "concluida"}}"#,
+                "Filtro 'status' só se aplica a rotas",
+            ),
+            (
+                r#"{"fonte": "rotas", "metrica": "registros", "filtros": {"regiao": "Norte"}}"#,
+                "Filtros 'tipo', 'regiao' e 'ponto_id' só se aplicam a coletas",
+            ),
+            (r#"{"fonte": "coletas", "metrica": "total_kg", "filtros": {"tipo": "  "}}"#, "Filtro 'tipo' inválido"),
+            (
+                r#"{"fonte": "coletas", "metrica": "total_kg", "filtros": {"de": "2024-02-01", "ate": "2024-01-01"}}"#,
+                "Período inválido: 2024-02-01 é depois de 2024-01-01",
+            ),
+            (r#"{"fonte": "coletas", "metrica": "total_kg", "limite": 0}"#, "Limite deve estar entre 1 e 50"),
+            (r#"{"fonte": "coletas", "metrica": "total_kg", "limite": 51}"#, "Limite deve estar entre 1 e 50"),
+        ];
+        for (json, erro) in casos {
+            assert_eq!(plano(json).validar(), Err(erro.to_string()), "{}", json);
+        }
+        assert_eq!(plano(r#"{"fonte": "rotas", "metrica": "custo_planejado_reais", "agrupar_por": "mes"}"#).validar(), Ok(()));
+    }
+
+    #[test]
+    fn executar_plano_valida_antes_de_consultar() {
+        let erro = executar_plano(&plano(r#"{"fonte": "rotas", "metrica": "media_kg"}"#), &dataset(), &banco());
+        assert_eq!(erro.unwrap_err(), "Métrica 'media_kg' não se aplica à fonte 'rotas'");
+    }
+
+    #[test]
+    fn executar_plano_agrupa_por_regiao_e_conta_coletas_sem_fator() {
+        let (dataset, conn) = (dataset(), banco());
+
+        let por_regiao = plano(r#"{"fonte": "coletas", "metrica": "registros", "agrupar_por": "regiao"}"#);
+        let resultado = executar_plano(&por_regiao, &dataset, &conn).unwrap();
+        let grupos: Vec<(&str, usize)> = resultado.grupos.iter().map(|g| (g.chave.as_str(), g.registros)).collect();
+        assert_eq!(grupos, [("Norte", 2), ("sem região", 2)]);
+
+        let co2 = plano(r#"{"fonte": "coletas", "metrica": "co2_evitado_kg", "limite": 1, "agrupar_por": "tipo"}"#);
+        let resultado = executar_plano(&co2, &dataset, &conn).unwrap();
+        assert_eq!(resultado.registros, 3);
+        assert_eq!(resultado.registros_sem_fator, Some(1));
+        assert_eq!(resultado.grupos.len(), 1);
+        assert_eq!(resultado.grupos[0].chave, "plastico");
+        assert_eq!(resultado.grupos_omitidos, 1);
+    }
+}
@@ -0,0 +1,127 @@
+// src/ia_api/mod.rs
+// Acesso aos modelos de linguagem. O provedor (Gemini, servidor compatível
+// com a API da OpenAI ou o modo offline) é escolhido pelas variáveis de
+// ambiente, então dá para rodar tudo localmente e sem rede.
+
+mod analise;
+mod consulta;
+mod provedores;
+mod resiliencia;
+mod templates;
+
+pub use analise::{
+    analise_de_fallback, calcular_estatisticas, estatisticas_de_exemplo, interpretar_resposta,
+    montar_prompt, predicao_de_exemplo, registrar_analise, variaveis_analise, AnaliseIA,
+    EstatisticasPredicao, RegistroAnalise,
+};
+pub use consulta::{
+    descrever_resultado, executar_plano, interpretar_plano, montar_prompt_plano, variaveis_plano,
+    ContextoPergunta, PlanoConsulta, ResultadoConsulta, MAX_CARACTERES_PERGUNTA,
+};
+pub use provedores::{ProvedorGemini, ProvedorOffline, ProvedorOpenAi};
+pub use resiliencia::{ConfigResiliencia, ProvedorResiliente};
+pub use templates::{
+    ativar_versao, buscar_versao, carregar_template, garantir_templates_padrao, listar_templates,
+    listar_versoes, renderizar, salvar_template, template_padrao, validar_conteudo,
+    variaveis_do_template, Idioma, NovoTemplate, PromptTemplate, TemplateUsado, ANALISE_PREDICAO,
+    PLANO_CONSULTA,
+};
+
+use async_trait::async_trait;
+use reqwest::Client;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Debug)]
+pub enum ErroIA {
+    /// Provedor mal configurado (ex.: chave de API ausente).
+    Configuracao(String),
+    /// Falha de rede ou tempo esgotado.
+    Requisicao(String),
+    /// O provedor respondeu com status HTTP de erro.
+    Http { status: u16, mensagem: String },
+    /// O provedor respondeu, mas sem o texto esperado.
+    RespostaInvalida(String),
+    /// Chamada não feita: o provedor vem falhando e o circuito está aberto.
+    CircuitoAberto,
+}
+
+impl ErroIA {
+    /// Falhas que valem nova tentativa: rede, 429 e 5xx.
+    pub fn transitorio(&self) -> bool {
+        match self {
+            ErroIA::Requisicao(_) => true,
+            ErroIA::Http { status, .. } => *status == 429 || *status >= 500,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for ErroIA {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ErroIA::Configuracao(msg) => write!(f, "configuração inválida: {}", msg),
+            ErroIA::Requisicao(msg) => write!(f, "falha na requisição: {}", msg),
+            ErroIA::Http { status, mensagem } => write!(f, "HTTP {}: {}", status, mensagem),
+            ErroIA::RespostaInvalida(msg) => write!(f, "resposta inválida: {}", msg),
+            ErroIA::CircuitoAberto => write!(f, "provedor indisponível (circuito aberto)"),
+        }
+    }
+}
+
+impl std::error::Error for ErroIA {}
+
+#[async_trait]
+pub trait ProvedorIA: Send + Sync {
+    /// Nome do provedor e do modelo, para logs e health check.
+    fn descricao(&self) -> String;
+
+    /// Envia um prompt de texto e retorna a resposta do modelo.
+    async fn gerar(&self, prompt: &str) -> Result<String, ErroIA>;
+
+    /// Estado do circuito (`fechado`, `aberto`, `meio_aberto`), quando houver.
+    fn circuito(&self) -> Option<&'static str> {
+        None
+    }
+}
+
+/// Lê uma variável de ambiente, tratando valor vazio como ausente.
+fn variavel(nome: &str) -> Option<String> {
+    std::env::var(nome).ok().filter(|v| !v.trim().is_empty())
+}
+
+/// Lê uma variável numérica, usando `padrao` se ausente ou inválida.
+fn variavel_num<T: std::str::FromStr>(nome: &str, padrao: T) -> T {
+    variavel(nome).and_then(|v| v.trim().parse().ok()).unwrap_or(padrao)
+}
+
+/// Cliente HTTP compartilhado pelos provedores remotos, com limite de
+/// tempo por chamada (`IA_TIMEOUT_SEG`).
+fn cliente_http() -> Client {
+    Client::builder()
+        .connect_timeout(Duration::from_secs(5))
+        .timeout(Duration::from_secs(variavel_num("IA_TIMEOUT_SEG", 30)))
+        .build()
+        .unwrap_or_else(|_| Client::new())
+}
+
+/// Escolhe o provedor por `IA_PROVEDOR` (`gemini`, `openai` ou `offline`).
+/// Sem a variável, usa o Gemini se houver `GEMINI_API_KEY` e o modo
+/// offline caso contrário. Os provedores remotos ganham retentativas,
+/// circuit breaker e cache de respostas.
+pub fn provedor_do_ambiente() -> Arc<dyn ProvedorIA> {
+    let escolhido = variavel("IA_PROVEDOR").map(|p| p.trim().to_lowercase());
+    let remoto: Box<dyn ProvedorIA> = match escolhido.as_deref() {
+        Some("gemini") => Box::new(ProvedorGemini::do_ambiente(cliente_http())),
+        Some("openai") => Box::new(ProvedorOpenAi::do_ambiente(cliente_http())),
+        Some("offline") => return Arc::new(ProvedorOffline::do_ambiente()),
+        Some(outro) => {
+            tracing::warn!("⚠️ IA_PROVEDOR '{}' desconhecido; usando o modo offline", outro);
+            return Arc::new(ProvedorOffline::do_ambiente());
+        }
+        None if variavel("GEMINI_API_KEY").is_some() => Box::new(ProvedorGemini::do_ambiente(cliente_http())),
+        None => return Arc::new(ProvedorOffline::do_ambiente()),
+    };
+    Arc::new(ProvedorResiliente::new(remoto, ConfigResiliencia::do_ambiente()))
+}
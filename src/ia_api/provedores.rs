@@ -0,0 +1,190 @@
+// src/ia_api/provedores.rs
+use super::{variavel, ErroIA, ProvedorIA};
+use async_trait::async_trait;
+use reqwest::{Client, Response};
+use serde_json::{json, Value};
+
+/// Limite do trecho do corpo de erro incluído na mensagem.
+const MAX_TRECHO_ERRO: usize = 200;
+
+fn trecho(texto: &str, limite: usize) -> String {
+    let mut trecho: String = texto.chars().take(limite).collect();
+    if texto.chars().count() > limite {
+        trecho.push('…');
+    }
+    trecho
+}
+
+fn erro_de_rede(e: reqwest::Error) -> ErroIA {
+    if e.is_timeout() {
+        ErroIA::Requisicao(format!("tempo esgotado ({})", e))
+    } else {
+        ErroIA::Requisicao(e.to_string())
+    }
+}
+
+/// Converte a resposta HTTP em JSON; status de erro vira `ErroIA::Http`.
+async fn ler_json(resposta: Response) -> Result<Value, ErroIA> {
+    let status = resposta.status();
+    if !status.is_success() {
+        let corpo = resposta.text().await.unwrap_or_default();
+        return Err(ErroIA::Http {
+            status: status.as_u16(),
+            mensagem: trecho(corpo.trim(), MAX_TRECHO_ERRO),
+        });
+    }
+    resposta
+        .json()
+        .await
+        .map_err(|e| ErroIA::RespostaInvalida(e.to_string()))
+}
+
+fn texto_em(json: &Value, caminho: &str) -> Result<String, ErroIA> {
+    json.pointer(caminho)
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| ErroIA::RespostaInvalida(format!("campo '{}' ausente", caminho)))
+}
+
+// ========== GEMINI ==========
+
+/// API REST do Google Gemini (`GEMINI_API_KEY`, `GEMINI_MODELO`).
+pub struct ProvedorGemini {
+    api_key: Option<String>,
+    modelo: String,
+    client: Client,
+}
+
+impl ProvedorGemini {
+    pub fn do_ambiente(client: Client) -> Self {
+        ProvedorGemini {
+            api_key: variavel("GEMINI_API_KEY"),
+            modelo: variavel("GEMINI_MODELO").unwrap_or_else(|| "gemini-2.5-flash".to_string()),
+            client,
+        }
+    }
+}
+
+#[async_trait]
+impl ProvedorIA for ProvedorGemini {
+    fn descricao(&self) -> String {
+        format!("gemini ({})", self.modelo)
+    }
+
+    async fn gerar(&self, prompt: &str) -> Result<String, ErroIA> {
+        let api_key = self
+            .api_key
+            .as_deref()
+            .ok_or_else(|| ErroIA::Configuracao("GEMINI_API_KEY não está definida".to_string()))?;
+
+        let url = format!(
+            "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent",
+            self.modelo
+        );
+        let body = json!({
+            "contents": [
+                { "parts": [ { "text": prompt } ] }
+            ]
+        });
+
+        let resposta = self
+            .client
+            .post(url)
+            .header("x-goog-api-key", api_key)
+            .json(&body)
+            .send()
+            .await
+            .map_err(erro_de_rede)?;
+
+        let json = ler_json(resposta).await?;
+        texto_em(&json, "/candidates/0/content/parts/0/text")
+    }
+}
+
+// ========== COMPATÍVEL COM OPENAI ==========
+
+/// Qualquer servidor com `POST /chat/completions` no formato da OpenAI,
+/// inclusive Ollama e llama.cpp locais (`OPENAI_BASE_URL`, `OPENAI_MODELO`
+/// e `OPENAI_API_KEY`, opcional em servidores locais).
+pub struct ProvedorOpenAi {
+    base_url: String,
+    api_key: Option<String>,
+    modelo: String,
+    client: Client,
+}
+
+impl ProvedorOpenAi {
+    pub fn do_ambiente(client: Client) -> Self {
+        let base_url = variavel("OPENAI_BASE_URL").unwrap_or_else(|| "https://api.openai.com/v1".to_string());
+        ProvedorOpenAi {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: variavel("OPENAI_API_KEY"),
+            modelo: variavel("OPENAI_MODELO").unwrap_or_else(|| "gpt-4o-mini".to_string()),
+            client,
+        }
+    }
+}
+
+#[async_trait]
+impl ProvedorIA for ProvedorOpenAi {
+    fn descricao(&self) -> String {
+        format!("openai ({} em {})", self.modelo, self.base_url)
+    }
+
+    async fn gerar(&self, prompt: &str) -> Result<String, ErroIA> {
+        let body = json!({
+            "model": self.modelo,
+            "messages": [ { "role": "user", "content": prompt } ]
+        });
+
+        let mut requisicao = self
+            .client
+            .post(format!("{}/chat/completions", self.base_url))
+            .json(&body);
+        if let Some(api_key) = &self.api_key {
+            requisicao = requisicao.bearer_auth(api_key);
+        }
+
+        let resposta = requisicao
+            .send()
+            .await
+            .map_err(erro_de_rede)?;
+
+        let json = ler_json(resposta).await?;
+        texto_em(&json, "/choices/0/message/content")
+    }
+}
+
+// ========== OFFLINE ==========
+
+const TEMPLATE_OFFLINE_PADRAO: &str =
+    "[offline] Análise automática indisponível: nenhum provedor de IA configurado. Pedido: {prompt}";
+
+/// Resposta determinística montada a partir de um template, sem rede.
+/// `IA_OFFLINE_TEMPLATE` pode trocar o texto; `{prompt}` é substituído
+/// pelo início do prompt.
+pub struct ProvedorOffline {
+    template: String,
+}
+
+impl ProvedorOffline {
+    /// `{prompt}` no template é trocado pelo início do prompt recebido.
+    pub fn new(template: impl Into<String>) -> Self {
+        ProvedorOffline { template: template.into() }
+    }
+
+    pub fn do_ambiente() -> Self {
+        ProvedorOffline::new(variavel("IA_OFFLINE_TEMPLATE").unwrap_or_else(|| TEMPLATE_OFFLINE_PADRAO.to_string()))
+    }
+}
+
+#[async_trait]
+impl ProvedorIA for ProvedorOffline {
+    fn descricao(&self) -> String {
+        "offline (template)".to_string()
+    }
+
+    async fn gerar(&self, prompt: &str) -> Result<String, ErroIA> {
+        Ok(self.template.replace("{prompt}", &trecho(prompt.trim(), 120)))
+    }
+}
@@ -0,0 +1,199 @@
+// src/ia_api/resiliencia.rs
+use super::{variavel_num, ErroIA, ProvedorIA};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+pub struct ConfigResiliencia {
+    /// Total de tentativas por chamada, incluindo a primeira.
+    pub tentativas: u32,
+    /// Espera antes da segunda tentativa; dobra a cada nova falha.
+    pub espera_inicial: Duration,
+    /// Falhas seguidas que abrem o circuito.
+    pub falhas_para_abrir: u32,
+    /// Tempo com o circuito aberto antes de testar o provedor de novo.
+    pub pausa_circuito: Duration,
+    /// Validade das respostas em cache; zero desliga o cache.
+    pub validade_cache: Duration,
+    pub max_cache: usize,
+}
+
+impl ConfigResiliencia {
+    pub fn do_ambiente() -> Self {
+        ConfigResiliencia {
+            tentativas: variavel_num("IA_TENTATIVAS", 3u32).max(1),
+            espera_inicial: Duration::from_millis(variavel_num("IA_ESPERA_INICIAL_MS", 500)),
+            falhas_para_abrir: variavel_num("IA_CIRCUITO_FALHAS", 5u32).max(1),
+            pausa_circuito: Duration::from_secs(variavel_num("IA_CIRCUITO_PAUSA_SEG", 60)),
+            validade_cache: Duration::from_secs(variavel_num("IA_CACHE_TTL_SEG", 3600)),
+            max_cache: variavel_num("IA_CACHE_MAX", 500),
+        }
+    }
+}
+
+enum Circuito {
+    Fechado { falhas: u32 },
+    Aberto { ate: Instant },
+    /// Pausa encerrada: uma única chamada de teste está em andamento. Se ela
+    /// for cancelada, outra é liberada depois de uma nova pausa.
+    MeioAberto { desde: Instant },
+}
+
+/// Envolve um provedor remoto com retentativas (429, 5xx e falhas de rede),
+/// circuit breaker e cache de respostas por prompt.
+pub struct ProvedorResiliente {
+    interno: Box<dyn ProvedorIA>,
+    config: ConfigResiliencia,
+    circuito: Mutex<Circuito>,
+    cache: Mutex<HashMap<String, (String, Instant)>>,
+}
+
+impl ProvedorResiliente {
+    pub fn new(interno: Box<dyn ProvedorIA>, config: ConfigResiliencia) -> Self {
+        ProvedorResiliente {
+            interno,
+            config,
+            circuito: Mutex::new(Circuito::Fechado { falhas: 0 }),
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn buscar_cache(&self, prompt: &str) -> Option<String> {
+        let mut cache = self.cache.lock().ok()?;
+        match cache.get(prompt) {
+            Some((resposta, guardada_em)) if guardada_em.elapsed() < self.config.validade_cache => {
+                Some(resposta.clone())
+            }
+            Some(_) => {
+                cache.remove(prompt);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn guardar_cache(&self, prompt: &str, resposta: &str) {
+        if self.config.validade_cache.is_zero() || self.config.max_cache == 0 {
+            return;
+        }
+        let Ok(mut cache) = self.cache.lock() else { return };
+        let validade = self.config.validade_cache;
+        cache.retain(|_, (_, guardada_em)| guardada_em.elapsed() < validade);
+        if cache.len() >= self.config.max_cache {
+            let mais_antiga = cache
+                .iter()
+                .min_by_key(|(_, (_, guardada_em))| *guardada_em)
+                .map(|(chave, _)| chave.clone());
+            if let Some(chave) = mais_antiga {
+                cache.remove(&chave);
+            }
+        }
+        cache.insert(prompt.to_string(), (resposta.to_string(), Instant::now()));
+    }
+
+    /// Diz se a chamada pode seguir. Passada a pausa, libera uma chamada de teste.
+    fn liberar_chamada(&self) -> bool {
+        let Ok(mut circuito) = self.circuito.lock() else { return true };
+        match *circuito {
+            Circuito::Fechado { .. } => true,
+            Circuito::Aberto { ate } if Instant::now() >= ate => {
+                *circuito = Circuito::MeioAberto { desde: Instant::now() };
+                true
+            }
+            Circuito::MeioAberto { desde } if desde.elapsed() >= self.config.pausa_circuito => {
+                *circuito = Circuito::MeioAberto { desde: Instant::now() };
+                true
+            }
+            Circuito::Aberto { .. } | Circuito::MeioAberto { .. } => false,
+        }
+    }
+
+    fn registrar_resultado(&self, resultado: &Result<String, ErroIA>) {
+        let Ok(mut circuito) = self.circuito.lock() else { return };
+        let falhas = match (&*circuito, resultado) {
+            (_, Ok(_)) => {
+                if matches!(*circuito, Circuito::MeioAberto { .. }) {
+                    tracing::info!("✅ IA: provedor respondeu, circuito fechado");
+                }
+                *circuito = Circuito::Fechado { falhas: 0 };
+                return;
+            }
+            // Erro de configuração não indica instabilidade do provedor, em
+            // nenhum estado do circuito.
+            (_, Err(ErroIA::Configuracao(_))) => return,
+            (Circuito::Fechado { falhas }, Err(_)) => falhas + 1,
+            (_, Err(_)) => {
+                tracing::warn!(
+                    "⚠️ IA: chamada de teste falhou, circuito aberto por mais {}s",
+                    self.config.pausa_circuito.as_secs()
+                );
+                *circuito = Circuito::Aberto { ate: Instant::now() + self.config.pausa_circuito };
+                return;
+            }
+        };
+
+        if falhas >= self.config.falhas_para_abrir {
+            tracing::warn!(
+                "⚠️ IA: {} falha(s) seguidas, circuito aberto por {}s",
+                falhas,
+                self.config.pausa_circuito.as_secs()
+            );
+            *circuito = Circuito::Aberto { ate: Instant::now() + self.config.pausa_circuito };
+        } else {
+            *circuito = Circuito::Fechado { falhas };
+        }
+    }
+}
+
+#[async_trait]
+impl ProvedorIA for ProvedorResiliente {
+    fn descricao(&self) -> String {
+        self.interno.descricao()
+    }
+
+    fn circuito(&self) -> Option<&'static str> {
+        let circuito = self.circuito.lock().ok()?;
+        Some(match *circuito {
+            Circuito::Fechado { .. } => "fechado",
+            Circuito::Aberto { .. } => "aberto",
+            Circuito::MeioAberto { .. } => "meio_aberto",
+        })
+    }
+
+    async fn gerar(&self, prompt: &str) -> Result<String, ErroIA> {
+        if let Some(resposta) = self.buscar_cache(prompt) {
+            tracing::info!("IA: resposta servida do cache");
+            return Ok(resposta);
+        }
+        if !self.liberar_chamada() {
+            return Err(ErroIA::CircuitoAberto);
+        }
+
+        let mut espera = self.config.espera_inicial;
+        let mut tentativa = 1;
+        let resultado = loop {
+            match self.interno.gerar(prompt).await {
+                Err(e) if e.transitorio() && tentativa < self.config.tentativas => {
+                    tracing::warn!(
+                        "⚠️ IA: tentativa {}/{} falhou ({}); nova tentativa em {}ms",
+                        tentativa,
+                        self.config.tentativas,
+                        e,
+                        espera.as_millis()
+                    );
+                    tokio::time::sleep(espera).await;
+                    espera *= 2;
+                    tentativa += 1;
+                }
+                resultado => break resultado,
+            }
+        };
+
+        self.registrar_resultado(&resultado);
+        if let Ok(resposta) = &resultado {
+            self.guardar_cache(prompt, resposta);
+        }
+        resultado
+    }
+}
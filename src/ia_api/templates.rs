@@ -0,0 +1,547 @@
+// src/ia_api/templates.rs
+// Biblioteca de templates de prompt. Cada template tem um nome, uma
+// variante por idioma e versões numeradas guardadas no banco; só uma versão
+// por nome e idioma fica ativa. As variáveis são escritas como `{{nome}}`.
+
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Template da análise que acompanha cada predição.
+pub const ANALISE_PREDICAO: &str = "analise_predicao";
+/// Template que traduz uma pergunta em plano de consulta (`POST /ia/perguntar`).
+pub const PLANO_CONSULTA: &str = "plano_consulta";
+
+/// Valores das variáveis de um template.
+pub type Variaveis = BTreeMap<&'static str, String>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Idioma {
+    #[default]
+    #[serde(rename = "pt-BR")]
+    PtBr,
+    #[serde(rename = "en")]
+    En,
+    #[serde(rename = "es")]
+    Es,
+}
+
+impl Idioma {
+    pub const TODOS: [Idioma; 3] = [Idioma::PtBr, Idioma::En, Idioma::Es];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Idioma::PtBr => "pt-BR",
+            Idioma::En => "en",
+            Idioma::Es => "es",
+        }
+    }
+}
+
+impl TryFrom<&str> for Idioma {
+    type Error = String;
+
+    fn try_from(valor: &str) -> Result<Self, Self::Error> {
+        Idioma::TODOS
+            .into_iter()
+            .find(|idioma| idioma.as_str().eq_ignore_ascii_case(valor.trim()))
+            .ok_or_else(|| format!("Idioma '{}' não suportado (use pt-BR, en ou es)", valor))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptTemplate {
+    pub nome: String,
+    pub idioma: Idioma,
+    /// Versão 0 é o template embutido no código, usado quando o banco não
+    /// tem nenhuma versão ativa.
+    pub versao: u32,
+    pub conteudo: String,
+    pub descricao: Option<String>,
+    pub ativo: bool,
+    pub criado_por: Option<u32>,
+    pub criado_em: String,
+}
+
+impl PromptTemplate {
+    pub fn usado(&self) -> TemplateUsado {
+        TemplateUsado {
+            nome: self.nome.clone(),
+            idioma: self.idioma,
+            versao: self.versao,
+        }
+    }
+}
+
+/// Identifica a versão do template que gerou um prompt.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateUsado {
+    pub nome: String,
+    pub idioma: Idioma,
+    pub versao: u32,
+}
+
+/// Nova versão enviada pelo administrador.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NovoTemplate {
+    pub nome: String,
+    #[serde(default)]
+    pub idioma: Idioma,
+    pub conteudo: String,
+    pub descricao: Option<String>,
+}
+
+struct DefinicaoTemplate {
+    nome: &'static str,
+    variaveis: &'static [&'static str],
+    padrao: fn(Idioma) -> &'static str,
+}
+
+const DEFINICOES: &[DefinicaoTemplate] = &[
+    DefinicaoTemplate {
+        nome: ANALISE_PREDICAO,
+        variaveis: &[
+            "tipo",
+            "quantidade",
+            "total_registros",
+            "total_kg",
+            "co2_evitado_total_kg",
+            "janela_recente",
+            "historico_por_tipo",
+            "demanda_prevista",
+            "predicao_modelo",
+        ],
+        padrao: padrao_analise_predicao,
+    },
+    DefinicaoTemplate {
+        nome: PLANO_CONSULTA,
+        variaveis: &["pergunta", "data_atual", "tipos_residuo", "regioes"],
+        padrao: padrao_plano_consulta,
+    },
+];
+
+fn padrao_analise_predicao(idioma: Idioma) -> &'static str {
+    match idioma {
+        Idioma::PtBr => "Você é um analista de reciclagem. Use SOMENTE os dados abaixo e não invente números.
+
+Nova coleta: {{quantidade}} kg de {{tipo}}.
+
+Histórico: {{total_registros}} registros, {{total_kg}} kg no total, {{co2_evitado_total_kg}} kg de CO₂ evitado (estimado).
+{{historico_por_tipo}}
+
+Demanda prevista por região (previsões vigentes):
+{{demanda_prevista}}
+
+Predição do modelo (a nova coleta é o último registro):
+{{predicao_modelo}}
+
+Responda APENAS com um objeto JSON, sem texto fora dele, no formato:
+{\"resumo\": \"até 50 palavras\", \"riscos\": [\"...\"], \"recomendacoes\": [\"...\"]}
+Use no máximo 3 itens em cada lista. Escreva os textos em português.",
+        Idioma::En => "You are a recycling analyst. Use ONLY the data below and do not make up numbers.
+
+New collection: {{quantidade}} kg of {{tipo}}.
+
+History: {{total_registros}} records, {{total_kg}} kg in total, {{co2_evitado_total_kg}} kg of CO₂ avoided (estimated).
+{{historico_por_tipo}}
+
+Forecast demand by region (current forecasts):
+{{demanda_prevista}}
+
+Model prediction (the new collection is the last record):
+{{predicao_modelo}}
+
+Reply ONLY with a JSON object, with no text outside it, in the format:
+{\"resumo\": \"up to 50 words\", \"riscos\": [\"...\"], \"recomendacoes\": [\"...\"]}
+Use at most 3 items in each list. Keep the JSON keys as shown and write the texts in English.",
+        Idioma::Es => "Eres un analista de reciclaje. Usa SOLO los datos de abajo y no inventes números.
+
+Nueva recolección: {{quantidade}} kg de {{tipo}}.
+
+Historial: {{total_registros}} registros, {{total_kg}} kg en total, {{co2_evitado_total_kg}} kg de CO₂ evitado (estimado).
+{{historico_por_tipo}}
+
+Demanda prevista por región (previsiones vigentes):
+{{demanda_prevista}}
+
+Predicción del modelo (la nueva recolección es el último registro):
+{{predicao_modelo}}
+
+Responde SOLO con un objeto JSON, sin texto fuera de él, en el formato:
+{\"resumo\": \"hasta 50 palabras\", \"riscos\": [\"...\"], \"recomendacoes\": [\"...\"]}
+Usa como máximo 3 elementos en cada lista. Mantén las claves del JSON y escribe los textos en español.",
+    }
+}
+
+fn padrao_plano_consulta(idioma: Idioma) -> &'static str {
+    match idioma {
+        Idioma::PtBr => "Você traduz perguntas sobre os dados de reciclagem em um plano de consulta. Não responda a pergunta; apenas monte o plano.
+
+Data de hoje: {{data_atual}}.
+Tipos de resíduo registrados: {{tipos_residuo}}.
+Regiões cadastradas: {{regioes}}.
+
+Fontes permitidas:
+- \"coletas\": metrica \"total_kg\", \"media_kg\", \"co2_evitado_kg\" ou \"registros\"; filtros \"tipo\", \"regiao\", \"ponto_id\", \"de\", \"ate\"; agrupar_por \"tipo\", \"regiao\", \"ponto\" ou \"mes\".
+- \"rotas\": metrica \"registros\", \"distancia_planejada_km\", \"litros_planejados\" ou \"custo_planejado_reais\"; filtros \"status\" (planejada, despachada, em_andamento, concluida, cancelada), \"de\", \"ate\"; agrupar_por \"status\" ou \"mes\".
+Datas no formato AAAA-MM-DD; \"de\" e \"ate\" são inclusivos.
+
+Pergunta: {{pergunta}}
+
+Responda APENAS com um objeto JSON, sem texto fora dele, no formato:
+{\"fonte\": \"coletas\", \"metrica\": \"total_kg\", \"filtros\": {\"tipo\": \"vidro\", \"regiao\": \"Norte\", \"de\": \"2024-01-01\", \"ate\": \"2024-01-31\"}, \"agrupar_por\": null, \"limite\": null}
+Omita os filtros que não se aplicam. Escreva os tipos de resíduo e as regiões como aparecem acima. Se a pergunta não puder ser respondida com esses dados, responda {\"nao_suportado\": \"motivo\"}.",
+        Idioma::En => "You translate questions about recycling data into a query plan. Do not answer the question; only build the plan.
+
+Today's date: {{data_atual}}.
+Recorded waste types: {{tipos_residuo}}.
+Registered regions: {{regioes}}.
+
+Allowed sources:
+- \"coletas\" (collections): metrica \"total_kg\", \"media_kg\", \"co2_evitado_kg\" or \"registros\"; filters \"tipo\", \"regiao\", \"ponto_id\", \"de\", \"ate\"; agrupar_por \"tipo\", \"regiao\", \"ponto\" or \"mes\".
+- \"rotas\" (routes): metrica \"registros\", \"distancia_planejada_km\", \"litros_planejados\" or \"custo_planejado_reais\"; filters \"status\" (planejada, despachada, em_andamento, concluida, cancelada), \"de\", \"ate\"; agrupar_por \"status\" or \"mes\".
+Dates as YYYY-MM-DD; \"de\" (from) and \"ate\" (to) are inclusive.
+
+Question: {{pergunta}}
+
+Reply ONLY with a JSON object, with no text outside it, in the format:
+{\"fonte\": \"coletas\", \"metrica\": \"total_kg\", \"filtros\": {\"tipo\": \"vidro\", \"regiao\": \"Norte\", \"de\": \"2024-01-01\", \"ate\": \"2024-01-31\"}, \"agrupar_por\": null, \"limite\": null}
+Leave out filters that do not apply. Waste types and regions must be written as listed above. If the question cannot be answered with this data, reply {\"nao_suportado\": \"reason\"}.",
+        Idioma::Es => "Traduces preguntas sobre los datos de reciclaje en un plan de consulta. No respondas la pregunta; solo arma el plan.
+
+Fecha de hoy: {{data_atual}}.
+Tipos de residuo registrados: {{tipos_residuo}}.
+Regiones registradas: {{regioes}}.
+
+Fuentes permitidas:
+- \"coletas\" (recolecciones): metrica \"total_kg\", \"media_kg\", \"co2_evitado_kg\" o \"registros\"; filtros \"tipo\", \"regiao\", \"ponto_id\", \"de\", \"ate\"; agrupar_por \"tipo\", \"regiao\", \"ponto\" o \"mes\".
+- \"rotas\" (rutas): metrica \"registros\", \"distancia_planejada_km\", \"litros_planejados\" o \"custo_planejado_reais\"; filtros \"status\" (planejada, despachada, em_andamento, concluida, cancelada), \"de\", \"ate\"; agrupar_por \"status\" o \"mes\".
+Fechas en formato AAAA-MM-DD; \"de\" (desde) y \"ate\" (hasta) son inclusivos.
+
+Pregunta: {{pergunta}}
+
+Responde SOLO con un objeto JSON, sin texto fuera de él, en el formato:
+{\"fonte\": \"coletas\", \"metrica\": \"total_kg\", \"filtros\": {\"tipo\": \"vidro\", \"regiao\": \"Norte\", \"de\": \"2024-01-01\", \"ate\": \"2024-01-31\"}, \"agrupar_por\": null, \"limite\": null}
+Omite los filtros que no apliquen. Escribe los tipos de residuo y las regiones como aparecen arriba. Si la pregunta no se puede responder con estos datos, responde {\"nao_suportado\": \"motivo\"}.",
+    }
+}
+
+fn definicao(nome: &str) -> Result<&'static DefinicaoTemplate, String> {
+    DEFINICOES.iter().find(|d| d.nome == nome).ok_or_else(|| {
+        let nomes: Vec<&str> = DEFINICOES.iter().map(|d| d.nome).collect();
+        format!("Template '{}' não existe (disponíveis: {})", nome, nomes.join(", "))
+    })
+}
+
+/// Variáveis aceitas por um template.
+pub fn variaveis_do_template(nome: &str) -> Result<&'static [&'static str], String> {
+    definicao(nome).map(|d| d.variaveis)
+}
+
+/// Versão embutida no código (versão 0).
+pub fn template_padrao(nome: &str, idioma: Idioma) -> Result<PromptTemplate, String> {
+    let definicao = definicao(nome)?;
+    Ok(PromptTemplate {
+        nome: definicao.nome.to_string(),
+        idioma,
+        versao: 0,
+        conteudo: (definicao.padrao)(idioma).to_string(),
+        descricao: Some("Template embutido".to_string()),
+        ativo: true,
+        criado_por: None,
+        criado_em: String::new(),
+    })
+}
+
+/// Nomes das variáveis usadas no conteúdo, na ordem em que aparecem.
+fn variaveis_usadas(conteudo: &str) -> Result<Vec<&str>, String> {
+    let mut usadas = Vec::new();
+    let mut resto = conteudo;
+    while let Some(inicio) = resto.find("{{") {
+        let depois = &resto[inicio + 2..];
+        let fim = depois
+            .find("}}")
+            .ok_or_else(|| "Variável aberta com '{{' sem '}}' correspondente".to_string())?;
+        usadas.push(depois[..fim].trim());
+        resto = &depois[fim + 2..];
+    }
+    Ok(usadas)
+}
+
+/// Confere se o conteúdo só usa variáveis conhecidas do template.
+pub fn validar_conteudo(nome: &str, conteudo: &str) -> Result<(), String> {
+    let definicao = definicao(nome)?;
+    if conteudo.trim().is_empty() {
+        return Err("Conteúdo do template não pode ser vazio".to_string());
+    }
+    let desconhecidas: Vec<&str> = variaveis_usadas(conteudo)?
+        .into_iter()
+        .filter(|v| !definicao.variaveis.contains(v))
+        .collect();
+    if !desconhecidas.is_empty() {
+        return Err(format!(
+            "Variáveis desconhecidas: {} (aceitas: {})",
+            desconhecidas.join(", "),
+            definicao.variaveis.join(", ")
+        ));
+    }
+    Ok(())
+}
+
+/// Substitui cada `{{variavel}}` pelo seu valor. Variáveis sem valor
+/// ficam como estão.
+pub fn renderizar(conteudo: &str, variaveis: &Variaveis) -> String {
+    let mut saida = String::with_capacity(conteudo.len());
+    let mut resto = conteudo;
+    while let Some(inicio) = resto.find("{{") {
+        let depois = &resto[inicio + 2..];
+        let Some(fim) = depois.find("}}") else { break };
+        saida.push_str(&resto[..inicio]);
+        match variaveis.get(depois[..fim].trim()) {
+            Some(valor) => saida.push_str(valor),
+            None => saida.push_str(&resto[inicio..inicio + fim + 4]),
+        }
+        resto = &depois[fim + 2..];
+    }
+    saida.push_str(resto);
+    saida
+}
+
+// ========== BANCO DE DADOS ==========
+
+const COLUNAS: &str = "nome, idioma, versao, conteudo, descricao, ativo, criado_por, criado_em";
+
+fn ler_template(row: &Row) -> rusqlite::Result<PromptTemplate> {
+    let idioma: String = row.get(1)?;
+    let idioma = Idioma::try_from(idioma.as_str()).map_err(|_| {
+        rusqlite::Error::InvalidColumnType(1, "idioma".to_string(), rusqlite::types::Type::Text)
+    })?;
+    Ok(PromptTemplate {
+        nome: row.get(0)?,
+        idioma,
+        versao: row.get(2)?,
+        conteudo: row.get(3)?,
+        descricao: row.get(4)?,
+        ativo: row.get(5)?,
+        criado_por: row.get(6)?,
+        criado_em: row.get(7)?,
+    })
+}
+
+/// Grava os templates embutidos como versão 1 para cada nome e idioma que
+/// ainda não tenha nenhuma versão no banco. Se o texto embutido mudou e a
+/// versão ativa também foi gravada pelo servidor (não por um
+/// administrador), o novo texto vira a próxima versão ativa.
+pub fn garantir_templates_padrao(conn: &Connection) -> rusqlite::Result<()> {
+    let agora = Utc::now().to_rfc3339();
+    for definicao in DEFINICOES {
+        for idioma in Idioma::TODOS {
+            let (nome, codigo_idioma, conteudo) = (definicao.nome, idioma.as_str(), (definicao.padrao)(idioma));
+            conn.execute(
+                "INSERT INTO prompts_templates
+                    (nome, idioma, versao, conteudo, descricao, ativo, criado_por, criado_em)
+                 SELECT ?1, ?2, 1, ?3, 'Versão inicial', 1, NULL, ?4
+                 WHERE NOT EXISTS (
+                    SELECT 1 FROM prompts_templates WHERE nome = ?1 AND idioma = ?2
+                 )",
+                params![nome, codigo_idioma, conteudo, agora],
+            )?;
+
+            let tx = conn.unchecked_transaction()?;
+            let atualizar = tx.execute(
+                "UPDATE prompts_templates SET ativo = 0
+                 WHERE nome = ?1 AND idioma = ?2 AND ativo = 1 AND criado_por IS NULL
+                 AND NOT EXISTS (
+                    SELECT 1 FROM prompts_templates WHERE nome = ?1 AND idioma = ?2 AND conteudo = ?3
+                 )",
+                params![nome, codigo_idioma, conteudo],
+            )?;
+            if atualizar > 0 {
+                tx.execute(
+                    "INSERT INTO prompts_templates
+                        (nome, idioma, versao, conteudo, descricao, ativo, criado_por, criado_em)
+                     SELECT ?1, ?2, MAX(versao) + 1, ?3, 'Atualização do template embutido', 1, NULL, ?4
+                     FROM prompts_templates WHERE nome = ?1 AND idioma = ?2",
+                    params![nome, codigo_idioma, conteudo, agora],
+                )?;
+            }
+            tx.commit()?;
+        }
+    }
+    Ok(())
+}
+
+pub fn buscar_template_ativo(
+    conn: &Connection,
+    nome: &str,
+    idioma: Idioma,
+) -> rusqlite::Result<Option<PromptTemplate>> {
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM prompts_templates WHERE nome = ?1 AND idioma = ?2 AND ativo = 1",
+            COLUNAS
+        ),
+        params![nome, idioma.as_str()],
+        ler_template,
+    )
+    .optional()
+}
+
+/// Versão ativa do template; sem versão no banco (ou com erro de leitura),
+/// usa a embutida no código.
+pub fn carregar_template(conn: &Connection, nome: &str, idioma: Idioma) -> Result<PromptTemplate, String> {
+    match buscar_template_ativo(conn, nome, idioma) {
+        Ok(Some(template)) => Ok(template),
+        Ok(None) => template_padrao(nome, idioma),
+        Err(e) => {
+            tracing::warn!("⚠️ Erro ao ler o template '{}' ({}): {}", nome, idioma.as_str(), e);
+            template_padrao(nome, idioma)
+        }
+    }
+}
+
+/// Versões ativas de todos os templates.
+pub fn listar_templates(conn: &Connection) -> rusqlite::Result<Vec<PromptTemplate>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM prompts_templates WHERE ativo = 1 ORDER BY nome, idioma",
+        COLUNAS
+    ))?;
+    let templates = stmt
+        .query_map([], ler_template)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(templates)
+}
+
+/// Histórico de versões de um template, da mais nova para a mais antiga.
+pub fn listar_versoes(
+    conn: &Connection,
+    nome: &str,
+    idioma: Option<Idioma>,
+) -> rusqlite::Result<Vec<PromptTemplate>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM prompts_templates
+         WHERE nome = ?1 AND (?2 IS NULL OR idioma = ?2)
+         ORDER BY idioma, versao DESC",
+        COLUNAS
+    ))?;
+    let templates = stmt
+        .query_map(params![nome, idioma.map(|i| i.as_str())], ler_template)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(templates)
+}
+
+/// Grava o conteúdo como nova versão. Com `ativar`, ela passa a ser a
+/// usada nas análises; sem, fica guardada para prévia e ativação depois.
+pub fn salvar_template(
+    conn: &Connection,
+    novo: &NovoTemplate,
+    ativar: bool,
+    usuario_id: u32,
+) -> Result<PromptTemplate, String> {
+    let nome = novo.nome.trim();
+    validar_conteudo(nome, &novo.conteudo)?;
+
+    let erro_banco = |e: rusqlite::Error| format!("Erro ao salvar template: {}", e);
+    let tx = conn.unchecked_transaction().map_err(erro_banco)?;
+    let versao: u32 = tx
+        .query_row(
+            "SELECT COALESCE(MAX(versao), 0) + 1 FROM prompts_templates WHERE nome = ?1 AND idioma = ?2",
+            params![nome, novo.idioma.as_str()],
+            |row| row.get(0),
+        )
+        .map_err(erro_banco)?;
+    if ativar {
+        tx.execute(
+            "UPDATE prompts_templates SET ativo = 0 WHERE nome = ?1 AND idioma = ?2",
+            params![nome, novo.idioma.as_str()],
+        )
+        .map_err(erro_banco)?;
+    }
+
+    let agora = Utc::now().to_rfc3339();
+    tx.execute(
+        "INSERT INTO prompts_templates
+            (nome, idioma, versao, conteudo, descricao, ativo, criado_por, criado_em)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            nome,
+            novo.idioma.as_str(),
+            versao,
+            novo.conteudo,
+            novo.descricao,
+            ativar,
+            usuario_id,
+            agora,
+        ],
+    )
+    .map_err(erro_banco)?;
+    tx.commit().map_err(erro_banco)?;
+
+    Ok(PromptTemplate {
+        nome: nome.to_string(),
+        idioma: novo.idioma,
+        versao,
+        conteudo: novo.conteudo.clone(),
+        descricao: novo.descricao.clone(),
+        ativo: ativar,
+        criado_por: Some(usuario_id),
+        criado_em: agora,
+    })
+}
+
+/// Torna ativa uma versão já gravada (por exemplo, para voltar atrás).
+pub fn ativar_versao(
+    conn: &Connection,
+    nome: &str,
+    idioma: Idioma,
+    versao: u32,
+) -> Result<PromptTemplate, String> {
+    let erro_banco = |e: rusqlite::Error| format!("Erro ao ativar template: {}", e);
+    let tx = conn.unchecked_transaction().map_err(erro_banco)?;
+    let alteradas = tx
+        .execute(
+            "UPDATE prompts_templates SET ativo = (versao = ?3) WHERE nome = ?1 AND idioma = ?2
+             AND EXISTS (
+                SELECT 1 FROM prompts_templates WHERE nome = ?1 AND idioma = ?2 AND versao = ?3
+             )",
+            params![nome, idioma.as_str(), versao],
+        )
+        .map_err(erro_banco)?;
+    if alteradas == 0 {
+        return Err(format!(
+            "Versão {} do template '{}' ({}) não encontrada",
+            versao,
+            nome,
+            idioma.as_str()
+        ));
+    }
+    tx.commit().map_err(erro_banco)?;
+
+    buscar_template_ativo(conn, nome, idioma)
+        .map_err(erro_banco)?
+        .ok_or_else(|| "Template ativado não encontrado".to_string())
+}
+
+/// Versão específica ou, sem `versao`, a ativa.
+pub fn buscar_versao(
+    conn: &Connection,
+    nome: &str,
+    idioma: Idioma,
+    versao: Option<u32>,
+) -> Result<PromptTemplate, String> {
+    let Some(versao) = versao else {
+        return carregar_template(conn, nome, idioma);
+    };
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM prompts_templates WHERE nome = ?1 AND idioma = ?2 AND versao = ?3",
+            COLUNAS
+        ),
+        params![nome, idioma.as_str(), versao],
+        ler_template,
+    )
+    .optional()
+    .map_err(|e| format!("Erro ao acessar banco de dados: {}", e))?
+    .ok_or_else(|| format!("Versão {} do template '{}' ({}) não encontrada", versao, nome, idioma.as_str()))
+}
@@ -0,0 +1,603 @@
+// src/importacao.rs
+// Importação de coletas históricas a partir de CSV, inclusive planilhas
+// exportadas pelo Excel (`;`, vírgula decimal, BOM e linha `sep=`). Cada
+// linha válida vira uma coleta com a data da planilha. O hash das células
+// da linha fica em `coletas_importadas`, de modo que reimportar o mesmo
+// arquivo não duplica coletas.
+
+use crate::dataset::{Dataset, VinculosColeta};
+use crate::otimizacao::validar_vinculos_coleta;
+use crate::tipos_residuo::Catalogo;
+use crate::unidades::{Leitura, UnidadeMedida};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Formatos tentados, depois de RFC 3339, quando `formato_data` não é informado.
+const FORMATOS_DATA_PADRAO: [&str; 3] = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d"];
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct FormatoCsv {
+    /// Um caractere; `\t` para tabulação.
+    pub delimitador: String,
+    /// Números como `1.234,5`.
+    pub decimal_virgula: bool,
+    /// Formato chrono, como `%d/%m/%Y` ou `%d/%m/%Y %H:%M`.
+    pub formato_data: Option<String>,
+    pub cabecalho: bool,
+}
+
+impl Default for FormatoCsv {
+    fn default() -> Self {
+        FormatoCsv {
+            delimitador: ",".to_string(),
+            decimal_virgula: false,
+            formato_data: None,
+            cabecalho: true,
+        }
+    }
+}
+
+/// Coluna pelo nome no cabeçalho ou pela posição (a partir de 1).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Coluna {
+    Posicao(usize),
+    Nome(String),
+}
+
+/// Coluna de cada campo. Sem mapeamento, vale a coluna do cabeçalho com o
+/// nome do próprio campo; sem cabeçalho, `tipo`, `quantidade` e `data` são
+/// as três primeiras.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct MapeamentoColunas {
+    pub tipo: Option<Coluna>,
+    pub quantidade: Option<Coluna>,
+    pub data: Option<Coluna>,
+    pub unidade: Option<Coluna>,
+    pub observacoes: Option<Coluna>,
+    pub ponto_id: Option<Coluna>,
+    pub rota_id: Option<Coluna>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PedidoImportacao {
+    pub csv: String,
+    /// Nome do arquivo de origem, guardado no histórico de importações.
+    #[serde(default)]
+    pub arquivo: Option<String>,
+    /// Valida e conta as linhas sem gravar nada.
+    #[serde(default)]
+    pub simular: bool,
+    #[serde(default)]
+    pub formato: FormatoCsv,
+    #[serde(default)]
+    pub colunas: MapeamentoColunas,
+    /// Unidade das linhas sem coluna de unidade (padrão kg).
+    #[serde(default)]
+    pub unidade: UnidadeMedida,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ErroLinha {
+    /// Linha no arquivo, contando o cabeçalho.
+    pub linha: usize,
+    pub erro: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AvisoLinha {
+    /// Linha no arquivo, contando o cabeçalho.
+    pub linha: usize,
+    pub aviso: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RelatorioImportacao {
+    /// Ausente na simulação.
+    pub importacao_id: Option<i64>,
+    pub simulacao: bool,
+    pub linhas: usize,
+    /// Linhas válidas e inéditas: gravadas, ou que seriam gravadas na simulação.
+    pub importadas: usize,
+    /// Já importadas antes.
+    pub duplicadas: usize,
+    pub com_erro: usize,
+    pub kg_por_tipo: BTreeMap<String, f64>,
+    pub erros: Vec<ErroLinha>,
+    /// Linhas importadas que merecem conferência, como as repetidas no
+    /// próprio arquivo.
+    pub avisos: Vec<AvisoLinha>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportacaoColetas {
+    pub id: i64,
+    pub usuario_id: u32,
+    pub arquivo: Option<String>,
+    pub linhas: usize,
+    pub importadas: usize,
+    pub duplicadas: usize,
+    pub com_erro: usize,
+    pub importado_em: String,
+}
+
+/// Posição (a partir de 0) de cada campo no registro.
+struct Colunas {
+    tipo: usize,
+    quantidade: usize,
+    data: usize,
+    unidade: Option<usize>,
+    observacoes: Option<usize>,
+    ponto_id: Option<usize>,
+    rota_id: Option<usize>,
+}
+
+fn localizar(
+    cabecalho: Option<&csv::StringRecord>,
+    coluna: Option<&Coluna>,
+    campo: &str,
+    posicao_padrao: Option<usize>,
+) -> Result<Option<usize>, String> {
+    let por_nome = |nome: &str| {
+        cabecalho.and_then(|c| c.iter().position(|titulo| titulo.eq_ignore_ascii_case(nome.trim())))
+    };
+    match coluna {
+        Some(Coluna::Posicao(0)) => Err(format!("Posição da coluna de '{}' começa em 1", campo)),
+        Some(Coluna::Posicao(posicao)) => Ok(Some(posicao - 1)),
+        Some(Coluna::Nome(nome)) if cabecalho.is_none() => Err(format!(
+            "Sem cabeçalho, a coluna de '{}' precisa ser indicada pela posição, não por '{}'",
+            campo, nome
+        )),
+        Some(Coluna::Nome(nome)) => por_nome(nome)
+            .map(Some)
+            .ok_or_else(|| format!("Coluna '{}' (campo '{}') não encontrada no cabeçalho", nome, campo)),
+        None if cabecalho.is_none() => Ok(posicao_padrao),
+        None => match (por_nome(campo), posicao_padrao) {
+            (Some(posicao), _) => Ok(Some(posicao)),
+            (None, Some(_)) => Err(format!(
+                "Coluna '{}' não encontrada no cabeçalho; indique-a em colunas.{}",
+                campo, campo
+            )),
+            (None, None) => Ok(None),
+        },
+    }
+}
+
+impl Colunas {
+    fn localizar(mapeamento: &MapeamentoColunas, cabecalho: Option<&csv::StringRecord>) -> Result<Self, String> {
+        let obrigatoria = |coluna: Option<&Coluna>, campo: &str, padrao: usize| {
+            localizar(cabecalho, coluna, campo, Some(padrao)).map(|posicao| posicao.unwrap_or(padrao))
+        };
+        Ok(Colunas {
+            tipo: obrigatoria(mapeamento.tipo.as_ref(), "tipo", 0)?,
+            quantidade: obrigatoria(mapeamento.quantidade.as_ref(), "quantidade", 1)?,
+            data: obrigatoria(mapeamento.data.as_ref(), "data", 2)?,
+            unidade: localizar(cabecalho, mapeamento.unidade.as_ref(), "unidade", None)?,
+            observacoes: localizar(cabecalho, mapeamento.observacoes.as_ref(), "observacoes", None)?,
+            ponto_id: localizar(cabecalho, mapeamento.ponto_id.as_ref(), "ponto_id", None)?,
+            rota_id: localizar(cabecalho, mapeamento.rota_id.as_ref(), "rota_id", None)?,
+        })
+    }
+}
+
+fn delimitador(formato: &FormatoCsv) -> Result<u8, String> {
+    match formato.delimitador.as_str() {
+        "\\t" | "\t" => Ok(b'\t'),
+        d if d.len() == 1 => Ok(d.as_bytes()[0]),
+        outro => Err(format!("Delimitador inválido: '{}' (use um único caractere)", outro)),
+    }
+}
+
+/// Com vírgula decimal, o ponto só é aceito como separador de milhar
+/// (`1.234,5`); `12.5` é recusado em vez de virar 125.
+fn ler_numero(valor: &str, decimal_virgula: bool) -> Option<f32> {
+    let texto = if decimal_virgula {
+        let (inteira, decimal) = valor.split_once(',').unwrap_or((valor, ""));
+        let mut grupos = inteira.split('.');
+        let mut digitos = grupos.next()?.to_string();
+        for grupo in grupos {
+            if grupo.len() != 3 {
+                return None;
+            }
+            digitos.push_str(grupo);
+        }
+        if decimal.is_empty() {
+            digitos
+        } else {
+            format!("{}.{}", digitos, decimal)
+        }
+    } else {
+        valor.to_string()
+    };
+    texto.parse::<f32>().ok().filter(|n| n.is_finite())
+}
+
+/// Datas sem horário ficam à meia-noite UTC.
+fn ler_data(valor: &str, formato: Option<&str>) -> Option<DateTime<Utc>> {
+    let formatos = match formato {
+        Some(formato) => vec![formato],
+        None => {
+            if let Ok(data) = DateTime::parse_from_rfc3339(valor) {
+                return Some(data.with_timezone(&Utc));
+            }
+            FORMATOS_DATA_PADRAO.to_vec()
+        }
+    };
+    formatos
+        .into_iter()
+        .find_map(|f| {
+            NaiveDateTime::parse_from_str(valor, f)
+                .ok()
+                .or_else(|| NaiveDate::parse_from_str(valor, f).ok()?.and_hms_opt(0, 0, 0))
+        })
+        .map(|data| data.and_utc())
+}
+
+/// FNV-1a de 64 bits: estável entre versões do Rust, ao contrário do
+/// `DefaultHasher`, e suficiente para reconhecer linhas repetidas. Recebe
+/// as células como estão no arquivo, para que uma mudança no catálogo ou na
+/// normalização não faça uma linha já importada parecer inédita.
+fn hash_linha(partes: &[&str]) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for parte in partes {
+        for byte in parte.bytes().chain(std::iter::once(0x1f)) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    format!("{:016x}", hash)
+}
+
+/// Chave gravada em `coletas_importadas` para a `ocorrencia`-ésima linha com
+/// esse hash no arquivo. Linhas iguais no mesmo arquivo são coletas distintas
+/// (dois sacos iguais no mesmo dia), e cada uma tem sua chave: reimportar o
+/// arquivo reconhece todas, e outro arquivo com uma repetição a mais grava só
+/// a que falta.
+fn chave_importacao(hash: &str, ocorrencia: usize) -> String {
+    match ocorrencia {
+        1 => hash.to_string(),
+        n => format!("{}#{}", hash, n),
+    }
+}
+
+struct LinhaImportada {
+    hash: String,
+    tipo: String,
+    leitura: Leitura,
+    quantidade_kg: f32,
+    timestamp: DateTime<Utc>,
+    observacoes: Option<String>,
+    ponto_id: Option<String>,
+    rota_id: Option<i64>,
+}
+
+fn ler_linha(
+    conn: &Connection,
+    catalogo: &Catalogo,
+    pedido: &PedidoImportacao,
+    colunas: &Colunas,
+    registro: &csv::StringRecord,
+) -> Result<LinhaImportada, String> {
+    let campo = |posicao: Option<usize>| posicao.and_then(|p| registro.get(p)).filter(|v| !v.is_empty());
+
+    let tipo = campo(Some(colunas.tipo)).ok_or("Tipo vazio")?;
+    let celula_quantidade = campo(Some(colunas.quantidade)).ok_or("Quantidade vazia")?;
+    let quantidade = ler_numero(celula_quantidade, pedido.formato.decimal_virgula)
+        .ok_or_else(|| format!("Quantidade '{}' não é um número", celula_quantidade))?;
+    let celula_unidade = campo(colunas.unidade);
+    let unidade = match celula_unidade {
+        Some(unidade) => UnidadeMedida::try_from(unidade)?,
+        None => pedido.unidade,
+    };
+    let coleta = catalogo.normalizar(tipo, Leitura { quantidade, unidade })?;
+
+    let data = campo(Some(colunas.data)).ok_or("Data vazia")?;
+    let formato_data = pedido.formato.formato_data.as_deref();
+    let timestamp = ler_data(data, formato_data).ok_or_else(|| match formato_data {
+        Some(formato) => format!("Data '{}' não segue o formato '{}'", data, formato),
+        None => format!("Data '{}' inválida (use AAAA-MM-DD, RFC 3339 ou informe formato_data)", data),
+    })?;
+    if timestamp > Utc::now() {
+        return Err(format!("Data '{}' está no futuro", data));
+    }
+
+    let ponto_id = campo(colunas.ponto_id).map(str::to_string);
+    let celula_rota = campo(colunas.rota_id);
+    let rota_id = match celula_rota {
+        Some(rota) => Some(rota.parse::<i64>().map_err(|_| format!("Rota '{}' inválida", rota))?),
+        None => None,
+    };
+    validar_vinculos_coleta(conn, ponto_id.as_deref(), rota_id)?;
+    let observacoes = campo(colunas.observacoes).map(str::to_string);
+
+    // Observações ficam de fora: corrigir um comentário na planilha não
+    // transforma a linha em outra coleta.
+    let hash = hash_linha(&[
+        tipo,
+        celula_quantidade,
+        celula_unidade.unwrap_or(pedido.unidade.as_str()),
+        data,
+        ponto_id.as_deref().unwrap_or_default(),
+        celula_rota.unwrap_or_default(),
+    ]);
+
+    Ok(LinhaImportada {
+        hash,
+        tipo: coleta.tipo,
+        leitura: coleta.leitura,
+        quantidade_kg: coleta.quantidade_kg,
+        timestamp,
+        observacoes,
+        ponto_id,
+        rota_id,
+    })
+}
+
+fn ja_importada(conn: &Connection, hash: &str) -> Result<bool, String> {
+    conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM coletas_importadas WHERE hash = ?1)",
+        [hash],
+        |row| row.get(0),
+    )
+    .map_err(|e| format!("Erro ao acessar banco de dados: {}", e))
+}
+
+/// Valida todas as linhas e, fora da simulação, grava as inéditas no
+/// dataset e registra a importação. Erros de linha vão para o relatório;
+/// só problemas no arquivo inteiro (delimitador, cabeçalho, colunas) viram
+/// `Err`. Quem chama grava o dataset e confirma a transação de `conn`.
+pub fn importar_coletas(
+    conn: &Connection,
+    dataset: &mut Dataset,
+    catalogo: &Catalogo,
+    usuario_id: u32,
+    pedido: &PedidoImportacao,
+) -> Result<RelatorioImportacao, String> {
+    let mut delimitador = delimitador(&pedido.formato)?;
+    let mut conteudo = pedido.csv.trim_start_matches('\u{feff}');
+    let mut linhas_puladas = 0;
+    // O Excel escreve `sep=;` na primeira linha quando o separador difere do padrão da região
+    if let Some((primeira, resto)) = conteudo.split_once('\n') {
+        if let Some(sep) = primeira.trim().strip_prefix("sep=").filter(|s| s.len() == 1) {
+            delimitador = sep.as_bytes()[0];
+            conteudo = resto;
+            linhas_puladas = 1;
+        }
+    }
+
+    let mut leitor = csv::ReaderBuilder::new()
+        .delimiter(delimitador)
+        .has_headers(pedido.formato.cabecalho)
+        .trim(csv::Trim::All)
+        .flexible(true)
+        .from_reader(conteudo.as_bytes());
+    let cabecalho = match pedido.formato.cabecalho {
+        true => Some(leitor.headers().map_err(|e| format!("Erro ao ler o cabeçalho: {}", e))?.clone()),
+        false => None,
+    };
+    let colunas = Colunas::localizar(&pedido.colunas, cabecalho.as_ref())?;
+
+    let registros: Vec<_> = leitor.records().collect();
+    let mut relatorio = RelatorioImportacao { simulacao: pedido.simular, ..Default::default() };
+    // Hash -> (primeira linha em que apareceu, ocorrências no arquivo).
+    let mut vistas: HashMap<String, (usize, usize)> = HashMap::new();
+    let mut novas = Vec::new();
+    let passo_progresso = (registros.len() / 10).max(1);
+
+    for (indice, registro) in registros.into_iter().enumerate() {
+        if indice % passo_progresso == 0 {
+            let progresso = 10 + indice * 80 / (passo_progresso * 10);
+            tracing::info!(progresso = progresso.min(90), "Validando linha {}", indice + 1);
+        }
+
+        let posicao = match &registro {
+            Ok(r) => r.position(),
+            Err(e) => e.position(),
+        };
+        let linha = linhas_puladas
+            + posicao
+                .map(|p| p.line() as usize)
+                .unwrap_or(indice + 1 + usize::from(pedido.formato.cabecalho));
+        let registro = match registro {
+            Ok(r) => r,
+            Err(e) => {
+                relatorio.linhas += 1;
+                relatorio.com_erro += 1;
+                relatorio.erros.push(ErroLinha { linha, erro: e.to_string() });
+                continue;
+            }
+        };
+        if registro.iter().all(|campo| campo.is_empty()) {
+            continue;
+        }
+        relatorio.linhas += 1;
+
+        let importada = match ler_linha(conn, catalogo, pedido, &colunas, &registro) {
+            Ok(importada) => importada,
+            Err(erro) => {
+                relatorio.com_erro += 1;
+                relatorio.erros.push(ErroLinha { linha, erro });
+                continue;
+            }
+        };
+        let (primeira_linha, ocorrencias) = vistas.entry(importada.hash.clone()).or_insert((linha, 0));
+        *ocorrencias += 1;
+        let chave = chave_importacao(&importada.hash, *ocorrencias);
+        if ja_importada(conn, &chave)? {
+            relatorio.duplicadas += 1;
+            continue;
+        }
+        if *ocorrencias > 1 {
+            relatorio.avisos.push(AvisoLinha {
+                linha,
+                aviso: format!("Linha igual à linha {}; importada como outra coleta", primeira_linha),
+            });
+        }
+
+        relatorio.importadas += 1;
+        *relatorio.kg_por_tipo.entry(importada.tipo.clone()).or_default() += importada.quantidade_kg as f64;
+        novas.push((chave, importada));
+    }
+
+    if relatorio.linhas == 0 {
+        return Err("O arquivo não tem linhas de coleta".to_string());
+    }
+    for kg in relatorio.kg_por_tipo.values_mut() {
+        *kg = (*kg * 100.0).round() / 100.0;
+    }
+    if pedido.simular {
+        return Ok(relatorio);
+    }
+
+    tracing::info!(progresso = 95, "Gravando {} coletas", novas.len());
+    conn.execute(
+        "INSERT INTO importacoes_coletas
+            (usuario_id, arquivo, linhas, importadas, duplicadas, com_erro, importado_em)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            usuario_id,
+            pedido.arquivo,
+            relatorio.linhas,
+            relatorio.importadas,
+            relatorio.duplicadas,
+            relatorio.com_erro,
+            Utc::now().to_rfc3339(),
+        ],
+    )
+    .map_err(|e| format!("Erro ao registrar importação: {}", e))?;
+    let importacao_id = conn.last_insert_rowid();
+
+    for (chave, nova) in novas {
+        let coleta_id = dataset.add_entry_in_unit_at(
+            nova.tipo,
+            nova.leitura,
+            nova.quantidade_kg,
+            nova.observacoes,
+            nova.timestamp,
+            VinculosColeta {
+                usuario_id: Some(usuario_id),
+                ponto_id: nova.ponto_id,
+                rota_id: nova.rota_id,
+            },
+        );
+        conn.execute(
+            "INSERT INTO coletas_importadas (hash, coleta_id, importacao_id) VALUES (?1, ?2, ?3)",
+            params![chave, coleta_id, importacao_id],
+        )
+        .map_err(|e| format!("Erro ao registrar importação: {}", e))?;
+    }
+
+    relatorio.importacao_id = Some(importacao_id);
+    Ok(relatorio)
+}
+
+/// Importações gravadas, da mais recente para a mais antiga.
+pub fn listar_importacoes(conn: &Connection) -> rusqlite::Result<Vec<ImportacaoColetas>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, usuario_id, arquivo, linhas, importadas, duplicadas, com_erro, importado_em
+         FROM importacoes_coletas
+         ORDER BY id DESC",
+    )?;
+
+    let importacoes = stmt
+        .query_map([], |row| {
+            Ok(ImportacaoColetas {
+                id: row.get(0)?,
+                usuario_id: row.get(1)?,
+                arquivo: row.get(2)?,
+                linhas: row.get(3)?,
+                importadas: row.get(4)?,
+                duplicadas: row.get(5)?,
+                com_erro: row.get(6)?,
+                importado_em: row.get(7)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    Ok(importacoes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    #[test]
+    fn ler_numero_com_ponto_decimal() {
+        assert_eq!(ler_numero("12.5", false), Some(12.5));
+        assert_eq!(ler_numero("3", false), Some(3.0));
+        assert_eq!(ler_numero("12,5", false), None);
+        assert_eq!(ler_numero("abc", false), None);
+        assert_eq!(ler_numero("inf", false), None);
+    }
+
+    #[test]
+    fn ler_numero_com_virgula_decimal() {
+        assert_eq!(ler_numero("12,5", true), Some(12.5));
+        assert_eq!(ler_numero("1.234,5", true), Some(1234.5));
+        assert_eq!(ler_numero("1.234.567", true), Some(1234567.0));
+        assert_eq!(ler_numero("3", true), Some(3.0));
+        // O ponto só vale como separador de milhar.
+        assert_eq!(ler_numero("12.5", true), None);
+        assert_eq!(ler_numero("1.23,4", true), None);
+        assert_eq!(ler_numero("1,2,3", true), None);
+    }
+
+    #[test]
+    fn ler_data_sem_formato() {
+        let utc = |a, m, d, h, min, s| Some(Utc.with_ymd_and_hms(a, m, d, h, min, s).unwrap());
+        assert_eq!(ler_data("2024-01-05", None), utc(2024, 1, 5, 0, 0, 0));
+        assert_eq!(ler_data("2024-01-05 10:20:30", None), utc(2024, 1, 5, 10, 20, 30));
+        assert_eq!(ler_data("2024-01-05T10:20:30", None), utc(2024, 1, 5, 10, 20, 30));
+        assert_eq!(ler_data("2024-01-05T10:00:00-03:00", None), utc(2024, 1, 5, 13, 0, 0));
+        assert_eq!(ler_data("05/01/2024", None), None);
+        assert_eq!(ler_data("2024-02-30", None), None);
+    }
+
+    #[test]
+    fn ler_data_com_formato() {
+        let utc = |a, m, d, h, min| Some(Utc.with_ymd_and_hms(a, m, d, h, min, 0).unwrap());
+        assert_eq!(ler_data("05/01/2024", Some("%d/%m/%Y")), utc(2024, 1, 5, 0, 0));
+        assert_eq!(ler_data("05/01/2024 10:30", Some("%d/%m/%Y %H:%M")), utc(2024, 1, 5, 10, 30));
+        assert_eq!(ler_data("31/02/2024", Some("%d/%m/%Y")), None);
+        // Com formato informado, os padrões não são tentados.
+        assert_eq!(ler_data("2024-01-05", Some("%d/%m/%Y")), None);
+    }
+
+    fn importar(conn: &Connection, dataset: &mut Dataset, csv: &str) -> RelatorioImportacao {
+        let catalogo = Catalogo::carregar(conn).unwrap();
+        let pedido: PedidoImportacao = serde_json::from_value(json!({ "csv": csv })).unwrap();
+        importar_coletas(conn, dataset, &catalogo, 1, &pedido).unwrap()
+    }
+
+    #[test]
+    fn linhas_repetidas_no_arquivo_sao_importadas_com_aviso_e_reconhecidas_ao_reimportar() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::criar_esquema(&conn).unwrap();
+        let mut dataset = Dataset::new();
+        let csv = "tipo,quantidade,data,observacoes\n\
+                   PET,2,2024-01-05,\n\
+                   vidro,3,2024-01-05,\n\
+                   PET,2,2024-01-05,outra observação\n";
+
+        let primeira = importar(&conn, &mut dataset, csv);
+        assert_eq!((primeira.importadas, primeira.duplicadas), (3, 0));
+        assert_eq!(primeira.avisos.len(), 1);
+        assert_eq!(primeira.avisos[0].linha, 4);
+        assert_eq!(primeira.avisos[0].aviso, "Linha igual à linha 2; importada como outra coleta");
+        assert_eq!(dataset.entries.len(), 3);
+
+        let reimportacao = importar(&conn, &mut dataset, csv);
+        assert_eq!((reimportacao.importadas, reimportacao.duplicadas), (0, 3));
+        assert!(reimportacao.avisos.is_empty());
+
+        // Uma repetição a mais grava só a que falta.
+        let mais_uma = importar(&conn, &mut dataset, &format!("{}PET,2,2024-01-05,\n", csv));
+        assert_eq!((mais_uma.importadas, mais_uma.duplicadas), (1, 3));
+        assert_eq!(dataset.entries.len(), 4);
+    }
+}
@@ -0,0 +1,361 @@
+// src/jobs.rs
+// Acompanhamento de operações longas (predição, pré-processamento,
+// otimização e importação de coletas). Cada job roda dentro de um span
+// `job`, filho do span da requisição criado pelo TraceLayer; a `CamadaJobs`
+// transforma os eventos de tracing desse span em progresso e linhas de log
+// para os streams SSE.
+
+use chrono::Utc;
+use futures_util::FutureExt;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id};
+use tracing::{Event, Instrument, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+const CAPACIDADE_EVENTOS: usize = 512;
+/// Eventos guardados por job para quem se inscreve depois do início.
+const MAX_EVENTOS_POR_JOB: usize = 500;
+/// Jobs finalizados mantidos em memória; os mais antigos são descartados.
+const MAX_JOBS_FINALIZADOS: usize = 100;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TipoJob {
+    Predicao,
+    Preprocessamento,
+    Otimizacao,
+    Importacao,
+}
+
+impl TipoJob {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TipoJob::Predicao => "predicao",
+            TipoJob::Preprocessamento => "preprocessamento",
+            TipoJob::Otimizacao => "otimizacao",
+            TipoJob::Importacao => "importacao",
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum StatusJob {
+    EmExecucao,
+    Concluido,
+    Falhou,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Job {
+    pub id: u64,
+    pub tipo: TipoJob,
+    pub usuario_id: u32,
+    pub status: StatusJob,
+    pub progresso: u8,
+    pub etapa: Option<String>,
+    pub iniciado_em: String,
+    pub finalizado_em: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resultado: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub erro: Option<String>,
+}
+
+/// Mensagens dos streams SSE (campo `tipo` no JSON e nome do evento SSE).
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "tipo", rename_all = "snake_case")]
+pub enum EventoJob {
+    Iniciado { job: Job },
+    Progresso { job_id: u64, percentual: u8, etapa: String },
+    Log { job_id: u64, nivel: String, mensagem: String, registrado_em: String },
+    Concluido { job_id: u64, resultado: Value },
+    Falhou { job_id: u64, erro: String },
+}
+
+impl EventoJob {
+    pub fn job_id(&self) -> u64 {
+        match self {
+            EventoJob::Iniciado { job } => job.id,
+            EventoJob::Progresso { job_id, .. }
+            | EventoJob::Log { job_id, .. }
+            | EventoJob::Concluido { job_id, .. }
+            | EventoJob::Falhou { job_id, .. } => *job_id,
+        }
+    }
+
+    pub fn nome(&self) -> &'static str {
+        match self {
+            EventoJob::Iniciado { .. } => "iniciado",
+            EventoJob::Progresso { .. } => "progresso",
+            EventoJob::Log { .. } => "log",
+            EventoJob::Concluido { .. } => "concluido",
+            EventoJob::Falhou { .. } => "falhou",
+        }
+    }
+
+    pub fn finaliza_job(&self) -> bool {
+        matches!(self, EventoJob::Concluido { .. } | EventoJob::Falhou { .. })
+    }
+}
+
+struct RegistroJob {
+    job: Job,
+    eventos: Vec<EventoJob>,
+}
+
+pub struct RegistroJobs {
+    proximo_id: AtomicU64,
+    jobs: Mutex<HashMap<u64, RegistroJob>>,
+    eventos: broadcast::Sender<EventoJob>,
+}
+
+impl Default for RegistroJobs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RegistroJobs {
+    pub fn new() -> Self {
+        RegistroJobs {
+            proximo_id: AtomicU64::new(1),
+            jobs: Mutex::new(HashMap::new()),
+            eventos: broadcast::channel(CAPACIDADE_EVENTOS).0,
+        }
+    }
+
+    /// Aplica o evento ao job, guarda no histórico e repassa aos inscritos.
+    /// Tudo sob o mesmo lock de `acompanhar`, para que ninguém perca ou
+    /// receba em dobro um evento entre o histórico e o canal.
+    fn publicar(&self, evento: EventoJob) {
+        let Ok(mut jobs) = self.jobs.lock() else { return };
+        let Some(registro) = jobs.get_mut(&evento.job_id()) else { return };
+
+        match &evento {
+            EventoJob::Iniciado { .. } | EventoJob::Log { .. } => {}
+            EventoJob::Progresso { percentual, etapa, .. } => {
+                registro.job.progresso = *percentual;
+                registro.job.etapa = Some(etapa.clone());
+            }
+            EventoJob::Concluido { resultado, .. } => {
+                registro.job.status = StatusJob::Concluido;
+                registro.job.progresso = 100;
+                registro.job.resultado = Some(resultado.clone());
+                registro.job.finalizado_em = Some(Utc::now().to_rfc3339());
+            }
+            EventoJob::Falhou { erro, .. } => {
+                registro.job.status = StatusJob::Falhou;
+                registro.job.erro = Some(erro.clone());
+                registro.job.finalizado_em = Some(Utc::now().to_rfc3339());
+            }
+        }
+        if registro.eventos.len() < MAX_EVENTOS_POR_JOB || evento.finaliza_job() {
+            registro.eventos.push(evento.clone());
+        }
+        let _ = self.eventos.send(evento);
+    }
+
+    /// Remove os jobs finalizados mais antigos além do limite.
+    fn descartar_antigos(jobs: &mut HashMap<u64, RegistroJob>) {
+        let mut finalizados: Vec<u64> = jobs
+            .values()
+            .filter(|r| r.job.status != StatusJob::EmExecucao)
+            .map(|r| r.job.id)
+            .collect();
+        if finalizados.len() <= MAX_JOBS_FINALIZADOS {
+            return;
+        }
+        finalizados.sort_unstable();
+        for id in &finalizados[..finalizados.len() - MAX_JOBS_FINALIZADOS] {
+            jobs.remove(id);
+        }
+    }
+
+    pub fn criar(&self, tipo: TipoJob, usuario_id: u32) -> Job {
+        let job = Job {
+            id: self.proximo_id.fetch_add(1, Ordering::Relaxed),
+            tipo,
+            usuario_id,
+            status: StatusJob::EmExecucao,
+            progresso: 0,
+            etapa: None,
+            iniciado_em: Utc::now().to_rfc3339(),
+            finalizado_em: None,
+            resultado: None,
+            erro: None,
+        };
+        if let Ok(mut jobs) = self.jobs.lock() {
+            Self::descartar_antigos(&mut jobs);
+            jobs.insert(job.id, RegistroJob { job: job.clone(), eventos: Vec::new() });
+        }
+        self.publicar(EventoJob::Iniciado { job: job.clone() });
+        job
+    }
+
+    pub fn buscar(&self, id: u64) -> Option<Job> {
+        self.jobs.lock().ok()?.get(&id).map(|r| r.job.clone())
+    }
+
+    pub fn listar(&self) -> Vec<Job> {
+        let mut jobs: Vec<Job> = match self.jobs.lock() {
+            Ok(jobs) => jobs.values().map(|r| r.job.clone()).collect(),
+            Err(_) => Vec::new(),
+        };
+        jobs.sort_by_key(|j| std::cmp::Reverse(j.id));
+        jobs
+    }
+
+    /// Eventos já emitidos pelo job e um receptor para os próximos.
+    pub fn acompanhar(&self, id: u64) -> Option<(Vec<EventoJob>, broadcast::Receiver<EventoJob>)> {
+        let jobs = self.jobs.lock().ok()?;
+        let registro = jobs.get(&id)?;
+        Some((registro.eventos.clone(), self.eventos.subscribe()))
+    }
+
+    /// Receptor de todos os eventos de todos os jobs.
+    pub fn assinar_todos(&self) -> broadcast::Receiver<EventoJob> {
+        self.eventos.subscribe()
+    }
+
+    /// Executa `tarefa` dentro do span do job e registra o resultado final.
+    /// O span é criado na chamada, então herda o span da requisição mesmo
+    /// quando o futuro é levado para `tokio::spawn`.
+    pub fn executar<T, F>(self: &Arc<Self>, job: &Job, tarefa: F) -> impl Future<Output = Result<T, String>>
+    where
+        T: Serialize,
+        F: Future<Output = Result<T, String>>,
+    {
+        let span = tracing::info_span!("job", job_id = job.id, tipo = job.tipo.as_str());
+        let registro = Arc::clone(self);
+        let job_id = job.id;
+
+        async move {
+            tracing::info!("⚙️ Job {} iniciado", job_id);
+            // Um pânico na tarefa não pode deixar o job eternamente em execução.
+            let resultado = match AssertUnwindSafe(tarefa).catch_unwind().await {
+                Ok(resultado) => resultado,
+                Err(_) => Err("Job interrompido por uma falha interna".to_string()),
+            };
+            match &resultado {
+                Ok(valor) => {
+                    tracing::info!("✅ Job {} concluído", job_id);
+                    let resultado = serde_json::to_value(valor).unwrap_or(Value::Null);
+                    registro.publicar(EventoJob::Concluido { job_id, resultado });
+                }
+                Err(erro) => {
+                    tracing::error!("❌ Job {} falhou: {}", job_id, erro);
+                    registro.publicar(EventoJob::Falhou { job_id, erro: erro.clone() });
+                }
+            }
+            resultado
+        }
+        .instrument(span)
+    }
+}
+
+/// Marca guardada nas extensões do span `job`.
+struct JobDoSpan(u64);
+
+#[derive(Default)]
+struct CamposEvento {
+    job_id: Option<u64>,
+    progresso: Option<u64>,
+    mensagem: Option<String>,
+}
+
+impl Visit for CamposEvento {
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        match field.name() {
+            "job_id" => self.job_id = Some(value),
+            "progresso" => self.progresso = Some(value),
+            _ => {}
+        }
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        if let Ok(value) = u64::try_from(value) {
+            self.record_u64(field, value);
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.mensagem = Some(value.to_string());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.mensagem = Some(format!("{:?}", value));
+        }
+    }
+}
+
+/// Camada de tracing que repassa ao `RegistroJobs` os eventos emitidos
+/// dentro de um span `job`: com o campo `progresso` viram eventos de
+/// progresso, os demais viram linhas de log.
+pub struct CamadaJobs {
+    registro: Arc<RegistroJobs>,
+}
+
+impl CamadaJobs {
+    pub fn new(registro: Arc<RegistroJobs>) -> Self {
+        CamadaJobs { registro }
+    }
+}
+
+impl<S> Layer<S> for CamadaJobs
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if attrs.metadata().name() != "job" {
+            return;
+        }
+        let mut campos = CamposEvento::default();
+        attrs.record(&mut campos);
+        if let (Some(job_id), Some(span)) = (campos.job_id, ctx.span(id)) {
+            span.extensions_mut().insert(JobDoSpan(job_id));
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let Some(escopo) = ctx.event_scope(event) else { return };
+        let Some(job_id) = escopo
+            .into_iter()
+            .find_map(|span| span.extensions().get::<JobDoSpan>().map(|j| j.0))
+        else {
+            return;
+        };
+
+        let mut campos = CamposEvento::default();
+        event.record(&mut campos);
+        let mensagem = campos.mensagem.unwrap_or_default();
+
+        let evento = match campos.progresso {
+            Some(percentual) => EventoJob::Progresso {
+                job_id,
+                percentual: percentual.min(100) as u8,
+                etapa: mensagem,
+            },
+            None => EventoJob::Log {
+                job_id,
+                nivel: event.metadata().level().to_string(),
+                mensagem,
+                registrado_em: Utc::now().to_rfc3339(),
+            },
+        };
+        self.registro.publicar(evento);
+    }
+}
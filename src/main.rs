@@ -1,12 +1,22 @@
 mod auth;
+#[allow(dead_code)] // Interface de terminal (fora do fluxo do servidor)
 mod actions;
 mod dataset;
 mod modelo;
 mod predicao;
-mod ia_api; // Módulo da IA (Gemini)
+mod ia_api; // Provedores de IA (Gemini, OpenAI, offline)
+#[allow(dead_code)]
 mod predicoes_module; // Hub da IA
 pub mod otimizacao; // Módulo de Otimização (com 'pub')
 mod api; // Módulo da API REST
+mod mqtt; // Assinante MQTT (sensores e GPS)
+mod jobs; // Progresso de jobs longos (SSE)
+mod emissoes; // Fatores de emissão de CO₂ por material
+mod relatorios; // Relatório de impacto ambiental
+mod tipos_residuo; // Catálogo de tipos de resíduo e apelidos
+mod unidades; // Unidades de medida das coletas
+mod coletas; // Correção e remoção de coletas
+mod importacao; // Importação de coletas históricas (CSV)
 
 use auth::{Usuario, PerfilUsuario};
 use actions::{
@@ -22,10 +32,13 @@ use std::fs::{self, File};
 use std::io::{stdin, stdout, Write};
 use std::path::Path;
 use bcrypt::{hash, verify, DEFAULT_COST};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
 
 const DB_FILE: &str = "sistema.db";
 
 
+#[allow(dead_code)] // Interface de terminal (fora do fluxo do servidor)
 fn get_input(prompt: &str) -> String {
     print!("{}", prompt);
     stdout().flush().unwrap(); 
@@ -34,7 +47,28 @@ fn get_input(prompt: &str) -> String {
     input.trim().to_string()
 }
 
-fn init_db(conn: &Connection) -> Result<(), Box<dyn Error>> {
+/// Migração simples para bancos criados antes de uma coluna existir.
+fn adicionar_coluna_se_ausente(
+    conn: &Connection,
+    tabela: &str,
+    coluna: &str,
+    definicao: &str,
+) -> Result<(), Box<dyn Error>> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", tabela))?;
+    let existe = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .filter_map(|r| r.ok())
+        .any(|nome| nome == coluna);
+
+    if !existe {
+        conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", tabela, coluna, definicao), [])?;
+    }
+    Ok(())
+}
+
+/// Tabelas, migrações e dados padrão (templates, fatores e tipos), sem o
+/// usuário admin nem as pastas de arquivos.
+fn criar_esquema(conn: &Connection) -> Result<(), Box<dyn Error>> {
     conn.execute(
         "CREATE TABLE IF NOT EXISTS usuarios (
             id              INTEGER PRIMARY KEY,
@@ -45,6 +79,163 @@ fn init_db(conn: &Connection) -> Result<(), Box<dyn Error>> {
         [],
     )?;
 
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS rotas_otimizadas (
+            id                      INTEGER PRIMARY KEY,
+            usuario_id              INTEGER NOT NULL,
+            pedido_json             TEXT NOT NULL,
+            comparacao_json         TEXT NOT NULL,
+            estrategia_escolhida    TEXT NOT NULL,
+            distancia_planejada_km  REAL NOT NULL,
+            litros_planejados       REAL NOT NULL,
+            custo_planejado_reais   REAL NOT NULL,
+            status                  TEXT NOT NULL,
+            criada_em               TEXT NOT NULL,
+            atualizada_em           TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS rotas_historico_status (
+            id                  INTEGER PRIMARY KEY,
+            rota_id             INTEGER NOT NULL REFERENCES rotas_otimizadas(id),
+            status_anterior     TEXT,
+            status_novo         TEXT NOT NULL,
+            usuario_id          INTEGER NOT NULL,
+            observacao          TEXT,
+            registrado_em       TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS rotas_checkins (
+            id              INTEGER PRIMARY KEY,
+            rota_id         INTEGER NOT NULL REFERENCES rotas_otimizadas(id),
+            ponto_id        TEXT NOT NULL,
+            chegada_em      TEXT NOT NULL,
+            odometro_km     REAL,
+            coletado_json   TEXT NOT NULL,
+            usuario_id      INTEGER NOT NULL,
+            registrado_em   TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS pontos_coleta (
+            id          TEXT PRIMARY KEY,
+            nome        TEXT,
+            latitude    REAL NOT NULL,
+            longitude   REAL NOT NULL,
+            regiao      TEXT
+        );
+        CREATE TABLE IF NOT EXISTS perfis_pesos (
+            nome                            TEXT PRIMARY KEY,
+            descricao                       TEXT,
+            peso_distancia                  REAL NOT NULL,
+            peso_demanda                    REAL NOT NULL,
+            peso_risco_transbordo           REAL NOT NULL,
+            peso_tempo_desde_ultima_visita  REAL NOT NULL,
+            atualizado_em                   TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS sensores (
+            id              TEXT PRIMARY KEY,
+            ponto_id        TEXT NOT NULL,
+            descricao       TEXT,
+            ativo           INTEGER NOT NULL DEFAULT 1,
+            cadastrado_em   TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS leituras_sensores (
+            id                  INTEGER PRIMARY KEY,
+            sensor_id           TEXT NOT NULL REFERENCES sensores(id),
+            ponto_id            TEXT NOT NULL,
+            nivel_percentual    REAL NOT NULL,
+            bateria_percentual  REAL,
+            medido_em           TEXT NOT NULL,
+            recebido_em         TEXT NOT NULL,
+            UNIQUE (sensor_id, medido_em)
+        );
+        CREATE INDEX IF NOT EXISTS idx_leituras_sensores_ponto
+            ON leituras_sensores (ponto_id, medido_em);
+        CREATE TABLE IF NOT EXISTS telemetria_veiculos (
+            id              INTEGER PRIMARY KEY,
+            veiculo_id      TEXT NOT NULL,
+            rota_id         INTEGER REFERENCES rotas_otimizadas(id),
+            latitude        REAL NOT NULL,
+            longitude       REAL NOT NULL,
+            velocidade_kmh  REAL,
+            medido_em       TEXT NOT NULL,
+            recebido_em     TEXT NOT NULL,
+            UNIQUE (veiculo_id, medido_em)
+        );
+        CREATE TABLE IF NOT EXISTS prompts_templates (
+            id          INTEGER PRIMARY KEY,
+            nome        TEXT NOT NULL,
+            idioma      TEXT NOT NULL,
+            versao      INTEGER NOT NULL,
+            conteudo    TEXT NOT NULL,
+            descricao   TEXT,
+            ativo       INTEGER NOT NULL DEFAULT 0,
+            criado_por  INTEGER REFERENCES usuarios(id),
+            criado_em   TEXT NOT NULL,
+            UNIQUE (nome, idioma, versao)
+        );
+        CREATE TABLE IF NOT EXISTS fatores_emissao (
+            id                      INTEGER PRIMARY KEY,
+            material                TEXT NOT NULL,
+            fator_kg_co2_por_kg     REAL NOT NULL,
+            fonte                   TEXT,
+            valido_desde            TEXT NOT NULL,
+            cadastrado_por          INTEGER REFERENCES usuarios(id),
+            cadastrado_em           TEXT NOT NULL,
+            UNIQUE (material, valido_desde)
+        );
+
+        CREATE TABLE IF NOT EXISTS tipos_residuo (
+            codigo      TEXT PRIMARY KEY,
+            nome_pt_br  TEXT NOT NULL,
+            nome_en     TEXT NOT NULL,
+            nome_es     TEXT NOT NULL,
+            categoria   TEXT NOT NULL,
+            unidade     TEXT NOT NULL DEFAULT 'kg',
+            densidade_kg_m3 REAL,
+            densidade_aterro_kg_m3 REAL
+        );
+
+        CREATE TABLE IF NOT EXISTS tipos_residuo_aliases (
+            alias   TEXT PRIMARY KEY,
+            codigo  TEXT NOT NULL REFERENCES tipos_residuo(codigo) ON DELETE CASCADE
+        );
+        CREATE TABLE IF NOT EXISTS coletas_correcoes (
+            id              INTEGER PRIMARY KEY,
+            coleta_id       INTEGER NOT NULL,
+            acao            TEXT NOT NULL,
+            anterior_json   TEXT NOT NULL,
+            novo_json       TEXT,
+            motivo          TEXT NOT NULL,
+            usuario_id      INTEGER NOT NULL,
+            registrado_em   TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_coletas_correcoes_coleta
+            ON coletas_correcoes(coleta_id);
+        CREATE TABLE IF NOT EXISTS importacoes_coletas (
+            id              INTEGER PRIMARY KEY,
+            usuario_id      INTEGER NOT NULL,
+            arquivo         TEXT,
+            linhas          INTEGER NOT NULL,
+            importadas      INTEGER NOT NULL,
+            duplicadas      INTEGER NOT NULL,
+            com_erro        INTEGER NOT NULL,
+            importado_em    TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS coletas_importadas (
+            hash            TEXT PRIMARY KEY,
+            coleta_id       INTEGER NOT NULL,
+            importacao_id   INTEGER NOT NULL REFERENCES importacoes_coletas(id)
+        );",
+    )?;
+    adicionar_coluna_se_ausente(conn, "rotas_otimizadas", "veiculo_id", "TEXT")?;
+    adicionar_coluna_se_ausente(conn, "tipos_residuo", "densidade_kg_m3", "REAL")?;
+    adicionar_coluna_se_ausente(conn, "tipos_residuo", "densidade_aterro_kg_m3", "REAL")?;
+    ia_api::garantir_templates_padrao(conn)?;
+    emissoes::garantir_fatores_padrao(conn)?;
+    tipos_residuo::garantir_tipos_padrao(conn)?;
+    Ok(())
+}
+
+fn init_db(conn: &Connection) -> Result<(), Box<dyn Error>> {
+    criar_esquema(conn)?;
+
     let admin_nome = "admin";
     let admin_pass = "admin";
     let admin_perfil = PerfilUsuario::Administrador.as_str();
@@ -89,6 +280,7 @@ fn init_db(conn: &Connection) -> Result<(), Box<dyn Error>> {
 }
 
 
+#[allow(dead_code)]
 fn handle_create_user(conn: &Connection) -> Result<(), Box<dyn Error>> {
     println!("\n--- 📝 Criar Novo Usuário ---");
     let nome = get_input("Nome de usuário: ");
@@ -116,6 +308,7 @@ fn handle_create_user(conn: &Connection) -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+#[allow(dead_code)]
 fn handle_login(conn: &Connection) -> Result<Option<Usuario>, Box<dyn Error>> {
     println!("\n--- 🔑 Tela de Login ---");
     let nome = get_input("Nome de usuário: ");
@@ -165,6 +358,7 @@ fn handle_login(conn: &Connection) -> Result<Option<Usuario>, Box<dyn Error>> {
 }
 
 
+#[allow(dead_code)]
 async fn show_app_menu(
     usuario: &Usuario, 
     conn: &Connection, 
@@ -265,6 +459,7 @@ async fn show_app_menu(
 }
 
 
+#[allow(dead_code)]
 fn handle_manage_users(admin: &Usuario, conn: &Connection) -> Result<(), Box<dyn Error>> {
     if !admin.pode_gerenciar_usuarios() {
         println!("❌ Acesso negado.");
@@ -287,6 +482,7 @@ fn handle_manage_users(admin: &Usuario, conn: &Connection) -> Result<(), Box<dyn
     Ok(())
 }
 
+#[allow(dead_code)]
 fn list_users(conn: &Connection) -> Result<(), Box<dyn Error>> {
     println!("\n--- 👥 Lista de Usuários ---");
 
@@ -308,6 +504,7 @@ fn list_users(conn: &Connection) -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+#[allow(dead_code)]
 fn handle_add_distancia(estado: &EstadoOtimizacao) -> Result<(), Box<dyn Error>> {
     println!("\n--- 🗺️  Adicionar Nova Distância ---");
     let origem = get_input("  -> Ponto de Origem: ");
@@ -322,25 +519,30 @@ fn handle_add_distancia(estado: &EstadoOtimizacao) -> Result<(), Box<dyn Error>>
         }
     };
 
+    let mao_unica = get_input("  -> Mão única? (s/N): ");
+    let simetrica = !mao_unica.eq_ignore_ascii_case("s");
+
     let dados = PedidoNovaDistancia {
-        origem: origem.clone(),
-        destino: destino.clone(),
+        origem,
+        destino,
         custo,
+        simetrica,
     };
     
-    otimizacao::alimentar_distancia(estado, dados);
-
-    let dados_reversos = PedidoNovaDistancia {
-        origem: destino,
-        destino: origem,
-        custo,
-    };
-    otimizacao::alimentar_distancia(estado, dados_reversos);
+    if let Err(e) = otimizacao::alimentar_distancia(estado, dados) {
+        println!("❌ {}", e);
+        return Ok(());
+    }
 
-    println!("✅ Distância (e rota reversa) adicionada/atualizada com sucesso!");
+    if simetrica {
+        println!("✅ Distância (e rota reversa) adicionada/atualizada com sucesso!");
+    } else {
+        println!("✅ Distância (somente ida) adicionada/atualizada com sucesso!");
+    }
     Ok(())
 }
 
+#[allow(dead_code)]
 fn delete_user(conn: &Connection, admin_id: u32) -> Result<(), Box<dyn Error>> {
     println!("\n--- ❌ Deletar Usuário ---");
     let id_str = get_input("Digite o ID do usuário a deletar: ");
@@ -374,8 +576,14 @@ fn delete_user(conn: &Connection, admin_id: u32) -> Result<(), Box<dyn Error>> {
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
-    // Inicializar logging
-    tracing_subscriber::fmt::init();
+    // Inicializar logging; a camada de jobs repassa os eventos dos spans
+    // `job` para os streams SSE de progresso
+    let registro_jobs = std::sync::Arc::new(jobs::RegistroJobs::new());
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer())
+        .with(jobs::CamadaJobs::new(registro_jobs.clone()))
+        .with(tracing_subscriber::filter::LevelFilter::INFO)
+        .init();
 
     dotenv::dotenv().ok();
 
@@ -385,10 +593,25 @@ async fn main() -> Result<(), Box<dyn Error>> {
     init_db(&conn)?;
     println!("✅ Banco de dados inicializado.");
 
-    let estado_otimizacao = std::sync::Arc::new(otimizacao::EstadoOtimizacao::new());
+    // Com a matriz de distâncias corrompida o servidor não sobe, a não ser
+    // que o modo degradado (somente leitura) seja pedido explicitamente.
+    let modo_degradado = std::env::var("DISTANCIAS_MODO_DEGRADADO")
+        .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+        .unwrap_or(false);
+    let estado_otimizacao = std::sync::Arc::new(otimizacao::EstadoOtimizacao::new(modo_degradado)?);
 
     // Criar router (sem necessidade de secret JWT)
-    let app = api::routes::create_router(conn, estado_otimizacao, "".to_string());
+    let auth_state = api::middleware::AuthState::new(conn, "".to_string());
+
+    // Assinante MQTT opcional, compartilhando a conexão com a API
+    if let Some(config_mqtt) = mqtt::ConfigMqtt::do_ambiente() {
+        mqtt::iniciar(config_mqtt, auth_state.conn.clone(), estado_otimizacao.clone());
+    }
+
+    let provedor_ia = ia_api::provedor_do_ambiente();
+    println!("🤖 Provedor de IA: {}", provedor_ia.descricao());
+
+    let app = api::routes::create_router(auth_state, estado_otimizacao, registro_jobs, provedor_ia);
 
     // Iniciar servidor
     let port = std::env::var("PORT")
@@ -24,11 +24,11 @@ impl ModeloML {
     pub fn treinar(&mut self, _dataset: &Dataset) {
         // Simulação: "ajusta" o coeficiente com base em média das quantidades
         self.treinado = true;
-        println!("Modelo '{}' treinado (simulado).", self.tipo);
+        tracing::info!("Modelo '{}' treinado (simulado).", self.tipo);
     }
 
     pub fn prever(&self, dataset: &Dataset) -> Predicao {
-        let coef = self.parametros.get(0).cloned().unwrap_or(1.0);
+        let coef = self.parametros.first().cloned().unwrap_or(1.0);
         let resultados: Vec<f32> = dataset
             .entries
             .iter()
@@ -55,6 +55,7 @@ impl ModeloML {
         Ok(())
     }
 
+    #[allow(dead_code)]
     pub fn carregar(caminho: &str) -> Result<ModeloML, Box<dyn Error>> {
         let mut f = File::open(caminho)?;
         let mut s = String::new();
@@ -0,0 +1,224 @@
+// src/mqtt.rs
+// Assinante MQTT opcional: recebe dos gateways as leituras de nível dos
+// sensores e as posições GPS dos caminhões e grava em sistema.db.
+
+use crate::otimizacao::{self, EstadoOtimizacao, EventoRastreamento, LeituraSensor, PosicaoVeiculo};
+use rumqttc::{AsyncClient, Event, MqttOptions, Packet, QoS};
+use rusqlite::Connection;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const INTERVALO_RECONEXAO_SEG: u64 = 5;
+
+pub struct ConfigMqtt {
+    pub host: String,
+    pub porta: u16,
+    pub client_id: String,
+    pub usuario: Option<String>,
+    pub senha: Option<String>,
+    pub topico_nivel: String,
+    pub topico_gps: String,
+}
+
+impl ConfigMqtt {
+    /// Lê a configuração das variáveis `MQTT_*`. Sem `MQTT_HOST` o
+    /// assinante fica desligado.
+    pub fn do_ambiente() -> Option<Self> {
+        let host = std::env::var("MQTT_HOST").ok().filter(|h| !h.trim().is_empty())?;
+        let var = |nome: &str, padrao: &str| std::env::var(nome).unwrap_or_else(|_| padrao.to_string());
+
+        Some(ConfigMqtt {
+            host,
+            porta: var("MQTT_PORTA", "1883").parse().unwrap_or(1883),
+            client_id: var("MQTT_CLIENT_ID", "sistema_permissoes"),
+            usuario: std::env::var("MQTT_USUARIO").ok(),
+            senha: std::env::var("MQTT_SENHA").ok(),
+            topico_nivel: var("MQTT_TOPICO_NIVEL", "sensores/+/nivel"),
+            topico_gps: var("MQTT_TOPICO_GPS", "veiculos/+/gps"),
+        })
+    }
+}
+
+/// Verifica se `topico` casa com o filtro MQTT (`+` = um nível, `#` = o resto).
+fn topico_corresponde(filtro: &str, topico: &str) -> bool {
+    let mut niveis = topico.split('/');
+    for parte in filtro.split('/') {
+        match (parte, niveis.next()) {
+            ("#", _) => return true,
+            ("+", Some(_)) => {}
+            (p, Some(nivel)) if p == nivel => {}
+            _ => return false,
+        }
+    }
+    niveis.next().is_none()
+}
+
+/// Valor do primeiro `+` do filtro no tópico (ex.: o id em `sensores/+/nivel`).
+fn segmento_curinga<'a>(filtro: &str, topico: &'a str) -> Option<&'a str> {
+    filtro
+        .split('/')
+        .zip(topico.split('/'))
+        .find(|(parte, _)| *parte == "+")
+        .map(|(_, nivel)| nivel)
+}
+
+/// Desserializa o payload JSON; se `campo_id` não vier no corpo, usa o
+/// segmento do tópico correspondente ao `+` do filtro.
+fn ler_payload<T: DeserializeOwned>(
+    payload: &[u8],
+    campo_id: &str,
+    filtro: &str,
+    topico: &str,
+) -> Result<T, String> {
+    let mut valor: Value =
+        serde_json::from_slice(payload).map_err(|e| format!("JSON inválido: {}", e))?;
+    if let (Some(objeto), Some(id)) = (valor.as_object_mut(), segmento_curinga(filtro, topico)) {
+        objeto
+            .entry(campo_id)
+            .or_insert_with(|| Value::String(id.to_string()));
+    }
+    serde_json::from_value(valor).map_err(|e| format!("Payload inválido: {}", e))
+}
+
+fn processar_mensagem(
+    config: &ConfigMqtt,
+    conn: &Mutex<Connection>,
+    estado: &EstadoOtimizacao,
+    topico: &str,
+    payload: &[u8],
+) -> Result<bool, String> {
+    let conn = conn.lock().map_err(|_| "Banco de dados indisponível".to_string())?;
+
+    if topico_corresponde(&config.topico_nivel, topico) {
+        let leitura: LeituraSensor = ler_payload(payload, "sensor_id", &config.topico_nivel, topico)?;
+        otimizacao::registrar_leitura(&conn, &leitura)
+    } else if topico_corresponde(&config.topico_gps, topico) {
+        let posicao: PosicaoVeiculo = ler_payload(payload, "veiculo_id", &config.topico_gps, topico)?;
+        let Some(gravada) = otimizacao::registrar_posicao(&conn, &posicao)? else {
+            return Ok(false);
+        };
+        if let Some(rota_id) = gravada.rota_id {
+            estado.publicar_evento(EventoRastreamento::Posicao { rota_id, posicao: gravada });
+        }
+        Ok(true)
+    } else {
+        Err("Tópico não assinado".to_string())
+    }
+}
+
+async fn executar(config: ConfigMqtt, conn: Arc<Mutex<Connection>>, estado: Arc<EstadoOtimizacao>) {
+    let mut opcoes = MqttOptions::new(&config.client_id, &config.host, config.porta);
+    opcoes.set_keep_alive(Duration::from_secs(30));
+    if let (Some(usuario), Some(senha)) = (&config.usuario, &config.senha) {
+        opcoes.set_credentials(usuario, senha);
+    }
+
+    let (cliente, mut eventos) = AsyncClient::new(opcoes, 100);
+    loop {
+        match eventos.poll().await {
+            // A sessão é limpa a cada conexão, então as assinaturas são refeitas.
+            Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                tracing::info!("📡 MQTT conectado em {}:{}", config.host, config.porta);
+                for topico in [&config.topico_nivel, &config.topico_gps] {
+                    if let Err(e) = cliente.try_subscribe(topico.as_str(), QoS::AtLeastOnce) {
+                        tracing::error!("❌ MQTT: falha ao assinar '{}': {}", topico, e);
+                    }
+                }
+            }
+            Ok(Event::Incoming(Packet::Publish(mensagem))) => {
+                match processar_mensagem(&config, &conn, &estado, &mensagem.topic, &mensagem.payload) {
+                    Ok(true) => tracing::debug!("MQTT: mensagem gravada ({})", mensagem.topic),
+                    Ok(false) => tracing::debug!("MQTT: mensagem duplicada ignorada ({})", mensagem.topic),
+                    Err(e) => tracing::warn!("⚠️ MQTT: mensagem descartada em '{}': {}", mensagem.topic, e),
+                }
+            }
+            Ok(_) => {}
+            Err(e) => {
+                tracing::warn!(
+                    "⚠️ MQTT: conexão com {}:{} falhou ({}). Nova tentativa em {}s",
+                    config.host,
+                    config.porta,
+                    e,
+                    INTERVALO_RECONEXAO_SEG
+                );
+                tokio::time::sleep(Duration::from_secs(INTERVALO_RECONEXAO_SEG)).await;
+            }
+        }
+    }
+}
+
+/// Sobe o assinante em uma task própria, ao lado do servidor HTTP.
+/// As posições gravadas também são repassadas ao rastreamento em tempo real.
+pub fn iniciar(
+    config: ConfigMqtt,
+    conn: Arc<Mutex<Connection>>,
+    estado: Arc<EstadoOtimizacao>,
+) -> tokio::task::JoinHandle<()> {
+    println!(
+        "📡 Assinante MQTT: {}:{} (nível: '{}', GPS: '{}')",
+        config.host, config.porta, config.topico_nivel, config.topico_gps
+    );
+    tokio::spawn(executar(config, conn, estado))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn topico_casa_com_curinga_de_um_nivel() {
+        assert!(topico_corresponde("sensores/+/nivel", "sensores/s1/nivel"));
+        assert!(topico_corresponde("+/+/gps", "veiculos/caminhao_1/gps"));
+        assert!(!topico_corresponde("sensores/+/nivel", "sensores/s1/bateria"));
+        // `+` ocupa exatamente um nível.
+        assert!(!topico_corresponde("sensores/+/nivel", "sensores/nivel"));
+        assert!(!topico_corresponde("sensores/+/nivel", "sensores/a/b/nivel"));
+    }
+
+    #[test]
+    fn topico_casa_com_curinga_multinivel() {
+        assert!(topico_corresponde("sensores/#", "sensores/s1/nivel"));
+        assert!(topico_corresponde("sensores/#", "sensores/s1"));
+        // `#` no fim também casa com o nível pai.
+        assert!(topico_corresponde("sensores/#", "sensores"));
+        assert!(topico_corresponde("#", "veiculos/caminhao_1/gps"));
+        assert!(!topico_corresponde("sensores/#", "veiculos/caminhao_1/gps"));
+    }
+
+    #[test]
+    fn topico_com_quantidade_de_niveis_diferente_nao_casa() {
+        assert!(!topico_corresponde("veiculos/caminhao_1/gps", "veiculos/caminhao_1/gps/extra"));
+        assert!(!topico_corresponde("veiculos/caminhao_1/gps", "veiculos/caminhao_1"));
+        assert!(topico_corresponde("veiculos/caminhao_1/gps", "veiculos/caminhao_1/gps"));
+    }
+
+    #[test]
+    fn segmento_curinga_devolve_o_id_do_topico() {
+        assert_eq!(segmento_curinga("sensores/+/nivel", "sensores/s1/nivel"), Some("s1"));
+        assert_eq!(segmento_curinga("veiculos/+/gps", "veiculos/caminhao_1/gps"), Some("caminhao_1"));
+        assert_eq!(segmento_curinga("sensores/s1/nivel", "sensores/s1/nivel"), None);
+        assert_eq!(segmento_curinga("sensores/+/nivel", "sensores"), None);
+    }
+
+    #[test]
+    fn ler_payload_usa_o_id_do_topico_so_quando_falta_no_corpo() {
+        let leitura: LeituraSensor =
+            ler_payload(br#"{"nivel_percentual": 72.5}"#, "sensor_id", "sensores/+/nivel", "sensores/s1/nivel")
+                .unwrap();
+        assert_eq!(leitura.sensor_id, "s1");
+
+        let posicao: PosicaoVeiculo = ler_payload(
+            br#"{"veiculo_id": "caminhao_2", "latitude": -23.5, "longitude": -46.6}"#,
+            "veiculo_id",
+            "veiculos/+/gps",
+            "veiculos/caminhao_1/gps",
+        )
+        .unwrap();
+        assert_eq!(posicao.veiculo_id, "caminhao_2");
+
+        assert!(ler_payload::<LeituraSensor>(b"nao e json", "sensor_id", "sensores/+/nivel", "sensores/s1/nivel")
+            .is_err());
+    }
+}
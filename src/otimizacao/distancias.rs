@@ -0,0 +1,210 @@
+// src/otimizacao/distancias.rs
+use crate::otimizacao::models::{
+    ArestaDistancia, PedidoNovaDistancia, RelatorioConectividade, ResultadoImportacaoDistancias,
+};
+use crate::otimizacao::EstadoOtimizacao;
+use std::collections::HashSet;
+
+pub fn validar_distancia(dados: &PedidoNovaDistancia) -> Result<(), String> {
+    if dados.origem.trim().is_empty() || dados.destino.trim().is_empty() {
+        return Err("Origem e destino não podem ser vazios".to_string());
+    }
+    if dados.origem == dados.destino {
+        return Err(format!("Trecho de '{}' para ele mesmo não é permitido", dados.origem));
+    }
+    if !dados.custo.is_finite() || dados.custo < 0.0 {
+        return Err(format!(
+            "Custo inválido para {} -> {}: {}",
+            dados.origem, dados.destino, dados.custo
+        ));
+    }
+    Ok(())
+}
+
+/// Lista os trechos (ordenados), opcionalmente só os que saem ou chegam em `ponto`.
+pub fn listar_distancias(estado: &EstadoOtimizacao, ponto: Option<&str>) -> Vec<ArestaDistancia> {
+    let servico = estado.servico_distancia.lock().unwrap();
+    let mut arestas: Vec<ArestaDistancia> = servico
+        .matriz()
+        .iter()
+        .flat_map(|(origem, vizinhos)| {
+            vizinhos.iter().map(move |(destino, custo)| ArestaDistancia {
+                origem: origem.clone(),
+                destino: destino.clone(),
+                custo: *custo,
+            })
+        })
+        .filter(|a| ponto.is_none_or(|p| a.origem == p || a.destino == p))
+        .collect();
+    arestas.sort_by(|a, b| (&a.origem, &a.destino).cmp(&(&b.origem, &b.destino)));
+    arestas
+}
+
+pub fn remover_distancia(
+    estado: &EstadoOtimizacao,
+    origem: &str,
+    destino: &str,
+    simetrica: bool,
+) -> Result<usize, String> {
+    estado
+        .servico_distancia
+        .lock()
+        .unwrap()
+        .remover_distancia(origem, destino, simetrica)
+}
+
+pub fn remover_ponto_distancias(estado: &EstadoOtimizacao, ponto: &str) -> Result<usize, String> {
+    estado.servico_distancia.lock().unwrap().remover_ponto(ponto)
+}
+
+/// Importa trechos de um CSV `origem,destino,custo` (cabeçalho opcional).
+/// Se alguma linha for inválida nada é gravado; caso contrário, o arquivo
+/// é gravado uma única vez. Pontos que não estão em
+/// `pontos_conhecidos` nem na matriz atual geram apenas avisos.
+pub fn importar_distancias_csv(
+    estado: &EstadoOtimizacao,
+    conteudo: &str,
+    simetrica: bool,
+    pontos_conhecidos: &HashSet<String>,
+) -> ResultadoImportacaoDistancias {
+    let mut leitor = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .trim(csv::Trim::All)
+        .flexible(true)
+        .from_reader(conteudo.as_bytes());
+
+    let mut pedidos = Vec::new();
+    let mut erros = Vec::new();
+
+    for (indice, registro) in leitor.records().enumerate() {
+        let linha = indice + 1;
+        let registro = match registro {
+            Ok(r) => r,
+            Err(e) => {
+                erros.push(format!("Linha {}: {}", linha, e));
+                continue;
+            }
+        };
+        if registro.iter().all(|campo| campo.is_empty()) {
+            continue;
+        }
+        if linha == 1 && registro.get(0).is_some_and(|c| c.eq_ignore_ascii_case("origem")) {
+            continue;
+        }
+        if registro.len() != 3 {
+            erros.push(format!("Linha {}: esperado 3 colunas (origem,destino,custo), encontrado {}", linha, registro.len()));
+            continue;
+        }
+
+        let custo = match registro[2].parse::<f64>() {
+            Ok(c) => c,
+            Err(_) => {
+                erros.push(format!("Linha {}: custo '{}' não é um número", linha, &registro[2]));
+                continue;
+            }
+        };
+        let pedido = PedidoNovaDistancia {
+            origem: registro[0].to_string(),
+            destino: registro[1].to_string(),
+            custo,
+            simetrica,
+        };
+        match validar_distancia(&pedido) {
+            Ok(()) => pedidos.push(pedido),
+            Err(e) => erros.push(format!("Linha {}: {}", linha, e)),
+        }
+    }
+
+    let mut servico = estado.servico_distancia.lock().unwrap();
+
+    let pontos_matriz = servico.pontos();
+    let mut avisos = Vec::new();
+    let mut avisados: HashSet<&str> = HashSet::new();
+    for pedido in &pedidos {
+        for ponto in [&pedido.origem, &pedido.destino] {
+            if !pontos_conhecidos.contains(ponto)
+                && !pontos_matriz.contains(ponto)
+                && avisados.insert(ponto)
+            {
+                avisos.push(format!("Ponto desconhecido: '{}'", ponto));
+            }
+        }
+    }
+
+    if !erros.is_empty() {
+        return ResultadoImportacaoDistancias {
+            trechos_importados: 0,
+            erros,
+            avisos,
+        };
+    }
+
+    let trechos_importados = pedidos.len();
+    let trechos = pedidos
+        .into_iter()
+        .map(|p| (p.origem, p.destino, p.custo, p.simetrica))
+        .collect();
+    if let Err(e) = servico.adicionar_distancias(trechos) {
+        erros.push(e);
+        return ResultadoImportacaoDistancias {
+            trechos_importados: 0,
+            erros,
+            avisos,
+        };
+    }
+
+    ResultadoImportacaoDistancias {
+        trechos_importados,
+        erros,
+        avisos,
+    }
+}
+
+pub fn exportar_distancias_csv(estado: &EstadoOtimizacao) -> Result<String, String> {
+    let mut escritor = csv::Writer::from_writer(Vec::new());
+    escritor
+        .write_record(["origem", "destino", "custo"])
+        .map_err(|e| e.to_string())?;
+    for aresta in listar_distancias(estado, None) {
+        escritor
+            .write_record([aresta.origem, aresta.destino, aresta.custo.to_string()])
+            .map_err(|e| e.to_string())?;
+    }
+    let bytes = escritor.into_inner().map_err(|e| e.to_string())?;
+    String::from_utf8(bytes).map_err(|e| e.to_string())
+}
+
+pub fn exportar_distancias_json(estado: &EstadoOtimizacao) -> serde_json::Value {
+    let servico = estado.servico_distancia.lock().unwrap();
+    serde_json::to_value(servico.matriz()).unwrap_or_default()
+}
+
+/// Pontos que não conseguem voltar para a garagem (ou não são alcançados a
+/// partir dela), considerando o sentido dos trechos.
+pub fn relatorio_conectividade(estado: &EstadoOtimizacao, garagem_id: &str) -> RelatorioConectividade {
+    let servico = estado.servico_distancia.lock().unwrap();
+    let pontos = servico.pontos();
+    let chegam_na_garagem = servico.alcancaveis(garagem_id, true);
+    let saem_da_garagem = servico.alcancaveis(garagem_id, false);
+
+    let mut sem_caminho_ate_garagem: Vec<String> = pontos
+        .iter()
+        .filter(|p| !chegam_na_garagem.contains(*p))
+        .cloned()
+        .collect();
+    let mut inalcancaveis_a_partir_da_garagem: Vec<String> = pontos
+        .iter()
+        .filter(|p| !saem_da_garagem.contains(*p))
+        .cloned()
+        .collect();
+    sem_caminho_ate_garagem.sort();
+    inalcancaveis_a_partir_da_garagem.sort();
+
+    RelatorioConectividade {
+        garagem_id: garagem_id.to_string(),
+        garagem_existe: pontos.contains(garagem_id),
+        total_pontos: pontos.len(),
+        sem_caminho_ate_garagem,
+        inalcancaveis_a_partir_da_garagem,
+    }
+}
@@ -0,0 +1,343 @@
+// src/otimizacao/execucao.rs
+use crate::otimizacao::historico::{self, ler_status};
+use crate::otimizacao::models::{
+    CheckinParada, ComparativoVeiculo, EmissoesRotas, ExecucaoRota, MetricasDeCusto,
+    PedidoCheckin, RelatorioExecucao, StatusRota,
+};
+use crate::otimizacao::{arredondar_duas_casas, calcular_metricas_consumo, FATOR_CO2_DIESEL_KG_L};
+use chrono::{DateTime, NaiveDate, Utc};
+use rusqlite::{params, Connection};
+use std::collections::{HashMap, HashSet};
+
+fn diferenca_metricas(real: &MetricasDeCusto, planejado: &MetricasDeCusto) -> MetricasDeCusto {
+    MetricasDeCusto {
+        distancia_total_km: arredondar_duas_casas(real.distancia_total_km - planejado.distancia_total_km),
+        litros_consumidos: arredondar_duas_casas(real.litros_consumidos - planejado.litros_consumidos),
+        custo_financeiro_reais: arredondar_duas_casas(real.custo_financeiro_reais - planejado.custo_financeiro_reais),
+    }
+}
+
+/// Confere o ponto e a rota informados numa coleta avulsa. Com rota, o ponto
+/// precisa fazer parte dela; sem rota, precisa estar cadastrado.
+pub fn validar_vinculos_coleta(
+    conn: &Connection,
+    ponto_id: Option<&str>,
+    rota_id: Option<i64>,
+) -> Result<(), String> {
+    if let Some(rota_id) = rota_id {
+        let rota = historico::buscar_rota(conn, rota_id)
+            .map_err(|e| format!("Erro ao acessar banco de dados: {}", e))?
+            .ok_or_else(|| format!("Rota {} não encontrada", rota_id))?;
+
+        if !matches!(
+            rota.status,
+            StatusRota::Despachada | StatusRota::EmAndamento | StatusRota::Concluida
+        ) {
+            return Err(format!(
+                "Coleta não pode ser vinculada à rota {} com status '{}'",
+                rota_id,
+                rota.status.as_str()
+            ));
+        }
+        if let Some(ponto_id) = ponto_id {
+            if !rota.rota_escolhida.sequencia_pontos.iter().any(|p| p == ponto_id) {
+                return Err(format!("Ponto '{}' não faz parte da rota {}", ponto_id, rota_id));
+            }
+        }
+    } else if let Some(ponto_id) = ponto_id {
+        let cadastrado: bool = conn
+            .query_row(
+                "SELECT EXISTS(SELECT 1 FROM pontos_coleta WHERE id = ?1)",
+                [ponto_id],
+                |row| row.get(0),
+            )
+            .map_err(|e| format!("Erro ao acessar banco de dados: {}", e))?;
+        if !cadastrado {
+            return Err(format!("Ponto de coleta '{}' não encontrado", ponto_id));
+        }
+    }
+    Ok(())
+}
+
+pub fn listar_checkins(conn: &Connection, rota_id: i64) -> rusqlite::Result<Vec<CheckinParada>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, ponto_id, chegada_em, odometro_km, coletado_json, usuario_id, registrado_em
+         FROM rotas_checkins
+         WHERE rota_id = ?1
+         ORDER BY chegada_em, id",
+    )?;
+
+    let checkins = stmt
+        .query_map([rota_id], |row| {
+            let coletado_json: String = row.get(4)?;
+            let coletado_kg: HashMap<String, f32> = serde_json::from_str(&coletado_json).map_err(|e| {
+                rusqlite::Error::FromSqlConversionFailure(4, rusqlite::types::Type::Text, Box::new(e))
+            })?;
+            Ok(CheckinParada {
+                id: row.get(0)?,
+                ponto_id: row.get(1)?,
+                chegada_em: row.get(2)?,
+                odometro_km: row.get(3)?,
+                coletado_kg,
+                usuario_id: row.get(5)?,
+                registrado_em: row.get(6)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    Ok(checkins)
+}
+
+/// Registra a chegada em uma parada. O primeiro check-in de uma rota
+/// despachada a coloca automaticamente em andamento. Não abre transação:
+/// quem chama abre uma, para que status e check-in sejam gravados juntos.
+pub fn registrar_checkin(
+    conn: &Connection,
+    rota_id: i64,
+    pedido: &PedidoCheckin,
+    usuario_id: u32,
+) -> Result<CheckinParada, String> {
+    let rota = historico::buscar_rota(conn, rota_id)
+        .map_err(|e| format!("Erro ao acessar banco de dados: {}", e))?
+        .ok_or_else(|| format!("Rota {} não encontrada", rota_id))?;
+
+    if !matches!(rota.status, StatusRota::Despachada | StatusRota::EmAndamento) {
+        return Err(format!(
+            "Check-in não permitido para rota com status '{}'",
+            rota.status.as_str()
+        ));
+    }
+
+    if !rota.rota_escolhida.sequencia_pontos.contains(&pedido.ponto_id) {
+        return Err(format!(
+            "Ponto '{}' não faz parte da rota {}",
+            pedido.ponto_id, rota_id
+        ));
+    }
+
+    for (tipo, quantidade) in &pedido.coletado_kg {
+        if tipo.trim().is_empty() {
+            return Err("Tipo de resíduo não pode ser vazio".to_string());
+        }
+        if *quantidade <= 0.0 {
+            return Err(format!("Quantidade inválida para '{}': deve ser maior que 0", tipo));
+        }
+    }
+
+    let checkins = listar_checkins(conn, rota_id)
+        .map_err(|e| format!("Erro ao acessar banco de dados: {}", e))?;
+    if let Some(odometro) = pedido.odometro_km {
+        let ultimo = checkins
+            .iter()
+            .filter_map(|c| c.odometro_km)
+            .fold(f64::MIN, f64::max);
+        if odometro < 0.0 || odometro < ultimo {
+            return Err(format!(
+                "Odômetro inválido: {:.1} km é menor que a última leitura registrada",
+                odometro
+            ));
+        }
+    }
+
+    if rota.status == StatusRota::Despachada {
+        historico::transicionar_status(
+            conn,
+            rota_id,
+            StatusRota::EmAndamento,
+            usuario_id,
+            Some("Primeiro check-in registrado"),
+            None,
+        )?;
+    }
+
+    let agora = Utc::now().to_rfc3339();
+    let chegada_em = pedido
+        .chegada_em
+        .map(|c| c.to_rfc3339())
+        .unwrap_or_else(|| agora.clone());
+    let coletado_json = serde_json::to_string(&pedido.coletado_kg)
+        .map_err(|e| format!("Erro ao serializar coleta: {}", e))?;
+
+    conn.execute(
+        "INSERT INTO rotas_checkins
+            (rota_id, ponto_id, chegada_em, odometro_km, coletado_json, usuario_id, registrado_em)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![rota_id, pedido.ponto_id, chegada_em, pedido.odometro_km, coletado_json, usuario_id, agora],
+    )
+    .map_err(|e| format!("Erro ao registrar check-in: {}", e))?;
+
+    Ok(CheckinParada {
+        id: conn.last_insert_rowid(),
+        ponto_id: pedido.ponto_id.clone(),
+        chegada_em,
+        odometro_km: pedido.odometro_km,
+        coletado_kg: pedido.coletado_kg.clone(),
+        usuario_id,
+        registrado_em: agora,
+    })
+}
+
+pub fn execucao_rota(conn: &Connection, rota_id: i64) -> rusqlite::Result<Option<ExecucaoRota>> {
+    let rota = match historico::buscar_rota(conn, rota_id)? {
+        Some(rota) => rota,
+        None => return Ok(None),
+    };
+    let checkins = listar_checkins(conn, rota_id)?;
+
+    let garagem = &rota.pedido.garagem_id;
+    let paradas_planejadas = rota
+        .rota_escolhida
+        .sequencia_pontos
+        .iter()
+        .filter(|p| *p != garagem)
+        .collect::<HashSet<_>>()
+        .len();
+    let paradas_realizadas = checkins
+        .iter()
+        .map(|c| &c.ponto_id)
+        .filter(|p| *p != garagem)
+        .collect::<HashSet<_>>()
+        .len();
+    let total_coletado_kg = checkins
+        .iter()
+        .flat_map(|c| c.coletado_kg.values())
+        .map(|q| *q as f64)
+        .sum::<f64>();
+
+    let leituras: Vec<f64> = checkins.iter().filter_map(|c| c.odometro_km).collect();
+    let real = if leituras.len() >= 2 {
+        let inicio = leituras.iter().cloned().fold(f64::MAX, f64::min);
+        let fim = leituras.iter().cloned().fold(f64::MIN, f64::max);
+        Some(calcular_metricas_consumo(fim - inicio))
+    } else {
+        None
+    };
+
+    let planejado = rota.rota_escolhida.metricas.clone();
+    let diferenca = real.as_ref().map(|r| diferenca_metricas(r, &planejado));
+
+    Ok(Some(ExecucaoRota {
+        rota_id,
+        veiculo_id: rota.veiculo_id,
+        status: rota.status,
+        planejado,
+        real,
+        diferenca,
+        paradas_planejadas,
+        paradas_realizadas,
+        total_coletado_kg: arredondar_duas_casas(total_coletado_kg),
+        checkins,
+    }))
+}
+
+/// Planejado x real para todas as rotas com check-in, agregado também por veículo.
+pub fn relatorio_execucao(
+    conn: &Connection,
+    status: Option<StatusRota>,
+) -> rusqlite::Result<RelatorioExecucao> {
+    let mut stmt = conn.prepare(
+        "SELECT DISTINCT r.id, r.status
+         FROM rotas_otimizadas r
+         JOIN rotas_checkins c ON c.rota_id = r.id
+         ORDER BY r.id",
+    )?;
+    let ids = stmt
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, ler_status(row, 1)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut rotas = Vec::new();
+    for (id, status_rota) in ids {
+        if status.is_some_and(|s| s != status_rota) {
+            continue;
+        }
+        if let Some(mut execucao) = execucao_rota(conn, id)? {
+            execucao.checkins.clear();
+            rotas.push(execucao);
+        }
+    }
+
+    // veiculo -> (rotas, km planejado, km real)
+    let mut por_veiculo: HashMap<String, (usize, f64, f64)> = HashMap::new();
+    for execucao in &rotas {
+        if let Some(real) = &execucao.real {
+            let veiculo = execucao
+                .veiculo_id
+                .clone()
+                .unwrap_or_else(|| "sem_veiculo".to_string());
+            let acumulado = por_veiculo.entry(veiculo).or_insert((0, 0.0, 0.0));
+            acumulado.0 += 1;
+            acumulado.1 += execucao.planejado.distancia_total_km;
+            acumulado.2 += real.distancia_total_km;
+        }
+    }
+
+    let mut veiculos: Vec<ComparativoVeiculo> = por_veiculo
+        .into_iter()
+        .map(|(veiculo_id, (rotas_comparadas, km_planejado, km_real))| {
+            let planejado = calcular_metricas_consumo(km_planejado);
+            let real = calcular_metricas_consumo(km_real);
+            let diferenca = diferenca_metricas(&real, &planejado);
+            ComparativoVeiculo {
+                veiculo_id,
+                rotas_comparadas,
+                planejado,
+                real,
+                diferenca,
+            }
+        })
+        .collect();
+    veiculos.sort_by(|a, b| a.veiculo_id.cmp(&b.veiculo_id));
+
+    Ok(RelatorioExecucao { rotas, veiculos })
+}
+
+/// Emissões dos caminhões nas rotas concluídas criadas entre `de` e `ate`
+/// (inclusivos). Cada rota entra com a distância do odômetro quando tem ao
+/// menos duas leituras; sem elas, com a distância planejada.
+pub fn emissoes_rotas(
+    conn: &Connection,
+    de: Option<NaiveDate>,
+    ate: Option<NaiveDate>,
+) -> rusqlite::Result<EmissoesRotas> {
+    let mut stmt = conn.prepare(
+        "SELECT id, criada_em
+         FROM rotas_otimizadas
+         WHERE status = ?1
+         ORDER BY id",
+    )?;
+    let concluidas = stmt
+        .query_map([StatusRota::Concluida.as_str()], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut rotas_concluidas = 0;
+    let mut rotas_com_odometro = 0;
+    let mut distancia_km = 0.0;
+    for (id, criada_em) in concluidas {
+        let Ok(criada_em) = DateTime::parse_from_rfc3339(&criada_em) else { continue };
+        let data = criada_em.with_timezone(&Utc).date_naive();
+        if de.is_some_and(|de| data < de) || ate.is_some_and(|ate| data > ate) {
+            continue;
+        }
+        let Some(execucao) = execucao_rota(conn, id)? else { continue };
+        rotas_concluidas += 1;
+        match &execucao.real {
+            Some(real) => {
+                rotas_com_odometro += 1;
+                distancia_km += real.distancia_total_km;
+            }
+            None => distancia_km += execucao.planejado.distancia_total_km,
+        }
+    }
+
+    let consumo = calcular_metricas_consumo(distancia_km);
+    Ok(EmissoesRotas {
+        rotas_concluidas,
+        rotas_com_odometro,
+        distancia_km: consumo.distancia_total_km,
+        litros_diesel: consumo.litros_consumidos,
+        fator_co2_diesel_kg_por_litro: FATOR_CO2_DIESEL_KG_L,
+        co2_emitido_kg: arredondar_duas_casas(consumo.litros_consumidos * FATOR_CO2_DIESEL_KG_L),
+    })
+}
@@ -0,0 +1,151 @@
+// src/otimizacao/exportacao.rs
+use crate::otimizacao::models::{EstrategiaRota, ParadaPlanejada, RotaArmazenada};
+use serde_json::{json, Value};
+
+/// Paradas já calculadas para uma estratégia da rota.
+pub struct TrajetoExportado<'a> {
+    pub estrategia: EstrategiaRota,
+    pub rota: &'a RotaArmazenada,
+    pub paradas: Vec<ParadaPlanejada>,
+}
+
+impl TrajetoExportado<'_> {
+    fn escolhida(&self) -> bool {
+        self.rota.estrategia_escolhida == self.estrategia
+    }
+}
+
+/// FeatureCollection com um trajeto por estratégia e um Point por parada.
+/// Coordenadas seguem a ordem GeoJSON: [longitude, latitude].
+pub fn gerar_geojson(trajetos: &[TrajetoExportado]) -> Value {
+    let mut features = Vec::new();
+    let mut sem_coordenadas: Vec<String> = Vec::new();
+
+    for trajeto in trajetos {
+        let detalhada = trajeto.rota.comparacao.rota(trajeto.estrategia);
+        let coordenadas: Vec<Value> = trajeto
+            .paradas
+            .iter()
+            .filter_map(|p| Some(json!([p.longitude?, p.latitude?])))
+            .collect();
+        // LineString exige ao menos duas posições; com uma só o trajeto é um
+        // ponto, e sem nenhuma a feature fica sem geometria
+        let geometria = match coordenadas.len() {
+            0 => Value::Null,
+            1 => json!({ "type": "Point", "coordinates": coordenadas[0] }),
+            _ => json!({ "type": "LineString", "coordinates": coordenadas }),
+        };
+
+        features.push(json!({
+            "type": "Feature",
+            "geometry": geometria,
+            "properties": {
+                "rota_id": trajeto.rota.id,
+                "estrategia": trajeto.estrategia.as_str(),
+                "tipo_otimizacao": detalhada.tipo_otimizacao,
+                "escolhida": trajeto.escolhida(),
+                "distancia_total_km": detalhada.metricas.distancia_total_km,
+            }
+        }));
+
+        for parada in &trajeto.paradas {
+            let (Some(lat), Some(lon)) = (parada.latitude, parada.longitude) else {
+                if !sem_coordenadas.contains(&parada.ponto_id) {
+                    sem_coordenadas.push(parada.ponto_id.clone());
+                }
+                continue;
+            };
+            features.push(json!({
+                "type": "Feature",
+                "geometry": { "type": "Point", "coordinates": [lon, lat] },
+                "properties": {
+                    "rota_id": trajeto.rota.id,
+                    "estrategia": trajeto.estrategia.as_str(),
+                    "ordem": parada.ordem,
+                    "ponto_id": parada.ponto_id,
+                    "nome": parada.nome,
+                    "carga_esperada_kg": parada.carga_esperada_kg,
+                    "distancia_acumulada_km": parada.distancia_acumulada_km,
+                    "eta": parada.eta,
+                }
+            }));
+        }
+    }
+
+    json!({
+        "type": "FeatureCollection",
+        "features": features,
+        "pontos_sem_coordenadas": sem_coordenadas,
+    })
+}
+
+fn escapar_xml(texto: &str) -> String {
+    texto
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// GPX 1.1 com um `<wpt>` por ponto e um `<trk>` por estratégia (ETA em `<time>`).
+pub fn gerar_gpx(trajetos: &[TrajetoExportado]) -> String {
+    let mut gpx = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <gpx version=\"1.1\" creator=\"sistema_permissoes\" xmlns=\"http://www.topografix.com/GPX/1/1\">\n",
+    );
+
+    let mut pontos_emitidos: Vec<&str> = Vec::new();
+    for trajeto in trajetos {
+        for parada in &trajeto.paradas {
+            let (Some(lat), Some(lon)) = (parada.latitude, parada.longitude) else { continue };
+            if pontos_emitidos.contains(&parada.ponto_id.as_str()) {
+                continue;
+            }
+            pontos_emitidos.push(&parada.ponto_id);
+
+            gpx.push_str(&format!("  <wpt lat=\"{}\" lon=\"{}\">\n", lat, lon));
+            gpx.push_str(&format!("    <name>{}</name>\n", escapar_xml(&parada.ponto_id)));
+            if let Some(nome) = &parada.nome {
+                gpx.push_str(&format!("    <cmt>{}</cmt>\n", escapar_xml(nome)));
+            }
+            if let Some(carga) = parada.carga_esperada_kg {
+                gpx.push_str(&format!("    <desc>Carga esperada: {:.2} kg</desc>\n", carga));
+            }
+            gpx.push_str("  </wpt>\n");
+        }
+    }
+
+    for trajeto in trajetos {
+        let detalhada = trajeto.rota.comparacao.rota(trajeto.estrategia);
+        gpx.push_str("  <trk>\n");
+        gpx.push_str(&format!(
+            "    <name>Rota {} - {}</name>\n",
+            trajeto.rota.id,
+            escapar_xml(&detalhada.tipo_otimizacao)
+        ));
+        gpx.push_str(&format!(
+            "    <desc>{} km{}</desc>\n",
+            detalhada.metricas.distancia_total_km,
+            if trajeto.escolhida() { " (escolhida)" } else { "" }
+        ));
+        gpx.push_str(&format!("    <type>{}</type>\n", trajeto.estrategia.as_str()));
+        gpx.push_str("    <trkseg>\n");
+        for parada in &trajeto.paradas {
+            let (Some(lat), Some(lon)) = (parada.latitude, parada.longitude) else { continue };
+            gpx.push_str(&format!("      <trkpt lat=\"{}\" lon=\"{}\">\n", lat, lon));
+            gpx.push_str(&format!("        <time>{}</time>\n", parada.eta));
+            gpx.push_str(&format!(
+                "        <name>{}. {}</name>\n",
+                parada.ordem,
+                escapar_xml(&parada.ponto_id)
+            ));
+            gpx.push_str("      </trkpt>\n");
+        }
+        gpx.push_str("    </trkseg>\n");
+        gpx.push_str("  </trk>\n");
+    }
+
+    gpx.push_str("</gpx>\n");
+    gpx
+}
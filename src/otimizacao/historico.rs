@@ -0,0 +1,260 @@
+// src/otimizacao/historico.rs
+use crate::otimizacao::models::{
+    ComparacaoOtimizacao, EstrategiaRota, EventoStatusRota, MetricasDeCusto, PedidoOtimizacao,
+    ResumoRota, RotaArmazenada, StatusRota,
+};
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+
+fn erro_conversao<E>(coluna: usize, e: E) -> rusqlite::Error
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    rusqlite::Error::FromSqlConversionFailure(coluna, rusqlite::types::Type::Text, Box::new(e))
+}
+
+pub(crate) fn ler_status(row: &Row, coluna: usize) -> rusqlite::Result<StatusRota> {
+    let texto: String = row.get(coluna)?;
+    StatusRota::try_from(texto.as_str()).map_err(|_| {
+        rusqlite::Error::InvalidColumnType(coluna, "status".to_string(), rusqlite::types::Type::Text)
+    })
+}
+
+fn ler_estrategia(row: &Row, coluna: usize) -> rusqlite::Result<EstrategiaRota> {
+    let texto: String = row.get(coluna)?;
+    EstrategiaRota::try_from(texto.as_str()).map_err(|_| {
+        rusqlite::Error::InvalidColumnType(coluna, "estrategia".to_string(), rusqlite::types::Type::Text)
+    })
+}
+
+pub(crate) fn registrar_evento(
+    conn: &Connection,
+    rota_id: i64,
+    anterior: Option<StatusRota>,
+    novo: StatusRota,
+    usuario_id: u32,
+    observacao: Option<&str>,
+    quando: &str,
+) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT INTO rotas_historico_status
+            (rota_id, status_anterior, status_novo, usuario_id, observacao, registrado_em)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![rota_id, anterior.map(|s| s.as_str()), novo.as_str(), usuario_id, observacao, quando],
+    )?;
+    Ok(())
+}
+
+/// Persiste uma execução de otimização com status inicial `planejada`. A rota
+/// e o primeiro evento do histórico são gravados na mesma transação.
+pub fn registrar_rota(
+    conn: &Connection,
+    usuario_id: u32,
+    pedido: &PedidoOtimizacao,
+    comparacao: &ComparacaoOtimizacao,
+    estrategia: EstrategiaRota,
+) -> rusqlite::Result<i64> {
+    let pedido_json = serde_json::to_string(pedido).map_err(|e| erro_conversao(2, e))?;
+    let comparacao_json = serde_json::to_string(comparacao).map_err(|e| erro_conversao(3, e))?;
+    let metricas = &comparacao.rota(estrategia).metricas;
+    let agora = Utc::now().to_rfc3339();
+
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "INSERT INTO rotas_otimizadas
+            (usuario_id, pedido_json, comparacao_json, estrategia_escolhida,
+             distancia_planejada_km, litros_planejados, custo_planejado_reais,
+             status, criada_em, atualizada_em)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?9)",
+        params![
+            usuario_id,
+            pedido_json,
+            comparacao_json,
+            estrategia.as_str(),
+            metricas.distancia_total_km,
+            metricas.litros_consumidos,
+            metricas.custo_financeiro_reais,
+            StatusRota::Planejada.as_str(),
+            agora,
+        ],
+    )?;
+    let id = tx.last_insert_rowid();
+    registrar_evento(&tx, id, None, StatusRota::Planejada, usuario_id, None, &agora)?;
+    tx.commit()?;
+    Ok(id)
+}
+
+pub fn listar_rotas(
+    conn: &Connection,
+    status: Option<StatusRota>,
+    limit: usize,
+    offset: usize,
+) -> rusqlite::Result<Vec<ResumoRota>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, usuario_id, pedido_json, estrategia_escolhida, status,
+                distancia_planejada_km, litros_planejados, custo_planejado_reais,
+                criada_em, atualizada_em, veiculo_id
+         FROM rotas_otimizadas
+         WHERE ?1 IS NULL OR status = ?1
+         ORDER BY id DESC
+         LIMIT ?2 OFFSET ?3",
+    )?;
+
+    let rotas = stmt
+        .query_map(
+            params![status.map(|s| s.as_str()), limit as i64, offset as i64],
+            |row| {
+                let pedido_json: String = row.get(2)?;
+                let pedido: PedidoOtimizacao =
+                    serde_json::from_str(&pedido_json).map_err(|e| erro_conversao(2, e))?;
+                Ok(ResumoRota {
+                    id: row.get(0)?,
+                    usuario_id: row.get(1)?,
+                    garagem_id: pedido.garagem_id,
+                    estrategia_escolhida: ler_estrategia(row, 3)?,
+                    status: ler_status(row, 4)?,
+                    veiculo_id: row.get(10)?,
+                    metricas_planejadas: MetricasDeCusto {
+                        distancia_total_km: row.get(5)?,
+                        litros_consumidos: row.get(6)?,
+                        custo_financeiro_reais: row.get(7)?,
+                    },
+                    criada_em: row.get(8)?,
+                    atualizada_em: row.get(9)?,
+                })
+            },
+        )?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    Ok(rotas)
+}
+
+fn listar_eventos(conn: &Connection, rota_id: i64) -> rusqlite::Result<Vec<EventoStatusRota>> {
+    let mut stmt = conn.prepare(
+        "SELECT status_anterior, status_novo, usuario_id, observacao, registrado_em
+         FROM rotas_historico_status
+         WHERE rota_id = ?1
+         ORDER BY id",
+    )?;
+
+    let eventos = stmt
+        .query_map([rota_id], |row| {
+            let anterior: Option<String> = row.get(0)?;
+            let status_anterior = match anterior {
+                Some(texto) => Some(StatusRota::try_from(texto.as_str()).map_err(|_| {
+                    rusqlite::Error::InvalidColumnType(0, "status_anterior".to_string(), rusqlite::types::Type::Text)
+                })?),
+                None => None,
+            };
+            Ok(EventoStatusRota {
+                status_anterior,
+                status_novo: ler_status(row, 1)?,
+                usuario_id: row.get(2)?,
+                observacao: row.get(3)?,
+                registrado_em: row.get(4)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    Ok(eventos)
+}
+
+pub fn buscar_rota(conn: &Connection, id: i64) -> rusqlite::Result<Option<RotaArmazenada>> {
+    let rota = conn
+        .query_row(
+            "SELECT id, usuario_id, pedido_json, comparacao_json, estrategia_escolhida,
+                    status, criada_em, atualizada_em, veiculo_id
+             FROM rotas_otimizadas
+             WHERE id = ?1",
+            [id],
+            |row| {
+                let pedido_json: String = row.get(2)?;
+                let comparacao_json: String = row.get(3)?;
+                let pedido: PedidoOtimizacao =
+                    serde_json::from_str(&pedido_json).map_err(|e| erro_conversao(2, e))?;
+                let mut comparacao: ComparacaoOtimizacao =
+                    serde_json::from_str(&comparacao_json).map_err(|e| erro_conversao(3, e))?;
+                let estrategia = ler_estrategia(row, 4)?;
+                comparacao.rota_id = Some(id);
+
+                Ok(RotaArmazenada {
+                    id: row.get(0)?,
+                    usuario_id: row.get(1)?,
+                    pedido,
+                    estrategia_escolhida: estrategia,
+                    rota_escolhida: comparacao.rota(estrategia).clone(),
+                    comparacao,
+                    status: ler_status(row, 5)?,
+                    veiculo_id: row.get(8)?,
+                    criada_em: row.get(6)?,
+                    atualizada_em: row.get(7)?,
+                    historico: Vec::new(),
+                })
+            },
+        )
+        .optional()?;
+
+    match rota {
+        Some(mut rota) => {
+            rota.historico = listar_eventos(conn, id)?;
+            Ok(Some(rota))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Aplica uma transição de status respeitando o ciclo de vida da rota.
+/// O veículo, quando informado, fica associado à rota (normalmente no despacho).
+pub fn atualizar_status_rota(
+    conn: &Connection,
+    id: i64,
+    novo: StatusRota,
+    usuario_id: u32,
+    observacao: Option<&str>,
+    veiculo_id: Option<&str>,
+) -> Result<RotaArmazenada, String> {
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("Erro ao acessar banco de dados: {}", e))?;
+    transicionar_status(&tx, id, novo, usuario_id, observacao, veiculo_id)?;
+    tx.commit().map_err(|e| format!("Erro ao atualizar status: {}", e))?;
+
+    buscar_rota(conn, id)
+        .map_err(|e| format!("Erro ao acessar banco de dados: {}", e))?
+        .ok_or_else(|| format!("Rota {} não encontrada", id))
+}
+
+/// Grava a transição e o evento do histórico sem abrir transação; quem
+/// chama precisa estar dentro de uma.
+pub(crate) fn transicionar_status(
+    conn: &Connection,
+    id: i64,
+    novo: StatusRota,
+    usuario_id: u32,
+    observacao: Option<&str>,
+    veiculo_id: Option<&str>,
+) -> Result<(), String> {
+    let atual = conn
+        .query_row("SELECT status FROM rotas_otimizadas WHERE id = ?1", [id], |row| ler_status(row, 0))
+        .optional()
+        .map_err(|e| format!("Erro ao acessar banco de dados: {}", e))?
+        .ok_or_else(|| format!("Rota {} não encontrada", id))?;
+
+    if !atual.pode_transicionar_para(novo) {
+        return Err(format!(
+            "Transição inválida: {} -> {}",
+            atual.as_str(),
+            novo.as_str()
+        ));
+    }
+
+    let agora = Utc::now().to_rfc3339();
+    conn.execute(
+        "UPDATE rotas_otimizadas
+         SET status = ?1, atualizada_em = ?2, veiculo_id = COALESCE(?3, veiculo_id)
+         WHERE id = ?4",
+        params![novo.as_str(), agora, veiculo_id, id],
+    )
+    .and_then(|_| registrar_evento(conn, id, Some(atual), novo, usuario_id, observacao, &agora))
+    .map_err(|e| format!("Erro ao atualizar status: {}", e))
+}
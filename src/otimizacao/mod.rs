@@ -1,36 +1,103 @@
+mod distancias;
+mod execucao;
+mod exportacao;
+mod historico;
 mod models;
+mod pontos;
+mod prioridade;
+mod rastreamento;
+mod sensores;
 mod services;
+mod telemetria;
 
 use services::{ServicoDistancia, ServicoDemanda};
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
 
 pub use models::{
     PedidoOtimizacao, ComparacaoOtimizacao, DadosPrevisao, PedidoNovaDistancia,
-    RotaDetalhada, MetricasDeCusto, BenchmarkInfo
+    RotaDetalhada, MetricasDeCusto, BenchmarkInfo, EstrategiaRota, StatusRota,
+    EventoStatusRota, ResumoRota, RotaArmazenada, PedidoCheckin, CheckinParada,
+    ExecucaoRota, ComparativoVeiculo, RelatorioExecucao, EmissoesRotas, PontoColeta, ParadaPlanejada,
+    ArestaDistancia, ResultadoImportacaoDistancias, RelatorioConectividade,
+    RegistroPrevisao, DemandaRegiao, PesosPrioridade, PerfilPesos, ContextoPrioridade,
+    ComponentePontuacao, ComponentesPontuacao, JustificativaParada, Sensor, LeituraSensor,
+    ResultadoIngestaoLeituras, EstadoEnchimento, SelecaoAutomatica, PosicaoVeiculo,
+    EstadoRastreamento, EventoRastreamento
 };
+pub(crate) use models::padrao_simetrica;
+pub use historico::{registrar_rota, listar_rotas, buscar_rota, atualizar_status_rota};
+pub use execucao::{
+    registrar_checkin, execucao_rota, relatorio_execucao, emissoes_rotas, validar_vinculos_coleta
+};
+pub use exportacao::{gerar_geojson, gerar_gpx, TrajetoExportado};
+pub use pontos::{salvar_ponto, listar_pontos, mapa_pontos};
+pub use prioridade::{
+    salvar_perfil_pesos, listar_perfis_pesos, buscar_perfil_pesos, montar_contexto_prioridade
+};
+pub use sensores::{
+    salvar_sensor, listar_sensores, registrar_leitura, registrar_leituras, importar_leituras_csv, estado_enchimento,
+    aplicar_selecao_automatica
+};
+pub use distancias::{
+    validar_distancia, listar_distancias, remover_distancia, remover_ponto_distancias,
+    importar_distancias_csv, exportar_distancias_csv, exportar_distancias_json,
+    relatorio_conectividade
+};
+pub use telemetria::{registrar_posicao, ultimas_posicoes};
+pub use rastreamento::estado_rastreamento;
 use models::ResultadoRotaInterna; 
 
 
 const CONSUMO_CAMINHAO_KML: f64 = 2.0;
 const PRECO_DIESEL_REAIS: f64 = 6.0;
+/// kg de CO₂ emitidos por litro de diesel queimado.
+const FATOR_CO2_DIESEL_KG_L: f64 = 2.68;
+const VELOCIDADE_MEDIA_KMH: f64 = 30.0;
+const TEMPO_PARADA_MIN: i64 = 5;
+/// Tolerância para relógios adiantados de sensores e rastreadores.
+const TOLERANCIA_FUTURO_MIN: i64 = 5;
+/// Eventos de rastreamento guardados para inscritos mais lentos.
+const CAPACIDADE_EVENTOS: usize = 256;
 
 #[derive(Clone)]
 pub struct EstadoOtimizacao {
     servico_distancia: Arc<Mutex<ServicoDistancia>>,
     servico_demanda: Arc<Mutex<ServicoDemanda>>,
+    eventos: broadcast::Sender<EventoRastreamento>,
 }
 
 impl EstadoOtimizacao {
-    pub fn new() -> Self {
-        let servico_distancia = ServicoDistancia::new("data/distancias.json"); // Caminho corrigido
-        let servico_demanda = ServicoDemanda::new();
+    /// Falha se `data/distancias.json` estiver corrompido, exceto em
+    /// `modo_degradado` (matriz do backup, somente leitura).
+    pub fn new(modo_degradado: bool) -> Result<Self, String> {
+        let servico_distancia = ServicoDistancia::new("data/distancias.json", modo_degradado)?; // Caminho corrigido
+        let servico_demanda = ServicoDemanda::new("data/previsoes_demanda.jsonl")?;
 
-        Self {
+        Ok(Self {
             servico_distancia: Arc::new(Mutex::new(servico_distancia)),
             servico_demanda: Arc::new(Mutex::new(servico_demanda)),
-        }
+            eventos: broadcast::channel(CAPACIDADE_EVENTOS).0,
+        })
+    }
+
+    /// Repassa o evento a quem estiver acompanhando a rota. Sem inscritos,
+    /// o evento é descartado.
+    pub fn publicar_evento(&self, evento: EventoRastreamento) {
+        let _ = self.eventos.send(evento);
     }
-} 
+
+    pub fn assinar_eventos(&self) -> broadcast::Receiver<EventoRastreamento> {
+        self.eventos.subscribe()
+    }
+
+    pub fn distancias_em_modo_degradado(&self) -> bool {
+        self.servico_distancia.lock().unwrap().modo_degradado()
+    }
+}
+
 fn arredondar_duas_casas(num: f64) -> f64 {
     (num * 100.0).round() / 100.0
 }
@@ -49,6 +116,7 @@ fn calcular_metricas_consumo(distancia_km: f64) -> MetricasDeCusto {
 pub fn executar_otimizacao_comparativa(
     estado: &EstadoOtimizacao,
     pedido: &PedidoOtimizacao,
+    contexto: &ContextoPrioridade,
 ) -> ComparacaoOtimizacao {
     
     println!("Iniciando cálculo de otimização comparativa...");
@@ -57,21 +125,25 @@ pub fn executar_otimizacao_comparativa(
     let servico_dem = estado.servico_demanda.lock().unwrap();
 
     let rota_gulosa_interna: ResultadoRotaInterna =
-        services::otimizar_rota_vizinho_proximo(pedido, &*servico_dist);
+        services::otimizar_rota_vizinho_proximo(pedido, &servico_dist);
 
     let rota_inteligente_interna: ResultadoRotaInterna =
-        services::otimizar_rota_por_prioridade(pedido, &*servico_dist, &*servico_dem);
+        services::otimizar_rota_por_prioridade(pedido, &servico_dist, &servico_dem, contexto);
 
     let rota_gulosa_detalhada = RotaDetalhada {
         tipo_otimizacao: "Gulosa (Menor Custo)".to_string(),
         sequencia_pontos: rota_gulosa_interna.sequencia_pontos,
         metricas: calcular_metricas_consumo(rota_gulosa_interna.distancia_total_km),
+        pesos: None,
+        detalhamento: rota_gulosa_interna.detalhamento,
     };
 
     let rota_inteligente_detalhada = RotaDetalhada {
-        tipo_otimizacao: "Prioridade (Demanda Urgente)".to_string(),
+        tipo_otimizacao: "Prioridade (Multicritério)".to_string(),
         sequencia_pontos: rota_inteligente_interna.sequencia_pontos,
         metricas: calcular_metricas_consumo(rota_inteligente_interna.distancia_total_km),
+        pesos: Some(contexto.pesos),
+        detalhamento: rota_inteligente_interna.detalhamento,
     };
 
     let comparacao = ComparacaoOtimizacao {
@@ -81,6 +153,8 @@ pub fn executar_otimizacao_comparativa(
             consumo_medio_kml: CONSUMO_CAMINHAO_KML,
             preco_diesel_reais_litro: PRECO_DIESEL_REAIS,
         },
+        rota_id: None,
+        pontos_selecionados_automaticamente: Vec::new(),
     };
 
     println!("Cálculo concluído.");
@@ -88,16 +162,66 @@ pub fn executar_otimizacao_comparativa(
 }
 
 
-pub fn alimentar_previsao(estado: &EstadoOtimizacao, dados: DadosPrevisao) {
+/// Detalha uma sequência de pontos com coordenadas, distância acumulada,
+/// carga esperada (previsão de demanda) e ETA a partir de `inicio`.
+pub fn planejar_paradas(
+    estado: &EstadoOtimizacao,
+    sequencia: &[String],
+    pontos: &HashMap<String, PontoColeta>,
+    inicio: DateTime<Utc>,
+) -> Vec<ParadaPlanejada> {
+    let servico_dist = estado.servico_distancia.lock().unwrap();
+    let servico_dem = estado.servico_demanda.lock().unwrap();
+
+    let mut paradas = Vec::with_capacity(sequencia.len());
+    let mut distancia_acumulada = 0.0;
+
+    for (ordem, ponto_id) in sequencia.iter().enumerate() {
+        if ordem > 0 {
+            distancia_acumulada += servico_dist
+                .get_custo(&sequencia[ordem - 1], ponto_id)
+                .unwrap_or(0.0);
+        }
+        let minutos_viagem = distancia_acumulada / VELOCIDADE_MEDIA_KMH * 60.0;
+        let eta = inicio
+            + Duration::minutes(minutos_viagem.round() as i64)
+            + Duration::minutes(TEMPO_PARADA_MIN * ordem as i64);
+        let ponto = pontos.get(ponto_id);
+
+        paradas.push(ParadaPlanejada {
+            ordem,
+            ponto_id: ponto_id.clone(),
+            nome: ponto.and_then(|p| p.nome.clone()),
+            latitude: ponto.map(|p| p.latitude),
+            longitude: ponto.map(|p| p.longitude),
+            distancia_acumulada_km: arredondar_duas_casas(distancia_acumulada),
+            carga_esperada_kg: servico_dem.get_demanda_ponto(ponto_id),
+            eta: eta.to_rfc3339_opts(SecondsFormat::Secs, true),
+        });
+    }
+
+    paradas
+}
+
+pub fn alimentar_previsao(estado: &EstadoOtimizacao, dados: DadosPrevisao) -> Result<RegistroPrevisao, String> {
     println!("Atualizando previsão: {:?}", dados);
-    estado.servico_demanda.lock().unwrap().atualizar_previsao(dados);
+    estado.servico_demanda.lock().unwrap().atualizar_previsao(dados)
+}
+
+pub fn historico_previsoes(estado: &EstadoOtimizacao, ponto_id: &str) -> Vec<RegistroPrevisao> {
+    estado.servico_demanda.lock().unwrap().historico_ponto(ponto_id)
+}
+
+pub fn demanda_por_regiao(estado: &EstadoOtimizacao, regiao: Option<&str>) -> Vec<DemandaRegiao> {
+    estado.servico_demanda.lock().unwrap().agregado_por_regiao(regiao)
 }
 
-pub fn alimentar_distancia(estado: &EstadoOtimizacao, dados: PedidoNovaDistancia) {
+pub fn alimentar_distancia(estado: &EstadoOtimizacao, dados: PedidoNovaDistancia) -> Result<(), String> {
     println!("Adicionando nova distância: {:?}", dados);
     estado.servico_distancia.lock().unwrap().adicionar_distancia(
         dados.origem,
         dados.destino,
-        dados.custo
-    );
+        dados.custo,
+        dados.simetrica
+    )
 }
\ No newline at end of file
@@ -1,33 +1,428 @@
 // src/otimizacao/models.rs
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 // --- Structs de Resposta (Públicas) ---
 
-#[derive(Serialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct MetricasDeCusto {
     pub distancia_total_km: f64,
     pub litros_consumidos: f64,
     pub custo_financeiro_reais: f64,
 }
 
-#[derive(Serialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct RotaDetalhada {
     pub tipo_otimizacao: String,
     pub sequencia_pontos: Vec<String>,
     pub metricas: MetricasDeCusto,
+    /// Pesos efetivamente usados (apenas na rota por prioridade).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pesos: Option<PesosPrioridade>,
+    /// Por que cada parada foi escolhida (apenas na rota por prioridade).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub detalhamento: Vec<JustificativaParada>,
 }
 
-#[derive(Serialize, Debug)]
+/// Um critério da pontuação: valor bruto, valor normalizado (0 a 1, maior é
+/// melhor), peso e quanto ele somou na pontuação final.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ComponentePontuacao {
+    pub valor: Option<f64>,
+    pub normalizado: f64,
+    pub peso: f64,
+    pub contribuicao: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ComponentesPontuacao {
+    /// Valor em km.
+    pub distancia: ComponentePontuacao,
+    /// Previsão vigente em kg.
+    pub demanda: ComponentePontuacao,
+    /// Risco informado, de 0 a 1.
+    pub risco_transbordo: ComponentePontuacao,
+    /// Horas desde o último check-in (`null` = nunca visitado).
+    pub tempo_desde_ultima_visita: ComponentePontuacao,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct JustificativaParada {
+    pub ordem: usize,
+    pub ponto_id: String,
+    pub pontuacao: f64,
+    pub componentes: ComponentesPontuacao,
+    pub candidatos_avaliados: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub segundo_colocado: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pontuacao_segundo_colocado: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
 pub struct BenchmarkInfo {
     pub consumo_medio_kml: f64,
     pub preco_diesel_reais_litro: f64,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct ComparacaoOtimizacao {
     pub rota_gulosa: RotaDetalhada,
     pub rota_prioridade: RotaDetalhada,
     pub benchmark_usado: BenchmarkInfo,
+    /// Id da execução registrada no histórico (preenchido pela API).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rota_id: Option<i64>,
+    /// Pontos incluídos pela seleção automática por nível de enchimento.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pontos_selecionados_automaticamente: Vec<String>,
+}
+
+impl ComparacaoOtimizacao {
+    pub fn rota(&self, estrategia: EstrategiaRota) -> &RotaDetalhada {
+        match estrategia {
+            EstrategiaRota::Gulosa => &self.rota_gulosa,
+            EstrategiaRota::Prioridade => &self.rota_prioridade,
+        }
+    }
+}
+
+// --- Histórico de Rotas (Públicas) ---
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum EstrategiaRota {
+    Gulosa,
+    #[default]
+    Prioridade,
+}
+
+impl EstrategiaRota {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EstrategiaRota::Gulosa => "gulosa",
+            EstrategiaRota::Prioridade => "prioridade",
+        }
+    }
+}
+
+impl TryFrom<&str> for EstrategiaRota {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "gulosa" => Ok(EstrategiaRota::Gulosa),
+            "prioridade" => Ok(EstrategiaRota::Prioridade),
+            _ => Err(format!("Estratégia desconhecida: '{}'", value)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum StatusRota {
+    Planejada,
+    Despachada,
+    EmAndamento,
+    Concluida,
+    Cancelada,
+}
+
+impl StatusRota {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StatusRota::Planejada => "planejada",
+            StatusRota::Despachada => "despachada",
+            StatusRota::EmAndamento => "em_andamento",
+            StatusRota::Concluida => "concluida",
+            StatusRota::Cancelada => "cancelada",
+        }
+    }
+
+    /// Ciclo de vida: planejada -> despachada -> em_andamento -> concluida.
+    /// Qualquer estado não final pode ser cancelado.
+    pub fn pode_transicionar_para(&self, novo: StatusRota) -> bool {
+        matches!(
+            (self, novo),
+            (StatusRota::Planejada, StatusRota::Despachada)
+                | (StatusRota::Despachada, StatusRota::EmAndamento)
+                | (StatusRota::EmAndamento, StatusRota::Concluida)
+                | (StatusRota::Planejada | StatusRota::Despachada | StatusRota::EmAndamento, StatusRota::Cancelada)
+        )
+    }
+}
+
+impl TryFrom<&str> for StatusRota {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "planejada" => Ok(StatusRota::Planejada),
+            "despachada" => Ok(StatusRota::Despachada),
+            "em_andamento" => Ok(StatusRota::EmAndamento),
+            "concluida" => Ok(StatusRota::Concluida),
+            "cancelada" => Ok(StatusRota::Cancelada),
+            _ => Err(format!("Status de rota desconhecido: '{}'", value)),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct EventoStatusRota {
+    pub status_anterior: Option<StatusRota>,
+    pub status_novo: StatusRota,
+    pub usuario_id: u32,
+    pub observacao: Option<String>,
+    pub registrado_em: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ResumoRota {
+    pub id: i64,
+    pub usuario_id: u32,
+    pub garagem_id: String,
+    pub estrategia_escolhida: EstrategiaRota,
+    pub status: StatusRota,
+    pub veiculo_id: Option<String>,
+    pub metricas_planejadas: MetricasDeCusto,
+    pub criada_em: String,
+    pub atualizada_em: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct RotaArmazenada {
+    pub id: i64,
+    pub usuario_id: u32,
+    pub pedido: PedidoOtimizacao,
+    pub estrategia_escolhida: EstrategiaRota,
+    pub rota_escolhida: RotaDetalhada,
+    pub comparacao: ComparacaoOtimizacao,
+    pub status: StatusRota,
+    pub veiculo_id: Option<String>,
+    pub criada_em: String,
+    pub atualizada_em: String,
+    pub historico: Vec<EventoStatusRota>,
+}
+
+// --- Execução Real de Rotas (Públicas) ---
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct PedidoCheckin {
+    pub ponto_id: String,
+    /// Padrão: momento do registro.
+    pub chegada_em: Option<DateTime<Utc>>,
+    pub odometro_km: Option<f64>,
+    /// Quantidade coletada na parada, em kg, por tipo de resíduo.
+    #[serde(default)]
+    pub coletado_kg: HashMap<String, f32>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct CheckinParada {
+    pub id: i64,
+    pub ponto_id: String,
+    pub chegada_em: String,
+    pub odometro_km: Option<f64>,
+    pub coletado_kg: HashMap<String, f32>,
+    pub usuario_id: u32,
+    pub registrado_em: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ExecucaoRota {
+    pub rota_id: i64,
+    pub veiculo_id: Option<String>,
+    pub status: StatusRota,
+    pub planejado: MetricasDeCusto,
+    /// Calculado pela diferença de odômetro; exige ao menos duas leituras.
+    pub real: Option<MetricasDeCusto>,
+    /// `real - planejado`.
+    pub diferenca: Option<MetricasDeCusto>,
+    pub paradas_planejadas: usize,
+    pub paradas_realizadas: usize,
+    pub total_coletado_kg: f64,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub checkins: Vec<CheckinParada>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ComparativoVeiculo {
+    pub veiculo_id: String,
+    pub rotas_comparadas: usize,
+    pub planejado: MetricasDeCusto,
+    pub real: MetricasDeCusto,
+    pub diferenca: MetricasDeCusto,
+}
+
+#[derive(Serialize, Debug)]
+pub struct RelatorioExecucao {
+    pub rotas: Vec<ExecucaoRota>,
+    pub veiculos: Vec<ComparativoVeiculo>,
+}
+
+/// Diesel queimado pelos caminhões nas rotas concluídas de um período.
+#[derive(Serialize, Debug, Clone)]
+pub struct EmissoesRotas {
+    pub rotas_concluidas: usize,
+    /// Rotas com distância medida pelo odômetro; as demais entram com a
+    /// distância planejada.
+    pub rotas_com_odometro: usize,
+    pub distancia_km: f64,
+    pub litros_diesel: f64,
+    pub fator_co2_diesel_kg_por_litro: f64,
+    pub co2_emitido_kg: f64,
+}
+
+// --- Pontos de Coleta e Exportação (Públicas) ---
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct PontoColeta {
+    pub id: String,
+    pub nome: Option<String>,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub regiao: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ParadaPlanejada {
+    pub ordem: usize,
+    pub ponto_id: String,
+    pub nome: Option<String>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub distancia_acumulada_km: f64,
+    pub carga_esperada_kg: Option<f64>,
+    pub eta: String,
+}
+
+// --- Gestão da Matriz de Distâncias (Públicas) ---
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ArestaDistancia {
+    pub origem: String,
+    pub destino: String,
+    pub custo: f64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ResultadoImportacaoDistancias {
+    pub trechos_importados: usize,
+    pub erros: Vec<String>,
+    pub avisos: Vec<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct RelatorioConectividade {
+    pub garagem_id: String,
+    pub garagem_existe: bool,
+    pub total_pontos: usize,
+    pub sem_caminho_ate_garagem: Vec<String>,
+    pub inalcancaveis_a_partir_da_garagem: Vec<String>,
+}
+
+// --- Sensores de Nível de Enchimento (Públicas) ---
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Sensor {
+    pub id: String,
+    pub ponto_id: String,
+    #[serde(default)]
+    pub descricao: Option<String>,
+    #[serde(default = "padrao_ativo")]
+    pub ativo: bool,
+    #[serde(default)]
+    pub cadastrado_em: String,
+}
+
+fn padrao_ativo() -> bool {
+    true
+}
+
+/// Leitura enviada por um sensor. Sem `ponto_id`, vale o ponto do cadastro;
+/// sem `medido_em`, vale o horário de recebimento.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LeituraSensor {
+    pub sensor_id: String,
+    #[serde(default)]
+    pub ponto_id: Option<String>,
+    pub nivel_percentual: f64,
+    #[serde(default)]
+    pub bateria_percentual: Option<f64>,
+    #[serde(default)]
+    pub medido_em: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct ResultadoIngestaoLeituras {
+    pub aceitas: usize,
+    /// Leituras já recebidas (mesmo sensor e mesmo `medido_em`), ignoradas.
+    pub duplicadas: usize,
+    pub erros: Vec<String>,
+}
+
+/// Última leitura conhecida de um ponto e a taxa de enchimento desde o
+/// último esvaziamento.
+#[derive(Serialize, Debug, Clone)]
+pub struct EstadoEnchimento {
+    pub ponto_id: String,
+    pub sensor_id: String,
+    pub nivel_percentual: f64,
+    pub bateria_percentual: Option<f64>,
+    pub bateria_baixa: bool,
+    pub medido_em: String,
+    pub taxa_enchimento_pct_hora: Option<f64>,
+}
+
+// --- Telemetria de Veículos (Públicas) ---
+
+/// Posição GPS de um caminhão. Sem `medido_em`, vale o horário de recebimento.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PosicaoVeiculo {
+    pub veiculo_id: String,
+    #[serde(default)]
+    pub rota_id: Option<i64>,
+    pub latitude: f64,
+    pub longitude: f64,
+    #[serde(default)]
+    pub velocidade_kmh: Option<f64>,
+    #[serde(default)]
+    pub medido_em: Option<DateTime<Utc>>,
+}
+
+// --- Rastreamento em Tempo Real (Públicas) ---
+
+#[derive(Serialize, Debug, Clone)]
+pub struct EstadoRastreamento {
+    pub rota_id: i64,
+    pub status: StatusRota,
+    pub veiculo_id: Option<String>,
+    pub sequencia_pontos: Vec<String>,
+    pub checkins: Vec<CheckinParada>,
+    pub posicao: Option<PosicaoVeiculo>,
+    pub historico: Vec<EventoStatusRota>,
+}
+
+/// Mensagens enviadas aos inscritos de uma rota (campo `tipo` no JSON).
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "tipo", rename_all = "snake_case")]
+pub enum EventoRastreamento {
+    EstadoInicial(EstadoRastreamento),
+    Posicao { rota_id: i64, posicao: PosicaoVeiculo },
+    Checkin { rota_id: i64, checkin: CheckinParada },
+    Status { rota_id: i64, veiculo_id: Option<String>, evento: EventoStatusRota },
+}
+
+impl EventoRastreamento {
+    pub fn rota_id(&self) -> i64 {
+        match self {
+            EventoRastreamento::EstadoInicial(estado) => estado.rota_id,
+            EventoRastreamento::Posicao { rota_id, .. }
+            | EventoRastreamento::Checkin { rota_id, .. }
+            | EventoRastreamento::Status { rota_id, .. } => *rota_id,
+        }
+    }
 }
 
 // --- Struct Interna (Privada para o módulo) ---
@@ -35,14 +430,105 @@ pub struct ComparacaoOtimizacao {
 pub(crate) struct ResultadoRotaInterna {
     pub sequencia_pontos: Vec<String>,
     pub distancia_total_km: f64,
+    pub detalhamento: Vec<JustificativaParada>,
+}
+
+/// Dados usados pela rota por prioridade além da matriz e das previsões.
+#[derive(Debug, Clone, Default)]
+pub struct ContextoPrioridade {
+    pub pesos: PesosPrioridade,
+    pub ultima_visita: HashMap<String, DateTime<Utc>>,
+    pub risco_transbordo: HashMap<String, f64>,
 }
 
 // --- Structs de Pedido (Públicas) ---
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct PedidoOtimizacao {
     pub garagem_id: String,
     pub pontos_a_visitar: Vec<String>,
+    /// Pesos explícitos; têm precedência sobre `perfil_pesos`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pesos: Option<PesosPrioridade>,
+    /// Nome de um perfil salvo em `/otimizacao/perfis-pesos`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub perfil_pesos: Option<String>,
+    /// Risco de transbordo por ponto, de 0 a 1. Ausente = nível do sensor
+    /// do ponto (se houver) ou 0.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub risco_transbordo: HashMap<String, f64>,
+    /// Inclui na rota os pontos cheios (ou que vão encher) segundo os sensores.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub selecao_automatica: Option<SelecaoAutomatica>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy)]
+pub struct SelecaoAutomatica {
+    /// Nível (%) a partir do qual o ponto entra na rota.
+    pub limite_percentual: f64,
+    /// Horas até a próxima rodada: também entram os pontos que, pela taxa
+    /// de enchimento, devem atingir o limite nesse intervalo.
+    #[serde(default)]
+    pub horizonte_horas: f64,
+}
+
+/// Pesos dos critérios da rota por prioridade. Não precisam somar 1: a
+/// pontuação é dividida pela soma dos pesos. Critério omitido vale 0.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct PesosPrioridade {
+    #[serde(default)]
+    pub distancia: f64,
+    #[serde(default)]
+    pub demanda: f64,
+    #[serde(default)]
+    pub risco_transbordo: f64,
+    #[serde(default)]
+    pub tempo_desde_ultima_visita: f64,
+}
+
+impl Default for PesosPrioridade {
+    fn default() -> Self {
+        Self {
+            distancia: 0.4,
+            demanda: 0.3,
+            risco_transbordo: 0.2,
+            tempo_desde_ultima_visita: 0.1,
+        }
+    }
+}
+
+impl PesosPrioridade {
+    pub fn soma(&self) -> f64 {
+        self.distancia + self.demanda + self.risco_transbordo + self.tempo_desde_ultima_visita
+    }
+
+    pub fn validar(&self) -> Result<(), String> {
+        let pesos = [
+            ("distancia", self.distancia),
+            ("demanda", self.demanda),
+            ("risco_transbordo", self.risco_transbordo),
+            ("tempo_desde_ultima_visita", self.tempo_desde_ultima_visita),
+        ];
+        for (nome, peso) in pesos {
+            if !peso.is_finite() || peso < 0.0 {
+                return Err(format!("Peso inválido para '{}': {}", nome, peso));
+            }
+        }
+        if self.soma() <= 0.0 {
+            return Err("Pelo menos um peso deve ser maior que 0".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct PerfilPesos {
+    pub nome: String,
+    #[serde(default)]
+    pub descricao: Option<String>,
+    pub pesos: PesosPrioridade,
+    #[serde(default)]
+    pub atualizado_em: String,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -50,6 +536,13 @@ pub struct PedidoNovaDistancia {
    pub origem: String,
    pub destino: String,
    pub custo: f64,
+   /// `false` para vias de mão única: só o trecho origem -> destino é gravado.
+   #[serde(default = "padrao_simetrica")]
+   pub simetrica: bool,
+}
+
+pub(crate) fn padrao_simetrica() -> bool {
+    true
 }
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -57,4 +550,26 @@ pub struct DadosPrevisao {
     pub ponto_id: String,
     pub regiao: String,
     pub previsao_demanda: f64,
+    /// Padrão: momento do registro.
+    pub valida_de: Option<DateTime<Utc>>,
+    /// Padrão: 7 dias após `valida_de`.
+    pub valida_ate: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RegistroPrevisao {
+    pub ponto_id: String,
+    pub regiao: String,
+    pub previsao_demanda: f64,
+    pub valida_de: DateTime<Utc>,
+    pub valida_ate: DateTime<Utc>,
+    pub registrada_em: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DemandaRegiao {
+    pub regiao: String,
+    pub pontos_com_previsao: usize,
+    pub demanda_total: f64,
+    pub demanda_media: f64,
 }
\ No newline at end of file
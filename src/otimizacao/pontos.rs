@@ -0,0 +1,60 @@
+// src/otimizacao/pontos.rs
+use crate::otimizacao::models::PontoColeta;
+use rusqlite::{params, Connection};
+use std::collections::HashMap;
+
+/// Cadastra ou atualiza um ponto de coleta (chave: `id`).
+pub fn salvar_ponto(conn: &Connection, ponto: &PontoColeta) -> Result<(), String> {
+    if ponto.id.trim().is_empty() {
+        return Err("Id do ponto não pode ser vazio".to_string());
+    }
+    if !(-90.0..=90.0).contains(&ponto.latitude) || !(-180.0..=180.0).contains(&ponto.longitude) {
+        return Err(format!(
+            "Coordenadas inválidas para '{}': ({}, {})",
+            ponto.id, ponto.latitude, ponto.longitude
+        ));
+    }
+
+    conn.execute(
+        "INSERT INTO pontos_coleta (id, nome, latitude, longitude, regiao)
+         VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT(id) DO UPDATE SET
+            nome = excluded.nome,
+            latitude = excluded.latitude,
+            longitude = excluded.longitude,
+            regiao = excluded.regiao",
+        params![ponto.id, ponto.nome, ponto.latitude, ponto.longitude, ponto.regiao],
+    )
+    .map_err(|e| format!("Erro ao salvar ponto: {}", e))?;
+    Ok(())
+}
+
+pub fn listar_pontos(conn: &Connection, regiao: Option<&str>) -> rusqlite::Result<Vec<PontoColeta>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, nome, latitude, longitude, regiao
+         FROM pontos_coleta
+         WHERE ?1 IS NULL OR regiao = ?1
+         ORDER BY id",
+    )?;
+
+    let pontos = stmt
+        .query_map([regiao], |row| {
+            Ok(PontoColeta {
+                id: row.get(0)?,
+                nome: row.get(1)?,
+                latitude: row.get(2)?,
+                longitude: row.get(3)?,
+                regiao: row.get(4)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    Ok(pontos)
+}
+
+pub fn mapa_pontos(conn: &Connection) -> rusqlite::Result<HashMap<String, PontoColeta>> {
+    Ok(listar_pontos(conn, None)?
+        .into_iter()
+        .map(|p| (p.id.clone(), p))
+        .collect())
+}
@@ -0,0 +1,153 @@
+// src/otimizacao/prioridade.rs
+use crate::otimizacao::models::{ContextoPrioridade, PedidoOtimizacao, PerfilPesos, PesosPrioridade};
+use crate::otimizacao::sensores;
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use std::collections::HashMap;
+
+fn ler_perfil(row: &Row) -> rusqlite::Result<PerfilPesos> {
+    Ok(PerfilPesos {
+        nome: row.get(0)?,
+        descricao: row.get(1)?,
+        pesos: PesosPrioridade {
+            distancia: row.get(2)?,
+            demanda: row.get(3)?,
+            risco_transbordo: row.get(4)?,
+            tempo_desde_ultima_visita: row.get(5)?,
+        },
+        atualizado_em: row.get(6)?,
+    })
+}
+
+/// Cadastra ou substitui um perfil de pesos (chave: `nome`).
+pub fn salvar_perfil_pesos(conn: &Connection, perfil: &PerfilPesos) -> Result<PerfilPesos, String> {
+    let nome = perfil.nome.trim();
+    if nome.is_empty() {
+        return Err("Nome do perfil não pode ser vazio".to_string());
+    }
+    perfil.pesos.validar()?;
+
+    let agora = Utc::now().to_rfc3339();
+    conn.execute(
+        "INSERT INTO perfis_pesos
+            (nome, descricao, peso_distancia, peso_demanda, peso_risco_transbordo,
+             peso_tempo_desde_ultima_visita, atualizado_em)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+         ON CONFLICT(nome) DO UPDATE SET
+            descricao = excluded.descricao,
+            peso_distancia = excluded.peso_distancia,
+            peso_demanda = excluded.peso_demanda,
+            peso_risco_transbordo = excluded.peso_risco_transbordo,
+            peso_tempo_desde_ultima_visita = excluded.peso_tempo_desde_ultima_visita,
+            atualizado_em = excluded.atualizado_em",
+        params![
+            nome,
+            perfil.descricao,
+            perfil.pesos.distancia,
+            perfil.pesos.demanda,
+            perfil.pesos.risco_transbordo,
+            perfil.pesos.tempo_desde_ultima_visita,
+            agora,
+        ],
+    )
+    .map_err(|e| format!("Erro ao salvar perfil: {}", e))?;
+
+    Ok(PerfilPesos {
+        nome: nome.to_string(),
+        descricao: perfil.descricao.clone(),
+        pesos: perfil.pesos,
+        atualizado_em: agora,
+    })
+}
+
+pub fn listar_perfis_pesos(conn: &Connection) -> rusqlite::Result<Vec<PerfilPesos>> {
+    let mut stmt = conn.prepare(
+        "SELECT nome, descricao, peso_distancia, peso_demanda, peso_risco_transbordo,
+                peso_tempo_desde_ultima_visita, atualizado_em
+         FROM perfis_pesos
+         ORDER BY nome",
+    )?;
+    let perfis = stmt
+        .query_map([], ler_perfil)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(perfis)
+}
+
+pub fn buscar_perfil_pesos(conn: &Connection, nome: &str) -> rusqlite::Result<Option<PerfilPesos>> {
+    conn.query_row(
+        "SELECT nome, descricao, peso_distancia, peso_demanda, peso_risco_transbordo,
+                peso_tempo_desde_ultima_visita, atualizado_em
+         FROM perfis_pesos
+         WHERE nome = ?1",
+        [nome],
+        ler_perfil,
+    )
+    .optional()
+}
+
+/// Último check-in registrado em cada ponto, considerando todas as rotas.
+pub fn ultimas_visitas(conn: &Connection) -> rusqlite::Result<HashMap<String, DateTime<Utc>>> {
+    let mut stmt = conn.prepare(
+        "SELECT ponto_id, MAX(chegada_em) FROM rotas_checkins GROUP BY ponto_id",
+    )?;
+    let visitas = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    Ok(visitas
+        .into_iter()
+        .filter_map(|(ponto, chegada)| {
+            let chegada = DateTime::parse_from_rfc3339(&chegada).ok()?;
+            Some((ponto, chegada.with_timezone(&Utc)))
+        })
+        .collect())
+}
+
+/// Resolve os pesos do pedido (explícitos > perfil salvo > padrão) e reúne
+/// os dados de risco e de última visita usados na pontuação. Pontos sem
+/// risco informado usam o nível do último sensor (0–100% vira 0–1).
+pub fn montar_contexto_prioridade(
+    conn: &Connection,
+    pedido: &PedidoOtimizacao,
+) -> Result<ContextoPrioridade, String> {
+    let pesos = match (&pedido.pesos, &pedido.perfil_pesos) {
+        (Some(pesos), _) => *pesos,
+        (None, Some(nome)) => {
+            buscar_perfil_pesos(conn, nome)
+                .map_err(|e| format!("Erro ao acessar banco de dados: {}", e))?
+                .ok_or_else(|| format!("Perfil de pesos '{}' não encontrado", nome))?
+                .pesos
+        }
+        (None, None) => PesosPrioridade::default(),
+    };
+    pesos.validar()?;
+
+    for (ponto, risco) in &pedido.risco_transbordo {
+        if !(0.0..=1.0).contains(risco) {
+            return Err(format!(
+                "Risco de transbordo inválido para '{}': {} (esperado entre 0 e 1)",
+                ponto, risco
+            ));
+        }
+    }
+
+    let ultima_visita = ultimas_visitas(conn)
+        .map_err(|e| format!("Erro ao acessar banco de dados: {}", e))?;
+
+    let mut risco_transbordo = pedido.risco_transbordo.clone();
+    let enchimento = sensores::mapa_enchimento(conn)
+        .map_err(|e| format!("Erro ao acessar banco de dados: {}", e))?;
+    for ponto in &pedido.pontos_a_visitar {
+        if let Some(estado) = enchimento.get(ponto) {
+            risco_transbordo
+                .entry(ponto.clone())
+                .or_insert((estado.nivel_percentual / 100.0).clamp(0.0, 1.0));
+        }
+    }
+
+    Ok(ContextoPrioridade {
+        pesos,
+        ultima_visita,
+        risco_transbordo,
+    })
+}
@@ -0,0 +1,29 @@
+// src/otimizacao/rastreamento.rs
+use crate::otimizacao::execucao::listar_checkins;
+use crate::otimizacao::historico;
+use crate::otimizacao::models::EstadoRastreamento;
+use crate::otimizacao::telemetria::ultimas_posicoes;
+use rusqlite::Connection;
+
+/// Estado atual de uma rota para quem acabou de se inscrever: status,
+/// check-ins já feitos e a última posição conhecida do caminhão.
+pub fn estado_rastreamento(conn: &Connection, rota_id: i64) -> rusqlite::Result<Option<EstadoRastreamento>> {
+    let rota = match historico::buscar_rota(conn, rota_id)? {
+        Some(rota) => rota,
+        None => return Ok(None),
+    };
+    let checkins = listar_checkins(conn, rota_id)?;
+    let posicao = ultimas_posicoes(conn, Some(rota_id))?
+        .into_iter()
+        .max_by_key(|p| p.medido_em);
+
+    Ok(Some(EstadoRastreamento {
+        rota_id,
+        status: rota.status,
+        veiculo_id: rota.veiculo_id,
+        sequencia_pontos: rota.rota_escolhida.sequencia_pontos,
+        checkins,
+        posicao,
+        historico: rota.historico,
+    }))
+}
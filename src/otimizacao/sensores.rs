@@ -0,0 +1,377 @@
+// src/otimizacao/sensores.rs
+use crate::otimizacao::{arredondar_duas_casas, TOLERANCIA_FUTURO_MIN};
+use crate::otimizacao::models::{
+    EstadoEnchimento, LeituraSensor, PedidoOtimizacao, ResultadoIngestaoLeituras, SelecaoAutomatica,
+    Sensor,
+};
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashMap;
+
+const BATERIA_BAIXA_PERCENTUAL: f64 = 20.0;
+/// Leituras mais antigas que isso (em relação à última) não entram na taxa.
+const JANELA_TAXA_HORAS: i64 = 72;
+
+fn erro_banco(e: rusqlite::Error) -> String {
+    format!("Erro ao acessar banco de dados: {}", e)
+}
+
+/// Cadastra ou atualiza um sensor (chave: `id`). A data de cadastro original
+/// é mantida na atualização.
+pub fn salvar_sensor(conn: &Connection, sensor: &Sensor) -> Result<Sensor, String> {
+    if sensor.id.trim().is_empty() || sensor.ponto_id.trim().is_empty() {
+        return Err("Id do sensor e ponto não podem ser vazios".to_string());
+    }
+    let ponto_cadastrado: bool = conn
+        .query_row(
+            "SELECT EXISTS(SELECT 1 FROM pontos_coleta WHERE id = ?1)",
+            [&sensor.ponto_id],
+            |row| row.get(0),
+        )
+        .map_err(erro_banco)?;
+    if !ponto_cadastrado {
+        return Err(format!("Ponto de coleta '{}' não encontrado", sensor.ponto_id));
+    }
+
+    let agora = Utc::now().to_rfc3339();
+    conn.execute(
+        "INSERT INTO sensores (id, ponto_id, descricao, ativo, cadastrado_em)
+         VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT(id) DO UPDATE SET
+            ponto_id = excluded.ponto_id,
+            descricao = excluded.descricao,
+            ativo = excluded.ativo",
+        params![sensor.id, sensor.ponto_id, sensor.descricao, sensor.ativo, agora],
+    )
+    .map_err(|e| format!("Erro ao salvar sensor: {}", e))?;
+
+    buscar_sensor(conn, &sensor.id)
+        .map_err(erro_banco)?
+        .ok_or_else(|| format!("Sensor '{}' não encontrado", sensor.id))
+}
+
+fn buscar_sensor(conn: &Connection, id: &str) -> rusqlite::Result<Option<Sensor>> {
+    conn.query_row(
+        "SELECT id, ponto_id, descricao, ativo, cadastrado_em FROM sensores WHERE id = ?1",
+        [id],
+        |row| {
+            Ok(Sensor {
+                id: row.get(0)?,
+                ponto_id: row.get(1)?,
+                descricao: row.get(2)?,
+                ativo: row.get(3)?,
+                cadastrado_em: row.get(4)?,
+            })
+        },
+    )
+    .optional()
+}
+
+pub fn listar_sensores(conn: &Connection) -> rusqlite::Result<Vec<Sensor>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, ponto_id, descricao, ativo, cadastrado_em FROM sensores ORDER BY id",
+    )?;
+    let sensores = stmt
+        .query_map([], |row| {
+            Ok(Sensor {
+                id: row.get(0)?,
+                ponto_id: row.get(1)?,
+                descricao: row.get(2)?,
+                ativo: row.get(3)?,
+                cadastrado_em: row.get(4)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(sensores)
+}
+
+/// Valida e grava uma leitura. Retorna `false` se ela já havia sido
+/// recebida (mesmo sensor e mesmo `medido_em`).
+pub fn registrar_leitura(conn: &Connection, leitura: &LeituraSensor) -> Result<bool, String> {
+    let sensor = buscar_sensor(conn, &leitura.sensor_id)
+        .map_err(erro_banco)?
+        .ok_or_else(|| format!("Sensor '{}' não cadastrado", leitura.sensor_id))?;
+    if !sensor.ativo {
+        return Err(format!("Sensor '{}' está inativo", sensor.id));
+    }
+
+    if let Some(ponto) = &leitura.ponto_id {
+        if *ponto != sensor.ponto_id {
+            return Err(format!(
+                "Sensor '{}' está cadastrado no ponto '{}', não em '{}'",
+                sensor.id, sensor.ponto_id, ponto
+            ));
+        }
+    }
+    if !(0.0..=100.0).contains(&leitura.nivel_percentual) {
+        return Err(format!(
+            "Nível inválido: {} (esperado entre 0 e 100)",
+            leitura.nivel_percentual
+        ));
+    }
+    if let Some(bateria) = leitura.bateria_percentual {
+        if !(0.0..=100.0).contains(&bateria) {
+            return Err(format!("Bateria inválida: {} (esperado entre 0 e 100)", bateria));
+        }
+    }
+
+    let agora = Utc::now();
+    let medido_em = leitura.medido_em.unwrap_or(agora);
+    if medido_em > agora + Duration::minutes(TOLERANCIA_FUTURO_MIN) {
+        return Err(format!("Leitura no futuro: {}", medido_em.to_rfc3339()));
+    }
+
+    let inseridas = conn
+        .execute(
+            "INSERT OR IGNORE INTO leituras_sensores
+                (sensor_id, ponto_id, nivel_percentual, bateria_percentual, medido_em, recebido_em)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                sensor.id,
+                sensor.ponto_id,
+                leitura.nivel_percentual,
+                leitura.bateria_percentual,
+                medido_em.to_rfc3339_opts(SecondsFormat::Secs, true),
+                agora.to_rfc3339(),
+            ],
+        )
+        .map_err(|e| format!("Erro ao registrar leitura: {}", e))?;
+
+    Ok(inseridas > 0)
+}
+
+/// Grava cada leitura de forma independente: uma leitura inválida não
+/// impede as demais.
+pub fn registrar_leituras(conn: &Connection, leituras: &[LeituraSensor]) -> ResultadoIngestaoLeituras {
+    let mut resultado = ResultadoIngestaoLeituras::default();
+    for (indice, leitura) in leituras.iter().enumerate() {
+        match registrar_leitura(conn, leitura) {
+            Ok(true) => resultado.aceitas += 1,
+            Ok(false) => resultado.duplicadas += 1,
+            Err(e) => resultado.erros.push(format!("Leitura {}: {}", indice + 1, e)),
+        }
+    }
+    resultado
+}
+
+fn campo_opcional(campo: Option<&str>) -> Option<&str> {
+    campo.filter(|c| !c.is_empty())
+}
+
+/// Importa leituras de um CSV
+/// `sensor_id,ponto_id,nivel_percentual,bateria_percentual,medido_em`
+/// (cabeçalho opcional; só `sensor_id` e `nivel_percentual` são obrigatórios).
+pub fn importar_leituras_csv(conn: &Connection, conteudo: &str) -> ResultadoIngestaoLeituras {
+    let mut leitor = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .trim(csv::Trim::All)
+        .flexible(true)
+        .from_reader(conteudo.as_bytes());
+
+    let mut resultado = ResultadoIngestaoLeituras::default();
+    for (indice, registro) in leitor.records().enumerate() {
+        let linha = indice + 1;
+        let registro = match registro {
+            Ok(r) => r,
+            Err(e) => {
+                resultado.erros.push(format!("Linha {}: {}", linha, e));
+                continue;
+            }
+        };
+        if registro.iter().all(|campo| campo.is_empty()) {
+            continue;
+        }
+        if linha == 1 && registro.get(0).is_some_and(|c| c.eq_ignore_ascii_case("sensor_id")) {
+            continue;
+        }
+        if registro.len() < 3 || registro.len() > 5 {
+            resultado.erros.push(format!(
+                "Linha {}: esperado 3 a 5 colunas (sensor_id,ponto_id,nivel_percentual,bateria_percentual,medido_em), encontrado {}",
+                linha,
+                registro.len()
+            ));
+            continue;
+        }
+
+        let nivel_percentual = match registro[2].parse::<f64>() {
+            Ok(n) => n,
+            Err(_) => {
+                resultado.erros.push(format!("Linha {}: nível '{}' não é um número", linha, &registro[2]));
+                continue;
+            }
+        };
+        let bateria_percentual = match campo_opcional(registro.get(3)).map(|b| b.parse::<f64>()) {
+            Some(Ok(b)) => Some(b),
+            Some(Err(_)) => {
+                resultado.erros.push(format!("Linha {}: bateria '{}' não é um número", linha, &registro[3]));
+                continue;
+            }
+            None => None,
+        };
+        let medido_em = match campo_opcional(registro.get(4)).map(DateTime::parse_from_rfc3339) {
+            Some(Ok(m)) => Some(m.with_timezone(&Utc)),
+            Some(Err(_)) => {
+                resultado.erros.push(format!(
+                    "Linha {}: data '{}' inválida (use RFC 3339)",
+                    linha, &registro[4]
+                ));
+                continue;
+            }
+            None => None,
+        };
+
+        let leitura = LeituraSensor {
+            sensor_id: registro[0].to_string(),
+            ponto_id: campo_opcional(registro.get(1)).map(|p| p.to_string()),
+            nivel_percentual,
+            bateria_percentual,
+            medido_em,
+        };
+        match registrar_leitura(conn, &leitura) {
+            Ok(true) => resultado.aceitas += 1,
+            Ok(false) => resultado.duplicadas += 1,
+            Err(e) => resultado.erros.push(format!("Linha {}: {}", linha, e)),
+        }
+    }
+    resultado
+}
+
+/// Taxa (%/h) no trecho final sem quedas de nível: uma queda indica que o
+/// contêiner foi esvaziado e reinicia o cálculo.
+fn taxa_enchimento(leituras: &[(DateTime<Utc>, f64)]) -> Option<f64> {
+    let fim = leituras.last()?;
+    let mut inicio = leituras.len() - 1;
+    while inicio > 0 && leituras[inicio - 1].1 <= leituras[inicio].1 {
+        inicio -= 1;
+    }
+    let primeiro = leituras[inicio];
+    let horas = (fim.0 - primeiro.0).num_seconds() as f64 / 3600.0;
+    if horas <= 0.0 {
+        return None;
+    }
+    Some(arredondar_duas_casas((fim.1 - primeiro.1) / horas))
+}
+
+fn leituras_recentes(
+    conn: &Connection,
+    ponto_id: &str,
+    ultima: DateTime<Utc>,
+) -> rusqlite::Result<Vec<(DateTime<Utc>, f64)>> {
+    let desde = (ultima - Duration::hours(JANELA_TAXA_HORAS)).to_rfc3339_opts(SecondsFormat::Secs, true);
+    let mut stmt = conn.prepare(
+        "SELECT l.medido_em, l.nivel_percentual
+         FROM leituras_sensores l
+         JOIN sensores s ON s.id = l.sensor_id AND s.ativo
+         WHERE l.ponto_id = ?1 AND l.medido_em >= ?2
+         ORDER BY l.medido_em, l.id",
+    )?;
+    let leituras = stmt
+        .query_map(params![ponto_id, desde], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    Ok(leituras
+        .into_iter()
+        .filter_map(|(medido_em, nivel)| {
+            let medido_em = DateTime::parse_from_rfc3339(&medido_em).ok()?;
+            Some((medido_em.with_timezone(&Utc), nivel))
+        })
+        .collect())
+}
+
+/// Última leitura de cada ponto com sensor ativo. Leituras de sensores
+/// desativados não contam, nem para o nível nem para a taxa de enchimento.
+pub fn estado_enchimento(conn: &Connection) -> rusqlite::Result<Vec<EstadoEnchimento>> {
+    let mut stmt = conn.prepare(
+        "SELECT l.ponto_id, l.sensor_id, l.nivel_percentual, l.bateria_percentual, l.medido_em
+         FROM leituras_sensores l
+         WHERE l.id = (
+            SELECT l2.id FROM leituras_sensores l2
+            JOIN sensores s ON s.id = l2.sensor_id AND s.ativo
+            WHERE l2.ponto_id = l.ponto_id
+            ORDER BY l2.medido_em DESC, l2.id DESC
+            LIMIT 1
+         )
+         ORDER BY l.ponto_id",
+    )?;
+    let ultimas = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, f64>(2)?,
+                row.get::<_, Option<f64>>(3)?,
+                row.get::<_, String>(4)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut estados = Vec::with_capacity(ultimas.len());
+    for (ponto_id, sensor_id, nivel_percentual, bateria_percentual, medido_em) in ultimas {
+        let taxa_enchimento_pct_hora = match DateTime::parse_from_rfc3339(&medido_em) {
+            Ok(ultima) => taxa_enchimento(&leituras_recentes(conn, &ponto_id, ultima.with_timezone(&Utc))?),
+            Err(_) => None,
+        };
+        estados.push(EstadoEnchimento {
+            bateria_baixa: bateria_percentual.is_some_and(|b| b < BATERIA_BAIXA_PERCENTUAL),
+            ponto_id,
+            sensor_id,
+            nivel_percentual,
+            bateria_percentual,
+            medido_em,
+            taxa_enchimento_pct_hora,
+        });
+    }
+    Ok(estados)
+}
+
+pub fn mapa_enchimento(conn: &Connection) -> rusqlite::Result<HashMap<String, EstadoEnchimento>> {
+    Ok(estado_enchimento(conn)?
+        .into_iter()
+        .map(|e| (e.ponto_id.clone(), e))
+        .collect())
+}
+
+/// Nível esperado daqui a `horizonte_horas`, projetando a taxa de enchimento
+/// a partir da última leitura.
+fn nivel_previsto(estado: &EstadoEnchimento, agora: DateTime<Utc>, horizonte_horas: f64) -> f64 {
+    let taxa = estado.taxa_enchimento_pct_hora.unwrap_or(0.0).max(0.0);
+    let desde_leitura = DateTime::parse_from_rfc3339(&estado.medido_em)
+        .map(|m| (agora - m.with_timezone(&Utc)).num_seconds().max(0) as f64 / 3600.0)
+        .unwrap_or(0.0);
+    estado.nivel_percentual + taxa * (desde_leitura + horizonte_horas)
+}
+
+/// Acrescenta a `pontos_a_visitar` os pontos cujo nível atual ou previsto
+/// atinge o limite. Retorna apenas os pontos incluídos.
+pub fn aplicar_selecao_automatica(
+    conn: &Connection,
+    pedido: &mut PedidoOtimizacao,
+) -> Result<Vec<String>, String> {
+    let Some(SelecaoAutomatica { limite_percentual, horizonte_horas }) = pedido.selecao_automatica else {
+        return Ok(Vec::new());
+    };
+    if !(limite_percentual > 0.0 && limite_percentual <= 100.0) {
+        return Err(format!(
+            "Limite de enchimento inválido: {} (esperado entre 0 e 100)",
+            limite_percentual
+        ));
+    }
+    if !horizonte_horas.is_finite() || horizonte_horas < 0.0 {
+        return Err(format!("Horizonte inválido: {} horas", horizonte_horas));
+    }
+
+    let agora = Utc::now();
+    let mut incluidos = Vec::new();
+    for estado in estado_enchimento(conn).map_err(erro_banco)? {
+        if estado.ponto_id == pedido.garagem_id || pedido.pontos_a_visitar.contains(&estado.ponto_id) {
+            continue;
+        }
+        if nivel_previsto(&estado, agora, horizonte_horas) >= limite_percentual {
+            pedido.pontos_a_visitar.push(estado.ponto_id.clone());
+            incluidos.push(estado.ponto_id);
+        }
+    }
+    Ok(incluidos)
+}
@@ -1,80 +1,454 @@
-use crate::otimizacao::models::{DadosPrevisao, PedidoOtimizacao, ResultadoRotaInterna};
+use crate::otimizacao::models::{
+    ComponentePontuacao, ComponentesPontuacao, ContextoPrioridade, DadosPrevisao, DemandaRegiao,
+    JustificativaParada, PedidoOtimizacao, PesosPrioridade, RegistroPrevisao, ResultadoRotaInterna,
+};
+use chrono::{DateTime, Duration, Utc};
 use std::collections::{HashMap, HashSet};
 use std::fs::{self, File}; 
 use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::error::Error;
 use serde::{Deserialize, Serialize};
 
+type MatrizCustos = HashMap<String, HashMap<String, f64>>;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ServicoDistancia { 
-    matriz_custos: HashMap<String, HashMap<String, f64>>,
+    matriz_custos: MatrizCustos,
     
     #[serde(skip)]
     caminho_arquivo: String,
+
+    /// Carregado a partir do backup (ou vazio) porque o arquivo principal
+    /// estava corrompido. Nesse modo nenhuma alteração é gravada, para não
+    /// sobrescrever o arquivo original antes de alguém inspecioná-lo.
+    #[serde(skip)]
+    degradado: bool,
+}
+
+fn caminho_backup(caminho: &Path) -> PathBuf {
+    caminho.with_extension("json.bak")
+}
+
+/// Grava em um arquivo temporário, sincroniza, guarda a versão anterior
+/// em `.bak` e só então substitui o arquivo (rename é atômico).
+fn gravar_json_atomico<T: Serialize>(caminho: &Path, valor: &T) -> Result<(), Box<dyn Error>> {
+    let temporario = caminho.with_extension("json.tmp");
+    let conteudo_json = serde_json::to_string_pretty(valor)?;
+
+    let mut file = File::create(&temporario)?;
+    file.write_all(conteudo_json.as_bytes())?;
+    file.sync_all()?;
+    drop(file);
+
+    if caminho.exists() {
+        fs::copy(caminho, caminho_backup(caminho))?;
+    }
+    fs::rename(&temporario, caminho)?;
+    Ok(())
+}
+
+/// Grava o histórico inteiro em JSON Lines, com a mesma troca atômica de
+/// `gravar_json_atomico` (usado só na migração do formato antigo).
+fn gravar_previsoes(caminho: &Path, registros: &[RegistroPrevisao]) -> Result<(), Box<dyn Error>> {
+    let temporario = caminho.with_extension("jsonl.tmp");
+    let mut file = File::create(&temporario)?;
+    for registro in registros {
+        writeln!(file, "{}", serde_json::to_string(registro)?)?;
+    }
+    file.sync_all()?;
+    drop(file);
+    fs::rename(&temporario, caminho)?;
+    Ok(())
+}
+
+fn ler_matriz(caminho: &Path) -> Result<MatrizCustos, Box<dyn Error>> {
+    let conteudo = fs::read_to_string(caminho)?;
+    Ok(serde_json::from_str(&conteudo)?)
 }
 
 impl ServicoDistancia {
     
-    pub fn new(caminho_json: &str) -> Self {
-        let matriz_custos = match fs::read_to_string(caminho_json) {
-            Ok(conteudo) => {
-                serde_json::from_str(&conteudo).unwrap_or_else(|e| {
-                    eprintln!("Aviso: Erro ao parsear distancias.json ({}). Começando com mapa vazio.", e);
-                    HashMap::new()
-                })
-            },
-            Err(e) => {
-                eprintln!("Aviso: {} não encontrado ({}). Começando com mapa vazio.", caminho_json, e);
-                HashMap::new()
+    /// Carrega a matriz. Arquivo ausente resulta em matriz vazia; arquivo
+    /// corrompido é um erro, a menos que `permitir_degradado` seja verdadeiro,
+    /// caso em que o backup (se válido) é usado em modo somente leitura.
+    pub fn new(caminho_json: &str, permitir_degradado: bool) -> Result<Self, String> {
+        let caminho = Path::new(caminho_json);
+        let mut degradado = false;
+
+        let matriz_custos = if !caminho.exists() {
+            eprintln!("Aviso: {} não encontrado. Começando com mapa vazio.", caminho_json);
+            HashMap::new()
+        } else {
+            match ler_matriz(caminho) {
+                Ok(matriz) => matriz,
+                Err(e) if permitir_degradado => {
+                    degradado = true;
+                    let backup = caminho_backup(caminho);
+                    match ler_matriz(&backup) {
+                        Ok(matriz) => {
+                            eprintln!(
+                                "ERRO: {} corrompido ({}). Modo degradado: usando {} (somente leitura).",
+                                caminho_json, e, backup.display()
+                            );
+                            matriz
+                        }
+                        Err(e_backup) => {
+                            eprintln!(
+                                "ERRO: {} corrompido ({}) e backup indisponível ({}). Modo degradado: matriz vazia (somente leitura).",
+                                caminho_json, e, e_backup
+                            );
+                            HashMap::new()
+                        }
+                    }
+                }
+                Err(e) => {
+                    return Err(format!(
+                        "{} está corrompido ({}). Restaure o arquivo (há um backup em {}) ou inicie com DISTANCIAS_MODO_DEGRADADO=1.",
+                        caminho_json, e, caminho_backup(caminho).display()
+                    ));
+                }
             }
         };
 
-        Self {
+        Ok(Self {
             matriz_custos,
             caminho_arquivo: caminho_json.to_string(),
-        }
+            degradado,
+        })
+    }
+
+    pub fn modo_degradado(&self) -> bool {
+        self.degradado
     }
     
+    /// Custo do trecho direcionado `origem -> destino`. A matriz pode ser
+    /// assimétrica (vias de mão única), então nunca assuma que o trecho
+    /// inverso existe ou tem o mesmo custo — inclusive ao inverter trechos
+    /// de uma sequência.
     pub fn get_custo(&self, origem: &str, destino: &str) -> Option<f64> {
         self.matriz_custos.get(origem)?.get(destino).copied()
     }
 
-    pub fn adicionar_distancia(&mut self, origem: String, destino: String, custo: f64) { // <-- 'pub(crate)' MUDOU PARA 'pub'
-        self.matriz_custos
-            .entry(origem.clone())
-            .or_default()
-            .insert(destino.clone(), custo);
+    /// Aplica `alteracao` a uma cópia da matriz e só a adota depois de
+    /// gravada em disco, com uma única escrita por chamada.
+    fn alterar<R>(&mut self, alteracao: impl FnOnce(&mut MatrizCustos) -> R) -> Result<R, String> {
+        if self.degradado {
+            return Err("Matriz de distâncias em modo degradado (somente leitura)".to_string());
+        }
+        let mut nova = self.matriz_custos.clone();
+        let resultado = alteracao(&mut nova);
+        nova.retain(|_, vizinhos| !vizinhos.is_empty());
+
+        if nova != self.matriz_custos {
+            self.salvar(&nova).map_err(|e| {
+                eprintln!("ERRO CRÍTICO: Não foi possível salvar distancias.json: {}", e);
+                format!("Não foi possível salvar a matriz de distâncias: {}", e)
+            })?;
+            self.matriz_custos = nova;
+        }
+        Ok(resultado)
+    }
+
+    pub fn adicionar_distancia(&mut self, origem: String, destino: String, custo: f64, simetrica: bool) -> Result<(), String> {
+        self.adicionar_distancias(vec![(origem, destino, custo, simetrica)])
+    }
+
+    /// Insere vários trechos `(origem, destino, custo, simetrica)` com uma única gravação.
+    pub fn adicionar_distancias(&mut self, trechos: Vec<(String, String, f64, bool)>) -> Result<(), String> {
+        self.alterar(|matriz| {
+            for (origem, destino, custo, simetrica) in trechos {
+                if simetrica {
+                    matriz
+                        .entry(destino.clone())
+                        .or_default()
+                        .insert(origem.clone(), custo);
+                }
+                matriz
+                    .entry(origem)
+                    .or_default()
+                    .insert(destino, custo);
+            }
+        })
+    }
+
+    /// Remove o trecho `origem -> destino` (e o inverso, se `simetrica`).
+    /// Retorna quantos trechos foram removidos.
+    pub fn remover_distancia(&mut self, origem: &str, destino: &str, simetrica: bool) -> Result<usize, String> {
+        self.alterar(|matriz| {
+            let mut removidos = 0;
+            if let Some(vizinhos) = matriz.get_mut(origem) {
+                removidos += vizinhos.remove(destino).is_some() as usize;
+            }
+            if simetrica {
+                if let Some(vizinhos) = matriz.get_mut(destino) {
+                    removidos += vizinhos.remove(origem).is_some() as usize;
+                }
+            }
+            removidos
+        })
+    }
+
+    /// Remove um ponto e todos os trechos que saem ou chegam nele.
+    pub fn remover_ponto(&mut self, ponto: &str) -> Result<usize, String> {
+        self.alterar(|matriz| {
+            let mut removidos = matriz
+                .remove(ponto)
+                .map(|vizinhos| vizinhos.len())
+                .unwrap_or(0);
+            for vizinhos in matriz.values_mut() {
+                removidos += vizinhos.remove(ponto).is_some() as usize;
+            }
+            removidos
+        })
+    }
+
+    pub fn matriz(&self) -> &MatrizCustos {
+        &self.matriz_custos
+    }
+
+    /// Todos os pontos que aparecem como origem ou destino.
+    pub fn pontos(&self) -> HashSet<String> {
         self.matriz_custos
-            .entry(destino)
-            .or_default()
-            .insert(origem, custo);
-            
-        if let Err(e) = self.salvar() {
-            eprintln!("ERRO CRÍTICO: Não foi possível salvar distancias.json: {}", e);
+            .iter()
+            .flat_map(|(origem, vizinhos)| std::iter::once(origem).chain(vizinhos.keys()))
+            .cloned()
+            .collect()
+    }
+
+    /// Pontos alcançáveis a partir de `inicio`. Com `reverso`, segue os
+    /// trechos no sentido contrário (pontos que conseguem chegar em `inicio`).
+    pub fn alcancaveis(&self, inicio: &str, reverso: bool) -> HashSet<String> {
+        let mut visitados: HashSet<String> = HashSet::new();
+        let mut pilha = vec![inicio.to_string()];
+
+        while let Some(atual) = pilha.pop() {
+            if !visitados.insert(atual.clone()) {
+                continue;
+            }
+            if reverso {
+                for (origem, vizinhos) in &self.matriz_custos {
+                    if vizinhos.contains_key(&atual) && !visitados.contains(origem) {
+                        pilha.push(origem.clone());
+                    }
+                }
+            } else if let Some(vizinhos) = self.matriz_custos.get(&atual) {
+                pilha.extend(vizinhos.keys().filter(|v| !visitados.contains(*v)).cloned());
+            }
         }
+        visitados
     }
 
-    fn salvar(&self) -> Result<(), Box<dyn Error>> {
-        let conteudo_json = serde_json::to_string_pretty(&self.matriz_custos)?;
-        let mut file = File::create(&self.caminho_arquivo)?;
-        file.write_all(conteudo_json.as_bytes())?;
-        Ok(())
+    fn salvar(&self, matriz: &MatrizCustos) -> Result<(), Box<dyn Error>> {
+        gravar_json_atomico(Path::new(&self.caminho_arquivo), matriz)
     }
 }
 
-#[derive(Debug, Default)]
+/// Validade usada quando a previsão não informa `valida_ate`.
+const VALIDADE_PADRAO_PREVISAO_DIAS: i64 = 7;
+
+/// Lê o histórico em JSON Lines. Uma última linha incompleta (gravação
+/// interrompida) é descartada e cortada do arquivo, para que o próximo
+/// registro comece numa linha nova.
+fn ler_previsoes(caminho: &Path) -> Result<Vec<RegistroPrevisao>, String> {
+    let conteudo = fs::read_to_string(caminho)
+        .map_err(|e| format!("Erro ao ler {}: {}", caminho.display(), e))?;
+
+    let completo = conteudo.rfind('\n').map_or(0, |fim| fim + 1);
+    if completo < conteudo.len() {
+        match serde_json::from_str::<RegistroPrevisao>(&conteudo[completo..]) {
+            // Registro inteiro, só faltou a quebra de linha
+            Ok(_) => {
+                let mut file = fs::OpenOptions::new()
+                    .append(true)
+                    .open(caminho)
+                    .map_err(|e| format!("Erro ao abrir {}: {}", caminho.display(), e))?;
+                file.write_all(b"\n")
+                    .map_err(|e| format!("Erro ao gravar {}: {}", caminho.display(), e))?;
+            }
+            Err(_) => {
+                eprintln!(
+                    "Aviso: última linha de {} incompleta foi descartada.",
+                    caminho.display()
+                );
+                fs::OpenOptions::new()
+                    .write(true)
+                    .open(caminho)
+                    .and_then(|file| file.set_len(completo as u64))
+                    .map_err(|e| format!("Erro ao corrigir {}: {}", caminho.display(), e))?;
+                return ler_linhas_previsoes(caminho, &conteudo[..completo]);
+            }
+        }
+    }
+    ler_linhas_previsoes(caminho, &conteudo)
+}
+
+fn ler_linhas_previsoes(caminho: &Path, conteudo: &str) -> Result<Vec<RegistroPrevisao>, String> {
+    conteudo
+        .lines()
+        .enumerate()
+        .filter(|(_, linha)| !linha.trim().is_empty())
+        .map(|(indice, linha)| {
+            serde_json::from_str(linha).map_err(|e| {
+                format!("{} está corrompido na linha {} ({}).", caminho.display(), indice + 1, e)
+            })
+        })
+        .collect()
+}
+
+/// Histórico de previsões de demanda em JSON Lines: cada previsão nova é
+/// acrescentada ao fim do arquivo, sem regravar as anteriores. Em memória,
+/// as previsões ficam indexadas por ponto e ordenadas por `valida_de`. A
+/// previsão em vigor de um ponto é a mais recente cuja validade cobre o
+/// instante atual.
+#[derive(Debug)]
 pub struct ServicoDemanda {
-    previsoes: HashMap<String, (String, f64)>,
+    por_ponto: HashMap<String, Vec<RegistroPrevisao>>,
+    caminho_arquivo: String,
 }
 impl ServicoDemanda {
-    pub fn new() -> Self {
-        Self::default()
+    /// Carrega `caminho_jsonl`. Se ele ainda não existe e há o arquivo do
+    /// formato antigo (mesmo nome com `.json`, um único array), as previsões
+    /// são migradas; o arquivo antigo fica intocado.
+    pub fn new(caminho_jsonl: &str) -> Result<Self, String> {
+        let caminho = Path::new(caminho_jsonl);
+        let legado = caminho.with_extension("json");
+
+        let registros = if caminho.exists() {
+            ler_previsoes(caminho)?
+        } else if legado.exists() {
+            let conteudo = fs::read_to_string(&legado)
+                .map_err(|e| format!("Erro ao ler {}: {}", legado.display(), e))?;
+            let registros: Vec<RegistroPrevisao> = serde_json::from_str(&conteudo).map_err(|e| {
+                format!(
+                    "{} está corrompido ({}). Restaure o arquivo a partir de {}.",
+                    legado.display(), e, caminho_backup(&legado).display()
+                )
+            })?;
+            gravar_previsoes(caminho, &registros)
+                .map_err(|e| format!("Não foi possível migrar as previsões de demanda: {}", e))?;
+            println!(
+                "✅ {} previsões de demanda migradas de {} para {}",
+                registros.len(), legado.display(), caminho_jsonl
+            );
+            registros
+        } else {
+            Vec::new()
+        };
+
+        let mut servico = Self {
+            por_ponto: HashMap::new(),
+            caminho_arquivo: caminho_jsonl.to_string(),
+        };
+        for registro in registros {
+            servico.indexar(registro);
+        }
+        Ok(servico)
+    }
+
+    /// Insere mantendo a ordem por `valida_de` (empates na ordem de chegada).
+    fn indexar(&mut self, registro: RegistroPrevisao) {
+        let registros = self.por_ponto.entry(registro.ponto_id.clone()).or_default();
+        let posicao = registros.partition_point(|r| r.valida_de <= registro.valida_de);
+        registros.insert(posicao, registro);
     }
-    pub fn atualizar_previsao(&mut self, dados: DadosPrevisao) {
-        self.previsoes.insert(dados.ponto_id, (dados.regiao, dados.previsao_demanda));
+
+    fn acrescentar(&self, registro: &RegistroPrevisao) -> Result<(), Box<dyn Error>> {
+        let mut linha = serde_json::to_string(registro)?;
+        linha.push('\n');
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.caminho_arquivo)?;
+        file.write_all(linha.as_bytes())?;
+        file.sync_data()?;
+        Ok(())
     }
+
+    pub fn atualizar_previsao(&mut self, dados: DadosPrevisao) -> Result<RegistroPrevisao, String> {
+        if dados.ponto_id.trim().is_empty() {
+            return Err("Id do ponto não pode ser vazio".to_string());
+        }
+        if !dados.previsao_demanda.is_finite() || dados.previsao_demanda < 0.0 {
+            return Err(format!("Previsão de demanda inválida: {}", dados.previsao_demanda));
+        }
+
+        let agora = Utc::now();
+        let valida_de = dados.valida_de.unwrap_or(agora);
+        let valida_ate = dados
+            .valida_ate
+            .unwrap_or_else(|| valida_de + Duration::days(VALIDADE_PADRAO_PREVISAO_DIAS));
+        if valida_ate <= valida_de {
+            return Err("'valida_ate' deve ser posterior a 'valida_de'".to_string());
+        }
+
+        let registro = RegistroPrevisao {
+            ponto_id: dados.ponto_id,
+            regiao: dados.regiao,
+            previsao_demanda: dados.previsao_demanda,
+            valida_de,
+            valida_ate,
+            registrada_em: agora,
+        };
+
+        self.acrescentar(&registro)
+            .map_err(|e| format!("Não foi possível salvar as previsões de demanda: {}", e))?;
+        self.indexar(registro.clone());
+
+        Ok(registro)
+    }
+
+    /// Só as previsões do ponto que já começaram a valer em `em` são
+    /// examinadas, e a busca por elas é binária.
+    fn previsao_vigente(&self, ponto_id: &str, em: DateTime<Utc>) -> Option<&RegistroPrevisao> {
+        let registros = self.por_ponto.get(ponto_id)?;
+        let iniciadas = registros.partition_point(|r| r.valida_de <= em);
+        registros[..iniciadas]
+            .iter()
+            .filter(|r| em < r.valida_ate)
+            .max_by_key(|r| r.registrada_em)
+    }
+
+    /// Demanda prevista em vigor agora; previsões expiradas são ignoradas.
     pub fn get_demanda_ponto(&self, ponto_id: &str) -> Option<f64> {
-        self.previsoes.get(ponto_id).map(|(_regiao, demanda)| *demanda)
+        self.previsao_vigente(ponto_id, Utc::now())
+            .map(|r| r.previsao_demanda)
+    }
+
+    /// Todas as previsões do ponto, da mais recente para a mais antiga.
+    pub fn historico_ponto(&self, ponto_id: &str) -> Vec<RegistroPrevisao> {
+        let mut registros = self.por_ponto.get(ponto_id).cloned().unwrap_or_default();
+        registros.sort_by_key(|r| std::cmp::Reverse(r.registrada_em));
+        registros
+    }
+
+    /// Soma das previsões em vigor por região (considerando a região da
+    /// previsão vigente de cada ponto).
+    pub fn agregado_por_regiao(&self, regiao: Option<&str>) -> Vec<DemandaRegiao> {
+        let agora = Utc::now();
+        let mut por_regiao: HashMap<String, (usize, f64)> = HashMap::new();
+        for ponto in self.por_ponto.keys() {
+            if let Some(vigente) = self.previsao_vigente(ponto, agora) {
+                if regiao.is_some_and(|r| r != vigente.regiao) {
+                    continue;
+                }
+                let acumulado = por_regiao.entry(vigente.regiao.clone()).or_insert((0, 0.0));
+                acumulado.0 += 1;
+                acumulado.1 += vigente.previsao_demanda;
+            }
+        }
+
+        let mut agregados: Vec<DemandaRegiao> = por_regiao
+            .into_iter()
+            .map(|(regiao, (pontos, total))| DemandaRegiao {
+                regiao,
+                pontos_com_previsao: pontos,
+                demanda_total: total,
+                demanda_media: total / pontos as f64,
+            })
+            .collect();
+        agregados.sort_by(|a, b| a.regiao.cmp(&b.regiao));
+        agregados
     }
 }
 
@@ -116,15 +490,109 @@ pub fn otimizar_rota_vizinho_proximo(
         sequencia_pontos.push(pedido.garagem_id.clone());
     }
     
-    ResultadoRotaInterna { sequencia_pontos, distancia_total_km }
+    ResultadoRotaInterna { sequencia_pontos, distancia_total_km, detalhamento: Vec::new() }
 }
 
+struct Candidato<'a> {
+    ponto_id: &'a str,
+    distancia: f64,
+    demanda: Option<f64>,
+    risco: Option<f64>,
+    horas_sem_visita: Option<f64>,
+}
+
+fn faixa(valores: impl Iterator<Item = f64>) -> (f64, f64) {
+    valores.fold((f64::MAX, f64::MIN), |(min, max), v| (min.min(v), max.max(v)))
+}
+
+/// Min-max entre os candidatos da etapa. Sem variação o critério não
+/// diferencia ninguém: vale 1 se houver valor e 0 caso contrário.
+fn normalizar(valor: f64, (min, max): (f64, f64)) -> f64 {
+    if max - min <= f64::EPSILON {
+        if valor > 0.0 { 1.0 } else { 0.0 }
+    } else {
+        (valor - min) / (max - min)
+    }
+}
+
+fn arredondar_pontuacao(num: f64) -> f64 {
+    (num * 10_000.0).round() / 10_000.0
+}
+
+fn componente(valor: Option<f64>, normalizado: f64, peso: f64, soma_pesos: f64) -> ComponentePontuacao {
+    ComponentePontuacao {
+        valor: valor.map(arredondar_pontuacao),
+        normalizado: arredondar_pontuacao(normalizado),
+        peso,
+        contribuicao: arredondar_pontuacao(peso * normalizado / soma_pesos),
+    }
+}
+
+fn pontuar(
+    candidatos: &[Candidato],
+    pesos: &PesosPrioridade,
+) -> Vec<(f64, ComponentesPontuacao)> {
+    let soma_pesos = pesos.soma();
+    let faixa_distancia = faixa(candidatos.iter().map(|c| c.distancia));
+    let faixa_demanda = faixa(candidatos.iter().map(|c| c.demanda.unwrap_or(0.0)));
+    let faixa_horas = faixa(candidatos.iter().filter_map(|c| c.horas_sem_visita));
+
+    candidatos
+        .iter()
+        .map(|c| {
+            // Menor distância é melhor; empate total conta como o melhor possível.
+            let distancia = if faixa_distancia.1 - faixa_distancia.0 <= f64::EPSILON {
+                1.0
+            } else {
+                1.0 - normalizar(c.distancia, faixa_distancia)
+            };
+            let demanda = normalizar(c.demanda.unwrap_or(0.0), faixa_demanda);
+            // O risco já vem na escala 0..1.
+            let risco = c.risco.unwrap_or(0.0);
+            // Ponto nunca visitado é tratado como o mais atrasado.
+            let tempo = match c.horas_sem_visita {
+                Some(horas) => normalizar(horas, faixa_horas),
+                None => 1.0,
+            };
+
+            let componentes = ComponentesPontuacao {
+                distancia: componente(Some(c.distancia), distancia, pesos.distancia, soma_pesos),
+                demanda: componente(c.demanda, demanda, pesos.demanda, soma_pesos),
+                risco_transbordo: componente(c.risco, risco, pesos.risco_transbordo, soma_pesos),
+                tempo_desde_ultima_visita: componente(
+                    c.horas_sem_visita,
+                    tempo,
+                    pesos.tempo_desde_ultima_visita,
+                    soma_pesos,
+                ),
+            };
+            let pontuacao = componentes.distancia.contribuicao
+                + componentes.demanda.contribuicao
+                + componentes.risco_transbordo.contribuicao
+                + componentes.tempo_desde_ultima_visita.contribuicao;
+            (pontuacao, componentes)
+        })
+        .collect()
+}
+
+/// A cada etapa escolhe, entre os pontos alcançáveis a partir do atual, o de
+/// maior pontuação ponderada (distância, demanda, risco de transbordo e
+/// tempo desde a última visita, cada um normalizado entre os candidatos).
 pub fn otimizar_rota_por_prioridade(
     pedido: &PedidoOtimizacao,
     servico_distancia: &ServicoDistancia,
     servico_demanda: &ServicoDemanda,
+    contexto: &ContextoPrioridade,
 ) -> ResultadoRotaInterna {
+    let agora = Utc::now();
+    let pesos = if contexto.pesos.validar().is_ok() {
+        contexto.pesos
+    } else {
+        PesosPrioridade::default()
+    };
+
     let mut sequencia_pontos = Vec::new();
+    let mut detalhamento = Vec::new();
     let mut distancia_total_km = 0.0;
     let mut nao_visitados: HashSet<String> =
         pedido.pontos_a_visitar.iter().cloned().collect();
@@ -132,45 +600,62 @@ pub fn otimizar_rota_por_prioridade(
     sequencia_pontos.push(ponto_atual.clone());
 
     while !nao_visitados.is_empty() {
-        let mut melhor_vizinho: Option<String> = None;
-        let mut maior_prioridade = f64::MIN; 
-
-        for ponto_destino in nao_visitados.iter() {
-            let distancia = servico_distancia
-                .get_custo(&ponto_atual, ponto_destino)
-                .unwrap_or(f64::MAX); 
-            if distancia == f64::MAX { continue; }
-
-            let demanda = servico_demanda
-                .get_demanda_ponto(ponto_destino)
-                .unwrap_or(0.0);
-            
-            let prioridade = (demanda * 1000.0) - distancia; 
-
-            if prioridade > maior_prioridade {
-                maior_prioridade = prioridade;
-                melhor_vizinho = Some(ponto_destino.to_string());
-            }
+        let candidatos: Vec<Candidato> = nao_visitados
+            .iter()
+            .filter_map(|ponto_destino| {
+                let distancia = servico_distancia.get_custo(&ponto_atual, ponto_destino)?;
+                Some(Candidato {
+                    ponto_id: ponto_destino,
+                    distancia,
+                    demanda: servico_demanda.get_demanda_ponto(ponto_destino),
+                    risco: contexto
+                        .risco_transbordo
+                        .get(ponto_destino)
+                        .map(|r| r.clamp(0.0, 1.0)),
+                    horas_sem_visita: contexto
+                        .ultima_visita
+                        .get(ponto_destino)
+                        .map(|v| (agora - *v).num_minutes().max(0) as f64 / 60.0),
+                })
+            })
+            .collect();
+        if candidatos.is_empty() {
+            break;
         }
 
-        match melhor_vizinho {
-            Some(proximo) => {
-                let custo_real_da_viagem = servico_distancia
-                    .get_custo(&ponto_atual, &proximo)
-                    .unwrap_or(0.0);
-                
-                distancia_total_km += custo_real_da_viagem;
-                ponto_atual = proximo.clone();
-                sequencia_pontos.push(ponto_atual.clone());
-                nao_visitados.remove(&ponto_atual);
-            }
-            None => break,
-        }
+        let mut avaliados: Vec<(f64, ComponentesPontuacao, &Candidato)> = pontuar(&candidatos, &pesos)
+            .into_iter()
+            .zip(candidatos.iter())
+            .map(|((pontuacao, componentes), c)| (pontuacao, componentes, c))
+            .collect();
+        // Empates resolvidos pelo id para a rota não depender da ordem do HashSet.
+        avaliados.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.2.ponto_id.cmp(b.2.ponto_id)));
+
+        let segundo = avaliados
+            .get(1)
+            .map(|(pontuacao, _, c)| (c.ponto_id.to_string(), arredondar_pontuacao(*pontuacao)));
+        let (pontuacao, componentes, escolhido) = avaliados.swap_remove(0);
+        let proximo = escolhido.ponto_id.to_string();
+
+        detalhamento.push(JustificativaParada {
+            ordem: sequencia_pontos.len(),
+            ponto_id: proximo.clone(),
+            pontuacao: arredondar_pontuacao(pontuacao),
+            componentes,
+            candidatos_avaliados: candidatos.len(),
+            pontuacao_segundo_colocado: segundo.as_ref().map(|(_, p)| *p),
+            segundo_colocado: segundo.map(|(id, _)| id),
+        });
+
+        distancia_total_km += escolhido.distancia;
+        nao_visitados.remove(&proximo);
+        sequencia_pontos.push(proximo.clone());
+        ponto_atual = proximo;
     }
     if let Some(custo_final) = servico_distancia.get_custo(&ponto_atual, &pedido.garagem_id) {
         distancia_total_km += custo_final;
         sequencia_pontos.push(pedido.garagem_id.clone());
     }
 
-    ResultadoRotaInterna { sequencia_pontos, distancia_total_km }
+    ResultadoRotaInterna { sequencia_pontos, distancia_total_km, detalhamento }
 }
\ No newline at end of file
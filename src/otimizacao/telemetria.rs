@@ -0,0 +1,122 @@
+// src/otimizacao/telemetria.rs
+use crate::otimizacao::models::{PosicaoVeiculo, StatusRota};
+use crate::otimizacao::TOLERANCIA_FUTURO_MIN;
+use chrono::{DateTime, Duration, SecondsFormat, SubsecRound, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+
+/// Rota despachada ou em andamento associada ao veículo (a mais recente).
+fn rota_ativa_do_veiculo(conn: &Connection, veiculo_id: &str) -> rusqlite::Result<Option<i64>> {
+    conn.query_row(
+        "SELECT id FROM rotas_otimizadas
+         WHERE veiculo_id = ?1 AND status IN (?2, ?3)
+         ORDER BY id DESC
+         LIMIT 1",
+        params![veiculo_id, StatusRota::Despachada.as_str(), StatusRota::EmAndamento.as_str()],
+        |row| row.get(0),
+    )
+    .optional()
+}
+
+/// Valida e grava uma posição GPS. Sem `rota_id`, a posição é associada à
+/// rota ativa do veículo, se houver. Retorna a posição gravada, ou `None`
+/// se ela já havia sido recebida (mesmo veículo e mesmo `medido_em`).
+pub fn registrar_posicao(conn: &Connection, posicao: &PosicaoVeiculo) -> Result<Option<PosicaoVeiculo>, String> {
+    if posicao.veiculo_id.trim().is_empty() {
+        return Err("Id do veículo não pode ser vazio".to_string());
+    }
+    if !(-90.0..=90.0).contains(&posicao.latitude) || !(-180.0..=180.0).contains(&posicao.longitude) {
+        return Err(format!(
+            "Coordenadas inválidas: ({}, {})",
+            posicao.latitude, posicao.longitude
+        ));
+    }
+    if let Some(velocidade) = posicao.velocidade_kmh {
+        if !velocidade.is_finite() || velocidade < 0.0 {
+            return Err(format!("Velocidade inválida: {}", velocidade));
+        }
+    }
+    if let Some(rota_id) = posicao.rota_id {
+        let existe: bool = conn
+            .query_row(
+                "SELECT EXISTS(SELECT 1 FROM rotas_otimizadas WHERE id = ?1)",
+                [rota_id],
+                |row| row.get(0),
+            )
+            .map_err(|e| format!("Erro ao acessar banco de dados: {}", e))?;
+        if !existe {
+            return Err(format!("Rota {} não encontrada", rota_id));
+        }
+    }
+
+    let agora = Utc::now();
+    let medido_em = posicao.medido_em.unwrap_or(agora);
+    if medido_em > agora + Duration::minutes(TOLERANCIA_FUTURO_MIN) {
+        return Err(format!("Posição no futuro: {}", medido_em.to_rfc3339()));
+    }
+
+    let rota_id = match posicao.rota_id {
+        Some(id) => Some(id),
+        None => rota_ativa_do_veiculo(conn, &posicao.veiculo_id)
+            .map_err(|e| format!("Erro ao acessar banco de dados: {}", e))?,
+    };
+
+    let inseridas = conn
+        .execute(
+            "INSERT OR IGNORE INTO telemetria_veiculos
+                (veiculo_id, rota_id, latitude, longitude, velocidade_kmh, medido_em, recebido_em)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                posicao.veiculo_id,
+                rota_id,
+                posicao.latitude,
+                posicao.longitude,
+                posicao.velocidade_kmh,
+                medido_em.to_rfc3339_opts(SecondsFormat::Secs, true),
+                agora.to_rfc3339(),
+            ],
+        )
+        .map_err(|e| format!("Erro ao registrar posição: {}", e))?;
+
+    if inseridas == 0 {
+        return Ok(None);
+    }
+    Ok(Some(PosicaoVeiculo {
+        rota_id,
+        medido_em: Some(medido_em.trunc_subsecs(0)),
+        ..posicao.clone()
+    }))
+}
+
+/// Última posição de cada veículo, opcionalmente só dos que estão em `rota_id`.
+pub fn ultimas_posicoes(conn: &Connection, rota_id: Option<i64>) -> rusqlite::Result<Vec<PosicaoVeiculo>> {
+    let mut stmt = conn.prepare(
+        "SELECT t.veiculo_id, t.rota_id, t.latitude, t.longitude, t.velocidade_kmh, t.medido_em
+         FROM telemetria_veiculos t
+         WHERE t.id = (
+            SELECT t2.id FROM telemetria_veiculos t2
+            WHERE t2.veiculo_id = t.veiculo_id
+            ORDER BY t2.medido_em DESC, t2.id DESC
+            LIMIT 1
+         )
+         AND (?1 IS NULL OR t.rota_id = ?1)
+         ORDER BY t.veiculo_id",
+    )?;
+
+    let posicoes = stmt
+        .query_map([rota_id], |row| {
+            let medido_em: String = row.get(5)?;
+            Ok(PosicaoVeiculo {
+                veiculo_id: row.get(0)?,
+                rota_id: row.get(1)?,
+                latitude: row.get(2)?,
+                longitude: row.get(3)?,
+                velocidade_kmh: row.get(4)?,
+                medido_em: DateTime::parse_from_rfc3339(&medido_em)
+                    .ok()
+                    .map(|m| m.with_timezone(&Utc)),
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    Ok(posicoes)
+}
@@ -32,6 +32,7 @@ impl Predicao {
         }
     }
 
+    #[allow(dead_code)]
     pub fn mostrar_terminal(&self) {
         println!("=== Predição (simulada) ===");
         println!("Timestamp: {}", self.timestamp);
@@ -2,12 +2,15 @@
 use crate::dataset::Dataset;
 use crate::modelo::ModeloML;
 use crate::predicao::Predicao;
+use crate::emissoes::TabelaFatores;
 use crate::ia_api;
+use crate::tipos_residuo::Catalogo;
+use chrono::Utc;
 
 use std::io::{self, Write};
 use std::path::Path;
 
-pub async fn run_prediction_module() -> Result<(), Box<dyn std::error::Error>> {
+pub async fn run_prediction_module(usuario_id: u32) -> Result<(), Box<dyn std::error::Error>> {
     println!("\n--- 🔮 Módulo de Predição e Análise de IA ---");
 
 
@@ -36,7 +39,7 @@ pub async fn run_prediction_module() -> Result<(), Box<dyn std::error::Error>> {
     let db_path = Path::new("data/db.json");
     let mut dataset = Dataset::load_from_file(db_path).unwrap_or_else(|_| Dataset::new());
 
-    dataset.add_entry(tipo.clone(), quantidade, observacoes.clone()); // Passa o clone de observacoes
+    dataset.add_entry(tipo.clone(), quantidade, observacoes.clone(), Some(usuario_id)); // Passa o clone de observacoes
     dataset.save_to_file(db_path)?;
 
     println!("Entrada salva com sucesso em {:?}", db_path);
@@ -48,14 +51,31 @@ pub async fn run_prediction_module() -> Result<(), Box<dyn std::error::Error>> {
     let predicao: Predicao = modelo.prever(&dataset);
     predicao.mostrar_terminal();
 
-    let factor = co2_factor(&tipo);
-    let co2_saved = quantidade * factor;
-    println!(
-        "\nEstimativa imediata: reciclar {:.3} kg de {} => ~{:.3} kg CO₂ evitado (fator {:.2})",
-        quantidade, tipo, co2_saved, factor
-    );
+    // Fora do servidor não há acesso às tabelas de fatores e de tipos do
+    // banco: usa os fatores e o catálogo padrão
+    let fatores = TabelaFatores::padrao();
+    let catalogo = Catalogo::padrao();
+    let estimativa = fatores.estimar(&tipo, quantidade, Utc::now().date_naive());
+    match (estimativa.co2_evitado, estimativa.fator) {
+        (Some(co2_saved), Some(factor)) => println!(
+            "\nEstimativa imediata: reciclar {:.3} kg de {} => ~{:.3} kg CO₂ evitado (fator {:.2})",
+            quantidade, tipo, co2_saved, factor
+        ),
+        _ => println!(
+            "\nEstimativa imediata: material '{}' sem fator de emissão cadastrado; CO₂ evitado não calculado",
+            tipo
+        ),
+    }
 
-    if let Some(trend) = dataset.trend_percent(&tipo, 3) {
+    // Fora do servidor não há previsões de demanda carregadas
+    let estatisticas = ia_api::calcular_estatisticas(&dataset, &catalogo, &fatores, Vec::new());
+    let chave = catalogo.chave(&tipo);
+    let tendencia = estatisticas
+        .por_tipo
+        .iter()
+        .find(|e| e.tipo == chave)
+        .and_then(|e| e.tendencia_percentual);
+    if let Some(trend) = tendencia {
         println!(
             "Tendência (média das últimas 3 vs anteriores 3) para {}: {:+.2}%",
             tipo, trend
@@ -69,14 +89,26 @@ pub async fn run_prediction_module() -> Result<(), Box<dyn std::error::Error>> {
 
     println!("\n🤖 Gerando análise avançada com IA...");
 
-    let prompt = format!(
-        "O usuário coletou {:.3} kg de {}. Considerando os dados históricos, forneça uma BREVE previsão ilustrativa sobre impacto ambiental e tendências. Responda em até 50 palavras.",
-        quantidade, tipo
-    );
+    // Sem acesso à biblioteca de templates do banco: usa o template embutido
+    let template = ia_api::template_padrao(ia_api::ANALISE_PREDICAO, ia_api::Idioma::default())?;
+    let prompt = ia_api::montar_prompt(&template, &tipo, quantidade, &estatisticas, &predicao);
 
-    match ia_api::gerar_resposta_preditiva(&prompt).await {
-        Ok(resposta) => println!("🔎 Previsão da IA: {}", resposta),
-        Err(e) => eprintln!("⚠️ Erro ao gerar previsão com Gemini: {}", e),
+    let provedor = ia_api::provedor_do_ambiente();
+    let analise = match provedor.gerar(&prompt).await {
+        Ok(texto) => ia_api::interpretar_resposta(&texto).map_err(|e| format!("resposta da IA inválida: {}", e)),
+        Err(e) => Err(format!("erro ao gerar previsão com {}: {}", provedor.descricao(), e)),
+    };
+    let analise = analise.unwrap_or_else(|motivo| {
+        eprintln!("⚠️ Usando análise baseada nas estatísticas ({})", motivo);
+        ia_api::analise_de_fallback(&tipo, quantidade, &estatisticas, &catalogo, template.idioma, motivo)
+    });
+
+    println!("🔎 Previsão da IA: {}", analise.resumo);
+    for risco in &analise.riscos {
+        println!("  ⚠️ Risco: {}", risco);
+    }
+    for recomendacao in &analise.recomendacoes {
+        println!("  ✅ Recomendação: {}", recomendacao);
     }
 
     Ok(())
@@ -87,15 +119,4 @@ fn read_input(prompt: &str) -> String {
     let mut buf = String::new();
     io::stdin().read_line(&mut buf).unwrap();
     buf.trim().to_string()
-}
-
-fn co2_factor(tipo: &str) -> f32 {
-    match tipo.to_lowercase().as_str() {
-        "plastico" | "plástico" => 2.0,
-        "papel" => 1.2,
-        "vidro" => 0.6,
-        "metal" => 3.0,
-        "organico" | "orgânico" => 0.3,
-        _ => 1.0,
-    }
 }
\ No newline at end of file
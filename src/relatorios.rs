@@ -0,0 +1,177 @@
+// src/relatorios.rs
+// Relatório de impacto ambiental: o que as coletas evitaram (CO₂ e volume de
+// aterro) contra o que os caminhões emitiram nas rotas do mesmo período.
+
+use crate::dataset::{Dataset, WasteEntry};
+use crate::emissoes::TabelaFatores;
+use crate::otimizacao::{emissoes_rotas, listar_pontos, EmissoesRotas};
+use crate::tipos_residuo::Catalogo;
+use chrono::NaiveDate;
+use rusqlite::Connection;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+
+const SEM_PONTO: &str = "sem_ponto";
+const SEM_REGIAO: &str = "sem_regiao";
+const SEM_USUARIO: &str = "sem_usuario";
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImpactoAgregado {
+    pub chave: String,
+    pub registros: usize,
+    pub total_kg: f64,
+    /// Soma das coletas com fator de emissão vigente na data.
+    pub co2_evitado_kg: f64,
+    /// Coletas sem fator, fora de `co2_evitado_kg`.
+    pub kg_sem_fator: f64,
+    pub volume_aterro_evitado_m3: f64,
+    /// Materiais sem densidade de aterro no catálogo, fora do volume.
+    pub kg_sem_densidade: f64,
+}
+
+impl ImpactoAgregado {
+    fn novo(chave: &str) -> Self {
+        ImpactoAgregado { chave: chave.to_string(), ..Default::default() }
+    }
+
+    fn somar(&mut self, kg: f64, co2: Option<f64>, volume: Option<f64>) {
+        self.registros += 1;
+        self.total_kg += kg;
+        match co2 {
+            Some(co2) => self.co2_evitado_kg += co2,
+            None => self.kg_sem_fator += kg,
+        }
+        match volume {
+            Some(volume) => self.volume_aterro_evitado_m3 += volume,
+            None => self.kg_sem_densidade += kg,
+        }
+    }
+
+    fn arredondado(mut self) -> Self {
+        self.total_kg = arredondar(self.total_kg);
+        self.co2_evitado_kg = arredondar(self.co2_evitado_kg);
+        self.kg_sem_fator = arredondar(self.kg_sem_fator);
+        self.volume_aterro_evitado_m3 = arredondar(self.volume_aterro_evitado_m3);
+        self.kg_sem_densidade = arredondar(self.kg_sem_densidade);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RelatorioImpacto {
+    pub de: Option<NaiveDate>,
+    pub ate: Option<NaiveDate>,
+    /// Todas as coletas do período (`chave` = "total").
+    pub coletas: ImpactoAgregado,
+    pub por_material: Vec<ImpactoAgregado>,
+    pub por_regiao: Vec<ImpactoAgregado>,
+    pub por_ponto: Vec<ImpactoAgregado>,
+    pub por_usuario: Vec<ImpactoAgregado>,
+    pub caminhoes: EmissoesRotas,
+    /// CO₂ evitado pelas coletas menos o emitido pelos caminhões.
+    pub saldo_co2_kg: f64,
+    pub materiais_sem_fator: Vec<String>,
+    pub materiais_sem_densidade: Vec<String>,
+}
+
+fn arredondar(valor: f64) -> f64 {
+    (valor * 100.0).round() / 100.0
+}
+
+/// Grupos do maior total coletado para o menor.
+fn ordenar(grupos: HashMap<String, ImpactoAgregado>) -> Vec<ImpactoAgregado> {
+    let mut grupos: Vec<ImpactoAgregado> = grupos.into_values().map(ImpactoAgregado::arredondado).collect();
+    grupos.sort_by(|a, b| b.total_kg.total_cmp(&a.total_kg).then_with(|| a.chave.cmp(&b.chave)));
+    grupos
+}
+
+fn no_periodo(entrada: &WasteEntry, de: Option<NaiveDate>, ate: Option<NaiveDate>) -> bool {
+    let data = entrada.timestamp.date_naive();
+    de.is_none_or(|de| data >= de) && ate.is_none_or(|ate| data <= ate)
+}
+
+/// Impacto das coletas entre `de` e `ate` (inclusivos), por material,
+/// região, ponto de coleta e usuário. O material é o código do catálogo,
+/// que também dá a densidade de aterro; a região vem do ponto da coleta.
+pub fn relatorio_impacto(
+    conn: &Connection,
+    dataset: &Dataset,
+    de: Option<NaiveDate>,
+    ate: Option<NaiveDate>,
+) -> rusqlite::Result<RelatorioImpacto> {
+    let fatores = TabelaFatores::carregar(conn)?;
+    let catalogo = Catalogo::carregar(conn)?;
+    let regiao_do_ponto: HashMap<String, String> = listar_pontos(conn, None)?
+        .into_iter()
+        .filter_map(|p| Some((p.id, p.regiao?)))
+        .collect();
+    let mut stmt = conn.prepare("SELECT id, nome FROM usuarios")?;
+    let nome_do_usuario = stmt
+        .query_map([], |row| Ok((row.get::<_, u32>(0)?, row.get::<_, String>(1)?)))?
+        .collect::<rusqlite::Result<HashMap<_, _>>>()?;
+
+    let mut coletas = ImpactoAgregado::novo("total");
+    let mut por_material: HashMap<String, ImpactoAgregado> = HashMap::new();
+    let mut por_regiao: HashMap<String, ImpactoAgregado> = HashMap::new();
+    let mut por_ponto: HashMap<String, ImpactoAgregado> = HashMap::new();
+    let mut por_usuario: HashMap<String, ImpactoAgregado> = HashMap::new();
+    let mut materiais_sem_fator = BTreeSet::new();
+    let mut materiais_sem_densidade = BTreeSet::new();
+
+    for entrada in dataset.entries.iter().filter(|e| no_periodo(e, de, ate)) {
+        let material = catalogo.chave(&entrada.tipo);
+        let kg = entrada.quantidade as f64;
+        let co2 = fatores
+            .vigente(&material, entrada.timestamp.date_naive())
+            .ok()
+            .map(|f| kg * f.fator_kg_co2_por_kg as f64);
+        let volume = catalogo.volume_aterro_m3(&material, kg);
+        if co2.is_none() {
+            materiais_sem_fator.insert(material.clone());
+        }
+        if volume.is_none() {
+            materiais_sem_densidade.insert(material.clone());
+        }
+
+        let ponto = entrada.ponto_id.as_deref().unwrap_or(SEM_PONTO);
+        let regiao = entrada
+            .ponto_id
+            .as_ref()
+            .and_then(|p| regiao_do_ponto.get(p))
+            .map(|r| r.as_str())
+            .unwrap_or(SEM_REGIAO);
+        let usuario = match entrada.usuario_id {
+            Some(id) => nome_do_usuario.get(&id).cloned().unwrap_or_else(|| format!("usuario_{}", id)),
+            None => SEM_USUARIO.to_string(),
+        };
+
+        coletas.somar(kg, co2, volume);
+        for (grupos, chave) in [
+            (&mut por_material, material.as_str()),
+            (&mut por_regiao, regiao),
+            (&mut por_ponto, ponto),
+            (&mut por_usuario, usuario.as_str()),
+        ] {
+            grupos
+                .entry(chave.to_string())
+                .or_insert_with(|| ImpactoAgregado::novo(chave))
+                .somar(kg, co2, volume);
+        }
+    }
+
+    let caminhoes = emissoes_rotas(conn, de, ate)?;
+    let coletas = coletas.arredondado();
+    Ok(RelatorioImpacto {
+        de,
+        ate,
+        saldo_co2_kg: arredondar(coletas.co2_evitado_kg - caminhoes.co2_emitido_kg),
+        coletas,
+        por_material: ordenar(por_material),
+        por_regiao: ordenar(por_regiao),
+        por_ponto: ordenar(por_ponto),
+        por_usuario: ordenar(por_usuario),
+        caminhoes,
+        materiais_sem_fator: materiais_sem_fator.into_iter().collect(),
+        materiais_sem_densidade: materiais_sem_densidade.into_iter().collect(),
+    })
+}
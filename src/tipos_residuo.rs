@@ -0,0 +1,508 @@
+// src/tipos_residuo.rs
+// Catálogo de tipos de resíduo: código canônico, nomes por idioma, categoria,
+// apelidos e unidade usual. Toda coleta nova é gravada com o código canônico;
+// apelidos ("PET", "Plastic", "plástico") apontam para o mesmo código.
+
+use crate::dataset::Dataset;
+use crate::emissoes::normalizar_material;
+use crate::unidades::{Leitura, UnidadeMedida};
+use rusqlite::{params, Connection, Row};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Tipo do catálogo inicial.
+struct TipoPadrao {
+    codigo: &'static str,
+    /// pt-BR, en, es.
+    nomes: [&'static str; 3],
+    categoria: CategoriaResiduo,
+    aliases: &'static [&'static str],
+    /// Material solto, como chega à coleta.
+    densidade_kg_m3: f64,
+    /// Material compactado em aterro.
+    densidade_aterro_kg_m3: f64,
+}
+
+const TIPOS_PADRAO: [TipoPadrao; 5] = [
+    TipoPadrao {
+        codigo: "plastico",
+        nomes: ["Plástico", "Plastic", "Plástico"],
+        categoria: CategoriaResiduo::Reciclavel,
+        aliases: &["plasticos", "plastic", "plastics", "pet", "pead", "pp"],
+        densidade_kg_m3: 45.0,
+        densidade_aterro_kg_m3: 350.0,
+    },
+    TipoPadrao {
+        codigo: "papel",
+        nomes: ["Papel", "Paper", "Papel"],
+        categoria: CategoriaResiduo::Reciclavel,
+        aliases: &["papeis", "paper", "papelao", "cartao", "carton"],
+        densidade_kg_m3: 90.0,
+        densidade_aterro_kg_m3: 650.0,
+    },
+    TipoPadrao {
+        codigo: "vidro",
+        nomes: ["Vidro", "Glass", "Vidrio"],
+        categoria: CategoriaResiduo::Reciclavel,
+        aliases: &["vidros", "glass", "vidrio"],
+        densidade_kg_m3: 300.0,
+        densidade_aterro_kg_m3: 1000.0,
+    },
+    TipoPadrao {
+        codigo: "metal",
+        nomes: ["Metal", "Metal", "Metal"],
+        categoria: CategoriaResiduo::Reciclavel,
+        aliases: &["metais", "metales", "aluminio", "aluminum", "lata", "latas", "aco"],
+        densidade_kg_m3: 80.0,
+        densidade_aterro_kg_m3: 550.0,
+    },
+    TipoPadrao {
+        codigo: "organico",
+        nomes: ["Orgânico", "Organic", "Orgánico"],
+        categoria: CategoriaResiduo::Organico,
+        aliases: &["organicos", "organic", "compostavel", "restos de comida"],
+        densidade_kg_m3: 500.0,
+        densidade_aterro_kg_m3: 900.0,
+    },
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CategoriaResiduo {
+    Reciclavel,
+    Organico,
+    Rejeito,
+    Perigoso,
+}
+
+impl CategoriaResiduo {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CategoriaResiduo::Reciclavel => "reciclavel",
+            CategoriaResiduo::Organico => "organico",
+            CategoriaResiduo::Rejeito => "rejeito",
+            CategoriaResiduo::Perigoso => "perigoso",
+        }
+    }
+}
+
+impl TryFrom<&str> for CategoriaResiduo {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "reciclavel" => Ok(CategoriaResiduo::Reciclavel),
+            "organico" => Ok(CategoriaResiduo::Organico),
+            "rejeito" => Ok(CategoriaResiduo::Rejeito),
+            "perigoso" => Ok(CategoriaResiduo::Perigoso),
+            _ => Err(format!("Categoria de resíduo desconhecida: '{}'", value)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NomesTipo {
+    #[serde(rename = "pt-BR")]
+    pub pt_br: String,
+    pub en: String,
+    pub es: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TipoResiduo {
+    pub codigo: String,
+    pub nomes: NomesTipo,
+    pub categoria: CategoriaResiduo,
+    /// Outras grafias aceitas; comparadas sem acentos e sem diferenciar
+    /// maiúsculas de minúsculas.
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// Unidade em que o material costuma ser registrado.
+    #[serde(default)]
+    pub unidade: UnidadeMedida,
+    /// Densidade do material solto (kg/m³), usada para converter litros,
+    /// m³ e sacos em kg. Sem ela, o tipo só aceita unidades de massa.
+    #[serde(default)]
+    pub densidade_kg_m3: Option<f64>,
+    /// Densidade do material compactado em aterro (kg/m³), usada para
+    /// estimar o volume de aterro poupado pela reciclagem.
+    #[serde(default)]
+    pub densidade_aterro_kg_m3: Option<f64>,
+}
+
+/// Coleta validada: tipo pelo código do catálogo, leitura como informada e
+/// a quantidade convertida para kg.
+#[derive(Debug, Clone)]
+pub struct ColetaNormalizada {
+    pub tipo: String,
+    pub leitura: Leitura,
+    pub quantidade_kg: f32,
+}
+
+/// Valor de `tipo` que não corresponde a nenhum código ou apelido.
+#[derive(Debug, Clone, Serialize)]
+pub struct ValorNaoMapeado {
+    pub valor: String,
+    pub ocorrencias: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RelatorioMigracao {
+    /// Sem gravar: só mostra o que seria alterado.
+    pub simulacao: bool,
+    pub total_coletas: usize,
+    pub ja_canonicas: usize,
+    pub convertidas: usize,
+    /// Conversões feitas, por valor original -> código.
+    pub mapeamentos: BTreeMap<String, String>,
+    pub nao_mapeadas: Vec<ValorNaoMapeado>,
+}
+
+/// Catálogo em memória para resolver vários valores de uma vez.
+#[derive(Debug, Clone, Default)]
+pub struct Catalogo {
+    tipos: Vec<TipoResiduo>,
+    /// Código e apelidos normalizados -> código.
+    por_nome: HashMap<String, String>,
+}
+
+impl Catalogo {
+    pub fn carregar(conn: &Connection) -> rusqlite::Result<Self> {
+        Ok(Self::de_tipos(listar_tipos(conn)?))
+    }
+
+    /// Catálogo inicial, para uso fora do servidor (módulo de terminal).
+    pub fn padrao() -> Self {
+        let tipos = TIPOS_PADRAO
+            .iter()
+            .map(|tipo| TipoResiduo {
+                codigo: tipo.codigo.to_string(),
+                nomes: NomesTipo {
+                    pt_br: tipo.nomes[0].to_string(),
+                    en: tipo.nomes[1].to_string(),
+                    es: tipo.nomes[2].to_string(),
+                },
+                categoria: tipo.categoria,
+                aliases: tipo.aliases.iter().map(|a| a.to_string()).collect(),
+                unidade: UnidadeMedida::default(),
+                densidade_kg_m3: Some(tipo.densidade_kg_m3),
+                densidade_aterro_kg_m3: Some(tipo.densidade_aterro_kg_m3),
+            })
+            .collect();
+        Self::de_tipos(tipos)
+    }
+
+    fn de_tipos(tipos: Vec<TipoResiduo>) -> Self {
+        let mut por_nome = HashMap::new();
+        for tipo in &tipos {
+            for nome in std::iter::once(&tipo.codigo).chain(&tipo.aliases) {
+                por_nome.insert(normalizar_material(nome), tipo.codigo.clone());
+            }
+        }
+        Catalogo { tipos, por_nome }
+    }
+
+    pub fn codigos(&self) -> impl Iterator<Item = &str> {
+        self.tipos.iter().map(|t| t.codigo.as_str())
+    }
+
+    /// Código canônico de um tipo, apelido ou grafia com acento.
+    pub fn resolver(&self, valor: &str) -> Option<&str> {
+        self.por_nome.get(&normalizar_material(valor)).map(|c| c.as_str())
+    }
+
+    /// Chave de agrupamento: o código do catálogo ou, para valores fora
+    /// dele, o nome normalizado (minúsculas, sem acento).
+    pub fn chave(&self, valor: &str) -> String {
+        self.resolver(valor)
+            .map(str::to_string)
+            .unwrap_or_else(|| normalizar_material(valor))
+    }
+
+    pub fn tipo(&self, codigo: &str) -> Option<&TipoResiduo> {
+        self.tipos.iter().find(|t| t.codigo == codigo)
+    }
+
+    /// Volume em aterro (m³) de `quantidade_kg` de um tipo; `None` se o tipo
+    /// estiver fora do catálogo ou sem densidade de aterro.
+    pub fn volume_aterro_m3(&self, tipo: &str, quantidade_kg: f64) -> Option<f64> {
+        let densidade = self.tipo(self.resolver(tipo)?)?.densidade_aterro_kg_m3?;
+        Some(quantidade_kg / densidade)
+    }
+
+    fn kg_por_unidade(&self, codigo: &str, unidade: UnidadeMedida) -> Option<f64> {
+        unidade.kg_por_unidade(self.tipo(codigo).and_then(|t| t.densidade_kg_m3))
+    }
+
+    /// Resolve o tipo e converte a leitura para kg.
+    pub fn normalizar(&self, tipo: &str, leitura: Leitura) -> Result<ColetaNormalizada, String> {
+        if !leitura.quantidade.is_finite() || leitura.quantidade <= 0.0 {
+            return Err("Quantidade inválida: deve ser maior que 0".to_string());
+        }
+        let tipo = self.exigir(tipo)?;
+        let kg_por_unidade = self.kg_por_unidade(&tipo, leitura.unidade).ok_or_else(|| {
+            format!(
+                "Tipo '{}' sem densidade cadastrada: registre em kg, g ou t, ou cadastre a densidade",
+                tipo
+            )
+        })?;
+        Ok(ColetaNormalizada {
+            quantidade_kg: (leitura.quantidade as f64 * kg_por_unidade) as f32,
+            tipo,
+            leitura,
+        })
+    }
+
+    /// kg de um tipo expressos em outra unidade; `None` se o volume não
+    /// puder ser calculado.
+    pub fn converter(&self, tipo: &str, quantidade_kg: f32, unidade: UnidadeMedida) -> Option<Leitura> {
+        let codigo = self.resolver(tipo)?;
+        let kg_por_unidade = self.kg_por_unidade(codigo, unidade)?;
+        Some(Leitura {
+            quantidade: (quantidade_kg as f64 / kg_por_unidade) as f32,
+            unidade,
+        })
+    }
+
+    /// Como `resolver`, com a mensagem de erro devolvida pela API.
+    pub fn exigir(&self, valor: &str) -> Result<String, String> {
+        self.resolver(valor).map(str::to_string).ok_or_else(|| {
+            let codigos: Vec<&str> = self.codigos().collect();
+            format!(
+                "Tipo de resíduo desconhecido: '{}' (tipos aceitos: {})",
+                valor.trim(),
+                codigos.join(", ")
+            )
+        })
+    }
+}
+
+fn carregar_catalogo(conn: &Connection) -> Result<Catalogo, String> {
+    Catalogo::carregar(conn).map_err(|e| format!("Erro ao acessar banco de dados: {}", e))
+}
+
+/// Código canônico de `valor`, consultando o catálogo gravado.
+pub fn tipo_canonico(conn: &Connection, valor: &str) -> Result<String, String> {
+    carregar_catalogo(conn)?.exigir(valor)
+}
+
+/// Valida uma coleta contra o catálogo gravado (ver `Catalogo::normalizar`).
+pub fn normalizar_coleta(conn: &Connection, tipo: &str, leitura: Leitura) -> Result<ColetaNormalizada, String> {
+    carregar_catalogo(conn)?.normalizar(tipo, leitura)
+}
+
+/// Troca o `tipo` de cada coleta pelo código canônico. Valores sem
+/// correspondência ficam como estão e vão para o relatório.
+pub fn migrar_dataset(dataset: &mut Dataset, catalogo: &Catalogo, simulacao: bool) -> RelatorioMigracao {
+    let mut relatorio = RelatorioMigracao {
+        simulacao,
+        total_coletas: dataset.entries.len(),
+        ja_canonicas: 0,
+        convertidas: 0,
+        mapeamentos: BTreeMap::new(),
+        nao_mapeadas: Vec::new(),
+    };
+    let mut nao_mapeadas: BTreeMap<String, usize> = BTreeMap::new();
+
+    for entrada in &mut dataset.entries {
+        match catalogo.resolver(&entrada.tipo) {
+            Some(codigo) if codigo == entrada.tipo => relatorio.ja_canonicas += 1,
+            Some(codigo) => {
+                relatorio.convertidas += 1;
+                relatorio.mapeamentos.insert(entrada.tipo.clone(), codigo.to_string());
+                if !simulacao {
+                    entrada.tipo = codigo.to_string();
+                }
+            }
+            None => *nao_mapeadas.entry(entrada.tipo.clone()).or_default() += 1,
+        }
+    }
+
+    relatorio.nao_mapeadas = nao_mapeadas
+        .into_iter()
+        .map(|(valor, ocorrencias)| ValorNaoMapeado { valor, ocorrencias })
+        .collect();
+    relatorio
+}
+
+// ========== BANCO DE DADOS ==========
+
+fn ler_tipo(row: &Row) -> rusqlite::Result<TipoResiduo> {
+    let categoria: String = row.get(4)?;
+    let categoria = CategoriaResiduo::try_from(categoria.as_str()).map_err(|_| {
+        rusqlite::Error::InvalidColumnType(4, "categoria".to_string(), rusqlite::types::Type::Text)
+    })?;
+    let unidade: String = row.get(5)?;
+    let unidade = UnidadeMedida::try_from(unidade.as_str()).map_err(|_| {
+        rusqlite::Error::InvalidColumnType(5, "unidade".to_string(), rusqlite::types::Type::Text)
+    })?;
+    Ok(TipoResiduo {
+        codigo: row.get(0)?,
+        nomes: NomesTipo {
+            pt_br: row.get(1)?,
+            en: row.get(2)?,
+            es: row.get(3)?,
+        },
+        categoria,
+        aliases: Vec::new(),
+        unidade,
+        densidade_kg_m3: row.get(6)?,
+        densidade_aterro_kg_m3: row.get(7)?,
+    })
+}
+
+/// Grava o catálogo inicial se a tabela estiver vazia. Em catálogos
+/// criados antes das colunas de densidade, preenche as dos tipos iniciais.
+pub fn garantir_tipos_padrao(conn: &Connection) -> rusqlite::Result<()> {
+    let vazia: bool = conn.query_row("SELECT NOT EXISTS (SELECT 1 FROM tipos_residuo)", [], |row| row.get(0))?;
+    if !vazia {
+        for tipo in TIPOS_PADRAO {
+            conn.execute(
+                "UPDATE tipos_residuo SET
+                    densidade_kg_m3 = COALESCE(densidade_kg_m3, ?2),
+                    densidade_aterro_kg_m3 = COALESCE(densidade_aterro_kg_m3, ?3)
+                 WHERE codigo = ?1",
+                params![tipo.codigo, tipo.densidade_kg_m3, tipo.densidade_aterro_kg_m3],
+            )?;
+        }
+        return Ok(());
+    }
+    for tipo in TIPOS_PADRAO {
+        let [pt_br, en, es] = tipo.nomes;
+        conn.execute(
+            "INSERT INTO tipos_residuo
+                (codigo, nome_pt_br, nome_en, nome_es, categoria, unidade, densidade_kg_m3, densidade_aterro_kg_m3)
+             VALUES (?1, ?2, ?3, ?4, ?5, 'kg', ?6, ?7)",
+            params![
+                tipo.codigo,
+                pt_br,
+                en,
+                es,
+                tipo.categoria.as_str(),
+                tipo.densidade_kg_m3,
+                tipo.densidade_aterro_kg_m3
+            ],
+        )?;
+        for alias in tipo.aliases {
+            conn.execute(
+                "INSERT INTO tipos_residuo_aliases (alias, codigo) VALUES (?1, ?2)",
+                params![alias, tipo.codigo],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+pub fn listar_tipos(conn: &Connection) -> rusqlite::Result<Vec<TipoResiduo>> {
+    let mut stmt = conn.prepare(
+        "SELECT codigo, nome_pt_br, nome_en, nome_es, categoria, unidade, densidade_kg_m3, densidade_aterro_kg_m3
+         FROM tipos_residuo
+         ORDER BY codigo",
+    )?;
+    let mut tipos = stmt.query_map([], ler_tipo)?.collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut stmt = conn.prepare("SELECT codigo, alias FROM tipos_residuo_aliases ORDER BY alias")?;
+    let aliases = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    for (codigo, alias) in aliases {
+        if let Some(tipo) = tipos.iter_mut().find(|t| t.codigo == codigo) {
+            tipo.aliases.push(alias);
+        }
+    }
+    Ok(tipos)
+}
+
+/// Cadastra ou substitui um tipo, com todos os seus apelidos. Um apelido não
+/// pode pertencer a outro tipo nem coincidir com outro código.
+pub fn salvar_tipo(conn: &Connection, tipo: &TipoResiduo) -> Result<TipoResiduo, String> {
+    let codigo = normalizar_material(&tipo.codigo);
+    if codigo.is_empty() || !codigo.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(format!(
+            "Código inválido: '{}' (use letras, números e '_')",
+            tipo.codigo
+        ));
+    }
+    let nomes = [&tipo.nomes.pt_br, &tipo.nomes.en, &tipo.nomes.es];
+    if nomes.iter().any(|n| n.trim().is_empty()) {
+        return Err("Informe o nome do tipo em pt-BR, en e es".to_string());
+    }
+    for densidade in [tipo.densidade_kg_m3, tipo.densidade_aterro_kg_m3].into_iter().flatten() {
+        if !densidade.is_finite() || densidade <= 0.0 {
+            return Err(format!("Densidade inválida: {} (esperado número maior que zero)", densidade));
+        }
+    }
+    if tipo.unidade.e_volume() && tipo.densidade_kg_m3.is_none() {
+        return Err(format!(
+            "A unidade '{}' é de volume e exige densidade_kg_m3",
+            tipo.unidade.as_str()
+        ));
+    }
+
+    let mut aliases: Vec<String> = tipo
+        .aliases
+        .iter()
+        .map(|a| normalizar_material(a))
+        .filter(|a| !a.is_empty() && *a != codigo)
+        .collect();
+    aliases.sort();
+    aliases.dedup();
+
+    let catalogo = Catalogo::carregar(conn).map_err(|e| format!("Erro ao acessar banco de dados: {}", e))?;
+    for nome in std::iter::once(&codigo).chain(&aliases) {
+        if let Some(outro) = catalogo.resolver(nome).filter(|outro| *outro != codigo) {
+            return Err(format!("'{}' já identifica o tipo '{}'", nome, outro));
+        }
+    }
+
+    let erro_banco = |e: rusqlite::Error| format!("Erro ao salvar tipo de resíduo: {}", e);
+    let tx = conn.unchecked_transaction().map_err(erro_banco)?;
+    tx.execute(
+        "INSERT INTO tipos_residuo
+            (codigo, nome_pt_br, nome_en, nome_es, categoria, unidade, densidade_kg_m3, densidade_aterro_kg_m3)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+         ON CONFLICT(codigo) DO UPDATE SET
+            nome_pt_br = excluded.nome_pt_br,
+            nome_en = excluded.nome_en,
+            nome_es = excluded.nome_es,
+            categoria = excluded.categoria,
+            unidade = excluded.unidade,
+            densidade_kg_m3 = excluded.densidade_kg_m3,
+            densidade_aterro_kg_m3 = excluded.densidade_aterro_kg_m3",
+        params![
+            codigo,
+            tipo.nomes.pt_br.trim(),
+            tipo.nomes.en.trim(),
+            tipo.nomes.es.trim(),
+            tipo.categoria.as_str(),
+            tipo.unidade.as_str(),
+            tipo.densidade_kg_m3,
+            tipo.densidade_aterro_kg_m3,
+        ],
+    )
+    .map_err(erro_banco)?;
+    tx.execute("DELETE FROM tipos_residuo_aliases WHERE codigo = ?1", [&codigo])
+        .map_err(erro_banco)?;
+    for alias in &aliases {
+        tx.execute(
+            "INSERT INTO tipos_residuo_aliases (alias, codigo) VALUES (?1, ?2)",
+            params![alias, codigo],
+        )
+        .map_err(erro_banco)?;
+    }
+    tx.commit().map_err(erro_banco)?;
+
+    Ok(TipoResiduo {
+        codigo,
+        nomes: NomesTipo {
+            pt_br: tipo.nomes.pt_br.trim().to_string(),
+            en: tipo.nomes.en.trim().to_string(),
+            es: tipo.nomes.es.trim().to_string(),
+        },
+        categoria: tipo.categoria,
+        aliases,
+        unidade: tipo.unidade,
+        densidade_kg_m3: tipo.densidade_kg_m3,
+        densidade_aterro_kg_m3: tipo.densidade_aterro_kg_m3,
+    })
+}
@@ -0,0 +1,99 @@
+// src/unidades.rs
+// Unidades em que uma coleta pode ser registrada. Massa converte direto para
+// kg; volume (litro, m³, saco) precisa da densidade do material, cadastrada
+// no catálogo de tipos de resíduo.
+
+use serde::{Deserialize, Serialize};
+
+/// Capacidade do saco de coleta padrão.
+const LITROS_POR_SACO: f64 = 100.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnidadeMedida {
+    #[default]
+    Kg,
+    G,
+    T,
+    L,
+    M3,
+    Saco,
+}
+
+impl UnidadeMedida {
+    pub const TODAS: [UnidadeMedida; 6] = [
+        UnidadeMedida::Kg,
+        UnidadeMedida::G,
+        UnidadeMedida::T,
+        UnidadeMedida::L,
+        UnidadeMedida::M3,
+        UnidadeMedida::Saco,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UnidadeMedida::Kg => "kg",
+            UnidadeMedida::G => "g",
+            UnidadeMedida::T => "t",
+            UnidadeMedida::L => "l",
+            UnidadeMedida::M3 => "m3",
+            UnidadeMedida::Saco => "saco",
+        }
+    }
+
+    /// kg em uma unidade, para unidades de massa.
+    fn massa_kg(&self) -> Option<f64> {
+        match self {
+            UnidadeMedida::Kg => Some(1.0),
+            UnidadeMedida::G => Some(0.001),
+            UnidadeMedida::T => Some(1000.0),
+            UnidadeMedida::L | UnidadeMedida::M3 | UnidadeMedida::Saco => None,
+        }
+    }
+
+    /// m³ em uma unidade, para unidades de volume.
+    fn volume_m3(&self) -> Option<f64> {
+        match self {
+            UnidadeMedida::L => Some(0.001),
+            UnidadeMedida::M3 => Some(1.0),
+            UnidadeMedida::Saco => Some(LITROS_POR_SACO / 1000.0),
+            UnidadeMedida::Kg | UnidadeMedida::G | UnidadeMedida::T => None,
+        }
+    }
+
+    pub fn e_volume(&self) -> bool {
+        self.volume_m3().is_some()
+    }
+
+    /// kg em uma unidade; `None` para volume sem densidade conhecida.
+    pub fn kg_por_unidade(&self, densidade_kg_m3: Option<f64>) -> Option<f64> {
+        self.massa_kg()
+            .or_else(|| Some(self.volume_m3()? * densidade_kg_m3?))
+    }
+}
+
+impl TryFrom<&str> for UnidadeMedida {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let valor = value.trim().to_lowercase();
+        UnidadeMedida::TODAS
+            .into_iter()
+            .find(|u| u.as_str() == valor)
+            .ok_or_else(|| {
+                let aceitas: Vec<&str> = UnidadeMedida::TODAS.iter().map(|u| u.as_str()).collect();
+                format!(
+                    "Unidade desconhecida: '{}' (unidades aceitas: {})",
+                    value.trim(),
+                    aceitas.join(", ")
+                )
+            })
+    }
+}
+
+/// Quantidade como foi informada, antes da conversão para kg.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Leitura {
+    pub quantidade: f32,
+    pub unidade: UnidadeMedida,
+}